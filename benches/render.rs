@@ -0,0 +1,78 @@
+// Times the phases of rfocxt on a generated crate with a few thousand items. Like the fixtures,
+// it needs the toolchain of rust-toolchain.toml with rustc-dev: `cargo bench --bench render`.
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const STRUCTS: usize = 2000;
+const FNS: usize = 1000;
+
+// Each struct has an impl with one method and holds the one at half its index, so that contexts
+// reach a few levels of relative types. Each fn takes two of the structs and calls their methods.
+fn generate_crate(crate_path: &Path) {
+    let mut lib = String::new();
+    for i in 0..STRUCTS {
+        let parent = match i {
+            0 => "()".to_string(),
+            _ => format!("Option<Box<S{}>>", i / 2),
+        };
+        writeln!(
+            lib,
+            "pub struct S{i} {{\n    pub n: u32,\n    pub parent: {parent},\n}}\n\n\
+             impl S{i} {{\n    pub fn get(&self) -> u32 {{\n        self.n\n    }}\n}}\n"
+        )
+        .unwrap();
+    }
+    for i in 0..FNS {
+        let (a, b) = (i % STRUCTS, (i * 7 + 3) % STRUCTS);
+        writeln!(
+            lib,
+            "pub fn f{i}(a: &S{a}, b: &S{b}) -> u32 {{\n    a.get() + b.get()\n}}\n"
+        )
+        .unwrap();
+    }
+    fs::create_dir_all(crate_path.join("src")).unwrap();
+    fs::write(
+        crate_path.join("Cargo.toml"),
+        "[package]\nname = \"generated\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    fs::write(
+        crate_path.join("rust-toolchain.toml"),
+        include_str!("../rust-toolchain.toml"),
+    )
+    .unwrap();
+    fs::write(crate_path.join("src/lib.rs"), lib).unwrap();
+}
+
+fn main() {
+    let bench_path = std::env::temp_dir().join(format!("rfocxt-bench-{}", std::process::id()));
+    let crate_path = bench_path.join("generated");
+    let out_dir = bench_path.join("out");
+    generate_crate(&crate_path);
+    let status = Command::new(env!("CARGO_BIN_EXE_rfocxt"))
+        .arg("--crate")
+        .arg(&crate_path)
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .args(["--profile", "--quiet"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "rfocxt failed on the generated crate");
+    let profile: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("profile.json")).unwrap()).unwrap();
+    fs::remove_dir_all(&bench_path).unwrap();
+    println!(
+        "{} structs with an impl, {} fns, {} contexts",
+        STRUCTS, FNS, profile["rendered_contexts"]
+    );
+    for (phase, micros) in profile["phase_micros"].as_object().unwrap() {
+        println!(
+            "{:>20}: {:.2}s",
+            phase,
+            micros.as_u64().unwrap() as f64 / 1e6
+        );
+    }
+}
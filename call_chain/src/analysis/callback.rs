@@ -1,21 +1,44 @@
 use rustc_driver::Compilation;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{LocalDefId, LOCAL_CRATE};
+use rustc_hir::intravisit::Visitor;
+use rustc_hir::{ConstArgKind, FnRetTy, GenericParamKind, Node};
 use rustc_interface::interface;
 use rustc_interface::Queries;
+use rustc_middle::mir::BasicBlockData;
+use rustc_middle::mir::CastKind;
+use rustc_middle::mir::LocalDecl;
 use rustc_middle::mir::Operand;
+use rustc_middle::mir::Rvalue;
+use rustc_middle::mir::StatementKind;
 use rustc_middle::mir::TerminatorKind;
+use rustc_middle::ty::adjustment::PointerCoercion;
+use rustc_middle::ty::AliasTy;
+use rustc_middle::ty::AliasTyKind;
+use rustc_middle::ty::Clause;
+use rustc_middle::ty::ClauseKind;
+use rustc_middle::ty::ExistentialPredicate;
 use rustc_middle::ty::GenericArgKind;
+use rustc_middle::ty::Instance;
+use rustc_middle::ty::ParamEnv;
 use rustc_middle::ty::Ty;
 use rustc_middle::ty::TyCtxt;
 use rustc_middle::ty::TyKind;
-use std::collections::HashSet;
+use rustc_middle::ty::TypeVisitableExt;
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+use rustc_span::Span;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs::create_dir_all;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
+use std::time::Instant;
 
-use super::exporter::CallsAndTypes;
+use super::exporter::{CallsAndTypes, CrateAnalysis, CrateVersion, Profile};
 use super::hirvisitor::HirVisitor;
 use super::hirvisitor::VisitorData;
+use super::pathvisitor::{MacroPathVisitor, MethodCallVisitor, NestedItemVisitor, PathVisitor};
+use super::sourceinfo::SourceInfo;
 
 pub struct MirCheckerCallbacks {
     pub source_name: String,
@@ -93,6 +116,16 @@ fn collect_subtypes<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>, result: &mut HashSet<
             }
         }
 
+        // Fn items and projections name types only in their generic args, e.g. the `Foo` of
+        // `Vec::<Foo>::new` passed as a value or of `<Foo as Trait>::Output`.
+        TyKind::FnDef(_, args) | TyKind::Alias(_, AliasTy { args, .. }) => {
+            for arg in args.iter() {
+                if let GenericArgKind::Type(sub_ty) = arg.unpack() {
+                    collect_subtypes(sub_ty, tcx, result);
+                }
+            }
+        }
+
         // 处理数组类型 [T; N]
         TyKind::Array(sub_ty, _) => {
             collect_subtypes(*sub_ty, tcx, result);
@@ -114,13 +147,428 @@ fn collect_subtypes<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>, result: &mut HashSet<
                 collect_subtypes(sub_ty, tcx, result);
             }
         }
+
+        // Trait objects `dyn Trait<A, Item = B>`, their traits are read in collect_dyn_traits
+        TyKind::Dynamic(predicates, _, _) => {
+            for predicate in predicates.iter() {
+                let (args, term) = match predicate.skip_binder() {
+                    ExistentialPredicate::Trait(trait_ref) => (trait_ref.args, None),
+                    ExistentialPredicate::Projection(projection) => {
+                        (projection.args, projection.term.as_type())
+                    }
+                    ExistentialPredicate::AutoTrait(_) => continue,
+                };
+                for arg in args.iter() {
+                    if let GenericArgKind::Type(sub_ty) = arg.unpack() {
+                        collect_subtypes(sub_ty, tcx, result);
+                    }
+                }
+                if let Some(term_ty) = term {
+                    collect_subtypes(term_ty, tcx, result);
+                }
+            }
+        }
+        // Closures and the coroutines of async fns and blocks, through their captures and
+        // signatures, so that a type only seen in what an awaited block returns isn't lost.
+        TyKind::Closure(_, args) => {
+            let closure_args = args.as_closure();
+            collect_subtypes(closure_args.tupled_upvars_ty(), tcx, result);
+            for sub_ty in closure_args.sig().skip_binder().inputs_and_output.iter() {
+                collect_subtypes(sub_ty, tcx, result);
+            }
+        }
+        TyKind::Coroutine(_, args) => {
+            let coroutine_args = args.as_coroutine();
+            collect_subtypes(coroutine_args.tupled_upvars_ty(), tcx, result);
+            collect_subtypes(coroutine_args.yield_ty(), tcx, result);
+            collect_subtypes(coroutine_args.return_ty(), tcx, result);
+        }
+        TyKind::CoroutineClosure(_, args) => {
+            collect_subtypes(args.as_coroutine_closure().tupled_upvars_ty(), tcx, result);
+        }
+        TyKind::FnPtr(sig) => {
+            for sub_ty in sig.skip_binder().inputs_and_output.iter() {
+                collect_subtypes(sub_ty, tcx, result);
+            }
+        }
         // 处理其他类型...
         _ => {}
     }
 }
 
+// Const generic defaults are evaluated away in MIR types (`Buf<4>`), so the
+// consts they reference have to be read back from the ADT's HIR generics.
+fn collect_const_param_defaults<'tcx>(
+    ty: Ty<'tcx>,
+    tcx: TyCtxt<'tcx>,
+    result: &mut HashSet<String>,
+) {
+    if let TyKind::Adt(adt, _) = ty.kind() {
+        if let Some(local_def_id) = adt.did().as_local() {
+            if let Some(generics) = tcx.hir().get_generics(local_def_id) {
+                let mut visitor = PathVisitor::new(tcx);
+                for param in generics.params.iter() {
+                    if let GenericParamKind::Const {
+                        default: Some(const_arg),
+                        ..
+                    } = param.kind
+                    {
+                        if let ConstArgKind::Anon(anon_const) = const_arg.kind {
+                            visitor.visit_body_id(anon_const.body);
+                        }
+                    }
+                }
+                result.extend(visitor.move_paths());
+            }
+        }
+    }
+}
+
+// Array lengths and const generic args are evaluated in MIR types as well (`[u8; 16]`,
+// `Grid<2>`), so the consts they name are read back from the HIR types of the fn's
+// signature and of the fields of the local ADTs it uses.
+fn collect_signature_consts(def_id: LocalDefId, tcx: TyCtxt<'_>, result: &mut HashSet<String>) {
+    if let Some(fn_decl) = tcx.hir_node_by_def_id(def_id).fn_decl() {
+        let mut visitor = PathVisitor::new(tcx);
+        for input in fn_decl.inputs.iter() {
+            visitor.visit_ty(input);
+        }
+        if let FnRetTy::Return(output) = fn_decl.output {
+            visitor.visit_ty(output);
+        }
+        result.extend(visitor.move_paths());
+    }
+}
+
+fn collect_field_consts<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>, result: &mut HashSet<String>) {
+    if let TyKind::Adt(adt, _) = ty.kind() {
+        if adt.did().is_local() {
+            let mut visitor = PathVisitor::new(tcx);
+            for field in adt.all_fields() {
+                if let Some(field_def_id) = field.did.as_local() {
+                    if let Node::Field(field_def) = tcx.hir_node_by_def_id(field_def_id) {
+                        visitor.visit_ty(field_def.ty);
+                    }
+                }
+            }
+            result.extend(visitor.move_paths());
+        }
+    }
+}
+
+fn collect_clause_types<'tcx>(
+    clauses: impl Iterator<Item = Clause<'tcx>>,
+    tcx: TyCtxt<'tcx>,
+    tys: &mut HashSet<Ty<'tcx>>,
+    types: &mut HashSet<String>,
+) {
+    for clause in clauses {
+        match clause.kind().skip_binder() {
+            ClauseKind::Trait(trait_predicate) => {
+                types.insert(tcx.def_path_str(trait_predicate.def_id()));
+                for arg in trait_predicate.trait_ref.args.iter().skip(1) {
+                    if let GenericArgKind::Type(sub_ty) = arg.unpack() {
+                        collect_subtypes(sub_ty, tcx, tys);
+                    }
+                }
+            }
+            ClauseKind::Projection(projection_predicate) => {
+                if let Some(term_ty) = projection_predicate.term.as_type() {
+                    collect_subtypes(term_ty, tcx, tys);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_dyn_traits<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>, result: &mut HashSet<String>) {
+    if let TyKind::Dynamic(predicates, _, _) = ty.kind() {
+        for predicate in predicates.iter() {
+            match predicate.skip_binder() {
+                // `trait Sub: Super` needs its supertraits, e.g. for upcasting to `dyn Super`
+                ExistentialPredicate::Trait(trait_ref) => {
+                    for trait_def_id in tcx.supertrait_def_ids(trait_ref.def_id) {
+                        result.insert(tcx.def_path_str(trait_def_id));
+                    }
+                }
+                ExistentialPredicate::AutoTrait(def_id) => {
+                    result.insert(tcx.def_path_str(def_id));
+                }
+                ExistentialPredicate::Projection(_) => {}
+            }
+        }
+    }
+}
+
+// Unsize coercions, e.g. `&Foo` to `&dyn Trait` or trait upcasting `&dyn Sub` to
+// `&dyn Super`, bring in the trait objects they target.
+fn collect_unsize_targets<'tcx>(
+    basic_blocks: &[BasicBlockData<'tcx>],
+    tcx: TyCtxt<'tcx>,
+    tys: &mut HashSet<Ty<'tcx>>,
+) {
+    for basic_block in basic_blocks.iter() {
+        for statement in basic_block.statements.iter() {
+            if let StatementKind::Assign(assign) = &statement.kind {
+                if let Rvalue::Cast(
+                    CastKind::PointerCoercion(PointerCoercion::Unsize) | CastKind::DynStar,
+                    _,
+                    target_ty,
+                ) = &assign.1
+                {
+                    collect_subtypes(*target_ty, tcx, tys);
+                }
+            }
+        }
+    }
+}
+
+// Consts referenced from a body, e.g. `Self::BUFFER_SIZE`, are evaluated into
+// MIR constants, so their paths are read back from the HIR body instead.
+fn collect_body_paths(def_id: LocalDefId, tcx: TyCtxt<'_>, result: &mut HashSet<String>) {
+    if let Some(body) = tcx.hir().maybe_body_owned_by(def_id) {
+        let mut visitor = PathVisitor::with_typeck_results(tcx, tcx.typeck(def_id));
+        visitor.visit_body_id(body.id());
+        result.extend(visitor.move_paths());
+    }
+}
+
+// Method calls are also read from the typeck results of the body, with trait methods
+// resolved to the impl of the receiver, and field accesses name the struct they belong to.
+fn collect_method_calls(
+    def_id: LocalDefId,
+    tcx: TyCtxt<'_>,
+    calls: &mut HashSet<String>,
+    types: &mut HashSet<String>,
+) {
+    if let Some(body) = tcx.hir().maybe_body_owned_by(def_id) {
+        let mut visitor = MethodCallVisitor::new(tcx, tcx.param_env(def_id), tcx.typeck(def_id));
+        visitor.visit_body_id(body.id());
+        let (method_calls, paths) = visitor.move_result();
+        calls.extend(method_calls);
+        types.extend(paths);
+    }
+}
+
+// The arguments of a macro call only reach MIR in whatever form the macro expands them to,
+// e.g. a type passed to `size_of::<$t>()` ends up in the generic args of the call.
+fn collect_macro_paths(def_id: LocalDefId, tcx: TyCtxt<'_>, result: &mut HashSet<String>) {
+    if let Some(body) = tcx.hir().maybe_body_owned_by(def_id) {
+        let mut visitor = MacroPathVisitor::new(tcx);
+        visitor.visit_body_id(body.id());
+        result.extend(visitor.move_paths());
+    }
+}
+
+// A struct, enum or union declared in a fn body, or in a fn nested in it, is only in the fn's
+// MIR where it is used, the types of its fields not at all.
+fn collect_nested_adts<'tcx>(def_id: LocalDefId, tcx: TyCtxt<'tcx>, tys: &mut HashSet<Ty<'tcx>>) {
+    let Some(body) = tcx.hir().maybe_body_owned_by(def_id) else {
+        return;
+    };
+    let mut visitor = NestedItemVisitor::new(tcx);
+    visitor.visit_body_id(body.id());
+    let (items, _) = visitor.move_result();
+    for item_id in items {
+        if let DefKind::Struct | DefKind::Enum | DefKind::Union = tcx.def_kind(item_id) {
+            collect_subtypes(tcx.type_of(item_id).instantiate_identity(), tcx, tys);
+            for variant in tcx.adt_def(item_id).variants().iter() {
+                for field in variant.fields.iter() {
+                    collect_subtypes(tcx.type_of(field.did).instantiate_identity(), tcx, tys);
+                }
+            }
+        }
+    }
+}
+
+// `impl Trait` only shows up as an opaque type (or an RPITIT projection) and, in argument
+// position, as a synthetic param, so the traits and types in its bounds are read from the
+// opaque's item bounds and from the fn's predicates.
+fn collect_bounds<'tcx>(
+    def_id: LocalDefId,
+    tcx: TyCtxt<'tcx>,
+    tys: &mut HashSet<Ty<'tcx>>,
+    types: &mut HashSet<String>,
+) {
+    let alias_tys: Vec<Ty<'tcx>> = tys
+        .iter()
+        .filter(|ty| match ty.kind() {
+            TyKind::Alias(AliasTyKind::Opaque, _) => true,
+            TyKind::Alias(AliasTyKind::Projection, alias_ty) => {
+                tcx.is_impl_trait_in_trait(alias_ty.def_id)
+            }
+            _ => false,
+        })
+        .copied()
+        .collect();
+    for alias_ty in alias_tys {
+        if let TyKind::Alias(_, alias_ty) = alias_ty.kind() {
+            let clauses = tcx
+                .explicit_item_bounds(alias_ty.def_id)
+                .iter_instantiated_copied(tcx, alias_ty.args)
+                .map(|(clause, _)| clause);
+            collect_clause_types(clauses, tcx, tys, types);
+        }
+    }
+    let predicates = tcx.predicates_of(def_id).instantiate_identity(tcx);
+    collect_clause_types(predicates.predicates.into_iter(), tcx, tys, types);
+}
+
+// Macros are expanded away before MIR, but the spans of the code they expand to still
+// record the macros they came from.
+fn collect_macros<'tcx>(
+    basic_blocks: &[BasicBlockData<'tcx>],
+    local_decls: &[LocalDecl<'tcx>],
+    tcx: TyCtxt<'tcx>,
+    result: &mut HashSet<String>,
+) {
+    let mut insert_macros = |span: Span| {
+        for expn_data in span.macro_backtrace() {
+            if let (ExpnKind::Macro(MacroKind::Bang, _), Some(macro_def_id)) =
+                (&expn_data.kind, expn_data.macro_def_id)
+            {
+                result.insert(tcx.def_path_str(macro_def_id));
+            }
+        }
+    };
+    for basic_block in basic_blocks.iter() {
+        for statement in basic_block.statements.iter() {
+            insert_macros(statement.source_info.span);
+        }
+        insert_macros(basic_block.terminator().source_info.span);
+    }
+    for local_decl in local_decls.iter() {
+        insert_macros(local_decl.source_info.span);
+    }
+}
+
+// The callee is printed before its args, as in `_0 = foo(move _1) -> ...`.
+// A call printed any other way is skipped instead of aborting the compilation.
+fn get_call_string(kind_string: &str) -> Option<&str> {
+    kind_string
+        .splitn(3, ' ')
+        .nth(2)
+        .and_then(|kind_string| kind_string.split_once('('))
+        .map(|(call_string, _)| call_string)
+}
+
+fn resolve_opaque() -> bool {
+    std::env::var_os("CALL_CHAIN_RESOLVE_OPAQUE").is_some()
+}
+
+// Set by rfocxt's --trace.
+fn trace() -> bool {
+    std::env::var_os("CALL_CHAIN_TRACE").is_some()
+}
+
+// Spans in a macro expansion are traced to the macro call in the body.
+fn insert_trace(
+    trace: &mut BTreeMap<String, BTreeSet<SourceInfo>>,
+    name: String,
+    span: Span,
+    tcx: TyCtxt<'_>,
+) {
+    trace.entry(name).or_default().insert(SourceInfo::from_span(
+        span.source_callsite(),
+        tcx.sess.source_map(),
+    ));
+}
+
+// `collect_subtypes`, also tracing every type found to the span it was found at.
+fn collect_traced_subtypes<'tcx>(
+    ty: Ty<'tcx>,
+    span: Span,
+    tcx: TyCtxt<'tcx>,
+    tys: &mut HashSet<Ty<'tcx>>,
+    trace: &mut Option<BTreeMap<String, BTreeSet<SourceInfo>>>,
+) {
+    match trace {
+        Some(trace) => {
+            let mut sub_tys: HashSet<Ty<'tcx>> = HashSet::new();
+            collect_subtypes(ty, tcx, &mut sub_tys);
+            for sub_ty in sub_tys.iter() {
+                insert_trace(trace, sub_ty.to_string(), span, tcx);
+            }
+            tys.extend(sub_tys);
+        }
+        None => collect_subtypes(ty, tcx, tys),
+    }
+}
+
+fn profile() -> bool {
+    std::env::var_os("CALL_CHAIN_PROFILE").is_some()
+}
+
+// Set by rfocxt, for outputs outside of the crate directory.
+fn output_path() -> PathBuf {
+    std::env::var_os("RFOCXT_OUT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("./rfocxt"))
+}
+
+// The analysis of a bin next to a lib, a test or an example goes to a directory of its own, see
+// cargo-call-chain.
+fn target_output_path() -> PathBuf {
+    match std::env::var_os("CALL_CHAIN_OUTPUT_SUBDIR") {
+        Some(output_subdir) => output_path().join(output_subdir),
+        None => output_path(),
+    }
+}
+
+fn crate_version() -> Option<String> {
+    std::env::var("MIR_CHECKER_TOP_CRATE_VERSION").ok()
+}
+
+// The opaque types of `impl Trait` returns are left unrevealed in MIR, so their
+// hidden types are normalized out with a reveal-all param env.
+fn collect_hidden_types<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    result: &mut HashSet<Ty<'tcx>>,
+) {
+    let opaque_tys: Vec<Ty<'tcx>> = result
+        .iter()
+        .filter(|ty| matches!(ty.kind(), TyKind::Alias(AliasTyKind::Opaque, _)))
+        .copied()
+        .collect();
+    for opaque_ty in opaque_tys {
+        if let Ok(hidden_ty) = tcx.try_normalize_erasing_regions(param_env, opaque_ty) {
+            collect_subtypes(hidden_ty, tcx, result);
+        }
+    }
+}
+
+// Trait methods called on an opaque type are resolved to the methods of the hidden type's impl.
+fn collect_opaque_calls<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    basic_blocks: &[BasicBlockData<'tcx>],
+    result: &mut HashSet<String>,
+) {
+    for basic_block in basic_blocks.iter() {
+        if let TerminatorKind::Call { func, .. } = &basic_block.terminator().kind {
+            if let Some((def_id, args)) = func.const_fn_def() {
+                if !args.has_opaque_types() {
+                    continue;
+                }
+                let Ok(args) = tcx.try_normalize_erasing_regions(param_env, args) else {
+                    continue;
+                };
+                if let Ok(Some(instance)) = Instance::try_resolve(tcx, param_env, def_id, args) {
+                    if instance.def_id() != def_id {
+                        result.insert(tcx.def_path_str_with_args(instance.def_id(), instance.args));
+                    }
+                }
+            }
+        }
+    }
+}
+
 impl MirCheckerCallbacks {
-    fn run_analysis<'tcx, 'compiler>(&mut self, tcx: TyCtxt<'tcx>) {
+    /// Analyzes every fn (and, with `CALL_CHAIN_FOCAL_CONSTS`, every const and static) of the
+    /// local crate and returns the result instead of writing it, e.g. to check it in a driver.
+    pub fn analyze_crate<'tcx>(&self, tcx: TyCtxt<'tcx>) -> CrateAnalysis {
         // let hir_krate = tcx.hir();
         // for id in hir_krate.items() {
         //     let item = id.owner_id.def_id;
@@ -148,13 +596,23 @@ impl MirCheckerCallbacks {
         //     }
         // }
         let hir_map = tcx.hir();
+        let hir_visit_start = Instant::now();
         let mut visitor = HirVisitor::new(tcx, hir_map);
         // hir_map.visit_all_item_likes_in_crate(&mut visitor);
         hir_map.walk_toplevel_module(&mut visitor);
+        let skipped = visitor.take_skipped();
         let result = visitor.move_result();
+        let crate_name = tcx.crate_name(LOCAL_CRATE).to_string();
+        let mut analysis = CrateAnalysis::new(Profile::new(
+            &crate_name,
+            hir_visit_start.elapsed().as_micros(),
+        ));
+        analysis.skipped = skipped;
         for data in result {
+            let item_start = Instant::now();
             let VisitorData {
                 id,
+                def_id,
                 fn_name,
                 doc,
                 has_ret,
@@ -168,7 +626,11 @@ impl MirCheckerCallbacks {
             let mut calls: HashSet<String> = HashSet::new();
             let mut tys: HashSet<Ty<'tcx>> = HashSet::new();
             let mut types: HashSet<String> = HashSet::new();
+            let mut external: HashSet<String> = HashSet::new();
+            let mut trace: Option<BTreeMap<String, BTreeSet<SourceInfo>>> =
+                trace().then(BTreeMap::new);
             for basic_block in basic_blocks.iter() {
+                let span = basic_block.terminator().source_info.span;
                 if let TerminatorKind::Call {
                     func,
                     args,
@@ -181,17 +643,29 @@ impl MirCheckerCallbacks {
                 {
                     // println!("{:#?}", &basic_block.terminator().kind);
                     let kind_string = format!("{:#?}", &basic_block.terminator().kind);
-                    let kind_strings: Vec<&str> = kind_string.splitn(3, ' ').into_iter().collect();
-                    let kind_string = kind_strings[2];
-                    let call_string = &kind_string[..kind_string.find("(").unwrap()];
-                    // println!("提取的函数调用：{}", call_string);
-                    calls.insert(call_string.to_string());
+                    if let Some(call_string) = get_call_string(&kind_string) {
+                        // println!("提取的函数调用：{}", call_string);
+                        calls.insert(call_string.to_string());
+                        if let Some(trace) = trace.as_mut() {
+                            insert_trace(trace, call_string.to_string(), span, tcx);
+                        }
+                    }
+                    if let Some((callee_def_id, callee_args)) = func.const_fn_def() {
+                        if !callee_def_id.is_local() {
+                            external.insert(tcx.def_path_str(callee_def_id));
+                        }
+                        // Turbofish and other explicit type args, e.g. `MyType` in
+                        // `size_of::<MyType>()`, may be in no local's type.
+                        for callee_ty in callee_args.types() {
+                            collect_traced_subtypes(callee_ty, span, tcx, &mut tys, &mut trace);
+                        }
+                    }
 
                     for arg in args.iter() {
                         if let Operand::Constant(constant) = &arg.node {
                             // let arg_type = constant.ty().peel_refs().to_string();
                             // types.insert(arg_type);
-                            collect_subtypes(constant.ty(), tcx, &mut tys);
+                            collect_traced_subtypes(constant.ty(), span, tcx, &mut tys, &mut trace);
                         }
                     }
                 }
@@ -205,10 +679,45 @@ impl MirCheckerCallbacks {
                 // let decl_type = local_decl.ty.peel_refs().to_string();
                 // println!("{:#?}", local_decl.ty.peel_refs().to_string());
                 // types.insert(decl_type);
-                collect_subtypes(local_decl.ty, tcx, &mut tys);
+                collect_traced_subtypes(
+                    local_decl.ty,
+                    local_decl.source_info.span,
+                    tcx,
+                    &mut tys,
+                    &mut trace,
+                );
+            }
+            collect_bounds(def_id, tcx, &mut tys, &mut types);
+            collect_macros(&basic_blocks, &local_decls, tcx, &mut types);
+            collect_body_paths(def_id, tcx, &mut types);
+            collect_signature_consts(def_id, tcx, &mut types);
+            collect_method_calls(def_id, tcx, &mut calls, &mut types);
+            collect_macro_paths(def_id, tcx, &mut types);
+            collect_unsize_targets(&basic_blocks, tcx, &mut tys);
+            collect_nested_adts(def_id, tcx, &mut tys);
+            if resolve_opaque() {
+                let param_env = tcx.param_env_reveal_all_normalized(def_id);
+                collect_hidden_types(tcx, param_env, &mut tys);
+                collect_opaque_calls(tcx, param_env, &basic_blocks, &mut calls);
             }
             for ty in tys.iter() {
                 types.insert(ty.to_string());
+                collect_const_param_defaults(*ty, tcx, &mut types);
+                collect_field_consts(*ty, tcx, &mut types);
+                collect_dyn_traits(*ty, tcx, &mut types);
+                // fn items passed as values, e.g. `opt.map(Foo::from)`
+                if let TyKind::FnDef(def_id, args) = ty.kind() {
+                    calls.insert(tcx.def_path_str_with_args(*def_id, args));
+                }
+                match ty.kind() {
+                    TyKind::Adt(adt, _) if !adt.did().is_local() => {
+                        external.insert(tcx.def_path_str(adt.did()));
+                    }
+                    TyKind::FnDef(def_id, _) if !def_id.is_local() => {
+                        external.insert(tcx.def_path_str(*def_id));
+                    }
+                    _ => {}
+                }
             }
             // println!("Types:");
             // for a_type in types.iter() {
@@ -234,20 +743,106 @@ impl MirCheckerCallbacks {
             // for new_call in new_calls.iter() {
             //     calls.insert(new_call.clone());
             // }
-            let calls_and_types = CallsAndTypes::new(&mod_info.name, &calls, &types);
-            let directory_path = "./rfocxt/callsandtypes";
-            create_dir_all(&directory_path).unwrap();
-            let file_path = PathBuf::from(&directory_path).join(format!("{}.json", fn_name));
+            let exported = tcx.effective_visibilities(()).is_exported(def_id);
+            let calls_and_types =
+                CallsAndTypes::new(&mod_info.name, &calls, &types, exported, &external);
+            analysis
+                .calls_and_types
+                .insert(fn_name.clone(), calls_and_types);
+            if let Some(trace) = trace {
+                analysis.traces.insert(fn_name.clone(), trace);
+            }
+            analysis
+                .basic_blocks
+                .insert(fn_name, format!("{:#?}\n{:#?}", basic_blocks, local_decls));
+            analysis.profile.insert_item(
+                tcx.def_kind(def_id).descr(def_id.to_def_id()),
+                item_start.elapsed().as_micros(),
+            );
+        }
+        analysis
+    }
+
+    fn run_analysis<'tcx, 'compiler>(&mut self, tcx: TyCtxt<'tcx>) {
+        let analysis = self.analyze_crate(tcx);
+        let crate_name = tcx.crate_name(LOCAL_CRATE).to_string();
+        let output_path = output_path();
+        let target_output_path = target_output_path();
+        let directory_path = target_output_path.join("callsandtypes");
+        create_dir_all(&directory_path).unwrap();
+        for (fn_name, calls_and_types) in analysis.calls_and_types.iter() {
+            let file_path = directory_path.join(format!("{}.json", fn_name));
             let mut file = File::create(&file_path).unwrap();
-            file.write_all(serde_json::to_string(&calls_and_types).unwrap().as_bytes())
+            file.write_all(serde_json::to_string(calls_and_types).unwrap().as_bytes())
                 .unwrap();
-
-            let directory_path = "./rfocxt/basic_blocks";
+        }
+        let directory_path = target_output_path.join("basic_blocks");
+        create_dir_all(&directory_path).unwrap();
+        for (fn_name, basic_blocks) in analysis.basic_blocks.iter() {
+            let file_path = directory_path.join(format!("{}.txt", fn_name));
+            let mut file = File::create(&file_path).unwrap();
+            file.write_all(basic_blocks.as_bytes()).unwrap();
+        }
+        // Read by rfocxt, to tell why a fn got no context.
+        if !analysis.skipped.is_empty() {
+            let directory_path = target_output_path.join("skipped");
+            create_dir_all(&directory_path).unwrap();
+            for (fn_name, err) in analysis.skipped.iter() {
+                let file_path = directory_path.join(format!("{}.json", fn_name));
+                let mut file = File::create(&file_path).unwrap();
+                file.write_all(serde_json::to_string(err).unwrap().as_bytes())
+                    .unwrap();
+            }
+        }
+        if !analysis.traces.is_empty() {
+            let directory_path = target_output_path.join("trace");
             create_dir_all(&directory_path).unwrap();
-            let file_path = PathBuf::from(&directory_path).join(format!("{}.txt", fn_name));
+            for (fn_name, trace) in analysis.traces.iter() {
+                let file_path = directory_path.join(format!("{}.json", fn_name));
+                let mut file = File::create(&file_path).unwrap();
+                file.write_all(serde_json::to_string(trace).unwrap().as_bytes())
+                    .unwrap();
+            }
+        }
+        if profile() {
+            let directory_path = output_path.join("profile");
+            create_dir_all(&directory_path).unwrap();
+            // lib, bin and test targets share the crate name, but not the process.
+            let file_path =
+                directory_path.join(format!("{}.{}.json", crate_name, std::process::id()));
             let mut file = File::create(&file_path).unwrap();
-            file.write_all(format!("{:#?}\n{:#?}", basic_blocks, local_decls).as_bytes())
+            file.write_all(serde_json::to_string(&analysis.profile).unwrap().as_bytes())
                 .unwrap();
         }
+        if let Some(version) = crate_version() {
+            create_dir_all(&output_path).unwrap();
+            let file_path = output_path.join("version.json");
+            let mut file = File::create(&file_path).unwrap();
+            file.write_all(
+                serde_json::to_string(&CrateVersion::new(&crate_name, &version))
+                    .unwrap()
+                    .as_bytes(),
+            )
+            .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_string_is_cut_before_the_args() {
+        assert_eq!(
+            get_call_string("_0 = foo::bar(move _1) -> [return: bb1, unwind: bb2]"),
+            Some("foo::bar")
+        );
+        assert_eq!(
+            get_call_string("_3 = <Vec<u8> as Clone>::clone(copy _4) -> bb1"),
+            Some("<Vec<u8> as Clone>::clone")
+        );
+        assert_eq!(get_call_string("_0 = foo"), None);
+        assert_eq!(get_call_string("_0"), None);
     }
 }
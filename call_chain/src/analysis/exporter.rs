@@ -1,6 +1,15 @@
 use super::sourceinfo::SourceInfo;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+// A bin of a package with a lib is a crate of the same name, so its analysis and its
+// contexts go to this directory in the output directory instead of next to the lib's.
+pub const BIN_OUTPUT_DIR: &str = "bin";
+// Integration tests and examples are crates of their own, whose analysis and contexts go to a
+// directory named after the target in these, so a target named like the package can't
+// overwrite the lib's.
+pub const TEST_OUTPUT_DIR: &str = "test";
+pub const EXAMPLE_OUTPUT_DIR: &str = "example";
 
 #[derive(Debug, Clone, Serialize)]
 pub struct BrData {
@@ -182,10 +191,22 @@ pub struct CallsAndTypes {
     pub mod_name: String,
     pub calls: Vec<String>,
     pub types: Vec<String>,
+    // Reachable from outside the crate, through public modules or re-exports.
+    #[serde(default)]
+    pub exported: bool,
+    // Fns and types of other crates, which rfocxt can't take the source of.
+    #[serde(default)]
+    pub external: Vec<String>,
 }
 
 impl CallsAndTypes {
-    pub fn new(mod_name: &String, calls: &HashSet<String>, types: &HashSet<String>) -> Self {
+    pub fn new(
+        mod_name: &String,
+        calls: &HashSet<String>,
+        types: &HashSet<String>,
+        exported: bool,
+        external: &HashSet<String>,
+    ) -> Self {
         let mut calls_vec: Vec<String> = Vec::new();
         for call in calls.iter() {
             calls_vec.push(call.clone());
@@ -198,6 +219,79 @@ impl CallsAndTypes {
             mod_name: mod_name.clone(),
             calls: calls_vec,
             types: types_vec,
+            exported,
+            external: external.iter().cloned().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ItemProfile {
+    pub count: usize,
+    pub micros: u128,
+}
+
+/// Time spent by call_chain on one compiled crate, written when profiling is on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub crate_name: String,
+    pub hir_visit_micros: u128,
+    pub items: HashMap<String, ItemProfile>,
+}
+
+impl Profile {
+    pub fn new(crate_name: &str, hir_visit_micros: u128) -> Self {
+        Profile {
+            crate_name: crate_name.to_string(),
+            hir_visit_micros,
+            items: HashMap::new(),
+        }
+    }
+
+    pub fn insert_item(&mut self, item_kind: &str, micros: u128) {
+        let item_profile = self.items.entry(item_kind.to_string()).or_default();
+        item_profile.count += 1;
+        item_profile.micros += micros;
+    }
+}
+
+/// The version of the analyzed crate, passed down by `cargo call-chain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateVersion {
+    pub crate_name: String,
+    pub version: String,
+}
+
+impl CrateVersion {
+    pub fn new(crate_name: &str, version: &str) -> Self {
+        CrateVersion {
+            crate_name: crate_name.to_string(),
+            version: version.to_string(),
+        }
+    }
+}
+
+/// The analysis of a crate as `cargo call-chain` writes it, by the name of each item: its calls
+/// and types, the MIR they were read from and, with `CALL_CHAIN_TRACE`, where in the source each
+/// call and type was found.
+#[derive(Debug, Clone)]
+pub struct CrateAnalysis {
+    pub calls_and_types: BTreeMap<String, CallsAndTypes>,
+    pub basic_blocks: BTreeMap<String, String>,
+    pub traces: BTreeMap<String, BTreeMap<String, BTreeSet<SourceInfo>>>,
+    // The items left out because their source isn't valid code, with the parse error.
+    pub skipped: BTreeMap<String, String>,
+    pub profile: Profile,
+}
+
+impl CrateAnalysis {
+    pub fn new(profile: Profile) -> Self {
+        CrateAnalysis {
+            calls_and_types: BTreeMap::new(),
+            basic_blocks: BTreeMap::new(),
+            traces: BTreeMap::new(),
+            skipped: BTreeMap::new(),
+            profile,
         }
     }
 }
@@ -1,24 +1,38 @@
 use rustc_ast::token::CommentKind;
 use rustc_ast::AttrKind;
-use rustc_hir::def_id::CRATE_DEF_ID;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{LocalDefId, CRATE_DEF_ID};
 use rustc_hir::intravisit::{self, Visitor};
 use rustc_hir::{self, BodyId, FnDecl};
 use rustc_middle::hir::map::Map;
 use rustc_middle::hir::nested_filter;
-use rustc_middle::mir::{BasicBlockData, LocalDecl};
+use rustc_middle::mir::{AggregateKind, BasicBlockData, LocalDecl, Rvalue, StatementKind};
 use rustc_middle::ty::TyCtxt;
-use rustc_span::symbol::sym;
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+use rustc_span::symbol::{kw, sym};
+use std::collections::BTreeMap;
 use syn::parse_str;
 
 use super::exporter::ModInfo;
+use super::pathvisitor::NestedItemVisitor;
 use super::sourceinfo::SourceInfo;
 
-fn is_valid_code(code: &str) -> bool {
-    parse_str::<syn::Item>(code).is_ok()
+// rfocxt can't use a fn whose source syn doesn't parse, e.g. one generated by a macro_rules
+// macro, whose span is in the macro.
+fn check_valid_code(code: &str) -> Result<(), String> {
+    parse_str::<syn::Item>(code)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+// Set by rfocxt's --focal-consts.
+fn focal_consts() -> bool {
+    std::env::var_os("CALL_CHAIN_FOCAL_CONSTS").is_some()
 }
 
 pub struct VisitorData<'tcx> {
     pub id: String,
+    pub def_id: rustc_hir::def_id::LocalDefId,
     pub fn_name: String,
     pub doc: String,
     pub has_ret: bool,
@@ -34,6 +48,7 @@ pub struct HirVisitor<'tcx> {
     hir_map: Map<'tcx>,
     mod_infos: Vec<ModInfo>,
     result: Vec<VisitorData<'tcx>>,
+    skipped: BTreeMap<String, String>,
 }
 
 impl<'tcx> HirVisitor<'tcx> {
@@ -43,6 +58,7 @@ impl<'tcx> HirVisitor<'tcx> {
             hir_map,
             mod_infos: Vec::new(),
             result: Vec::new(),
+            skipped: BTreeMap::new(),
         }
     }
 
@@ -50,6 +66,133 @@ impl<'tcx> HirVisitor<'tcx> {
         self.result
     }
 
+    /// The fns (and consts or statics) left out because their source isn't valid code, by
+    /// name, with the parse error.
+    pub fn take_skipped(&mut self) -> BTreeMap<String, String> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    // Const-evaluating a body, e.g. a const used as an array length, steals its mir_built, so
+    // a const, static or const block is read from mir_for_ctfe, which still has the calls of the
+    // initializer. It steals the mir_built of the const fns it calls too, and borrow-checking a
+    // const the ones of its closures and nested fns, which then fall back to their optimized_mir.
+    fn extend_with_mir(
+        &self,
+        id: LocalDefId,
+        basic_blocks: &mut Vec<BasicBlockData<'tcx>>,
+        local_decls: &mut Vec<LocalDecl<'tcx>>,
+    ) {
+        if matches!(
+            self.tcx.def_kind(id),
+            DefKind::Const | DefKind::Static { .. } | DefKind::InlineConst
+        ) {
+            let mir = self.tcx.mir_for_ctfe(id);
+            basic_blocks.extend(mir.basic_blocks.raw.iter().cloned());
+            local_decls.extend(mir.local_decls.raw.iter().cloned());
+        } else if self.tcx.mir_built(id).is_stolen() {
+            let mir = self.tcx.optimized_mir(id);
+            basic_blocks.extend(mir.basic_blocks.raw.iter().cloned());
+            local_decls.extend(mir.local_decls.raw.iter().cloned());
+        } else {
+            let mir = self.tcx.mir_built(id).borrow();
+            basic_blocks.extend(mir.basic_blocks.raw.iter().cloned());
+            local_decls.extend(mir.local_decls.raw.iter().cloned());
+        }
+    }
+
+    // Closure bodies are separate MIR bodies, so their calls and locals are
+    // merged into the enclosing fn. Nested closures are picked up as the blocks grow.
+    // The same goes for async bodies, including the ones attribute macros like
+    // `#[tokio::main]` move a fn's original body into.
+    fn collect_closure_bodies(
+        &self,
+        basic_blocks: &mut Vec<BasicBlockData<'tcx>>,
+        local_decls: &mut Vec<LocalDecl<'tcx>>,
+    ) {
+        let mut index = 0;
+        while index < basic_blocks.len() {
+            let mut closure_ids = Vec::new();
+            for statement in basic_blocks[index].statements.iter() {
+                if let StatementKind::Assign(assign) = &statement.kind {
+                    if let Rvalue::Aggregate(aggregate_kind, _) = &assign.1 {
+                        if let AggregateKind::Closure(closure_id, _)
+                        | AggregateKind::Coroutine(closure_id, _)
+                        | AggregateKind::CoroutineClosure(closure_id, _) = **aggregate_kind
+                        {
+                            if let Some(closure_id) = closure_id.as_local() {
+                                closure_ids.push(closure_id);
+                            }
+                        }
+                    }
+                }
+            }
+            for closure_id in closure_ids {
+                self.extend_with_mir(closure_id, basic_blocks, local_decls);
+            }
+            index += 1;
+        }
+    }
+
+    // The fns, consts and statics declared in a body and its inline const blocks have MIR bodies
+    // of their own, which are merged like closure bodies, before them as they may have closures.
+    // A nested fn is written as part of the fn, so only what it uses is needed.
+    fn collect_nested_bodies(
+        &self,
+        body_id: BodyId,
+        basic_blocks: &mut Vec<BasicBlockData<'tcx>>,
+        local_decls: &mut Vec<LocalDecl<'tcx>>,
+    ) {
+        let mut visitor = NestedItemVisitor::new(self.tcx);
+        visitor.visit_body_id(body_id);
+        let (items, const_blocks) = visitor.move_result();
+        let body_items = items.into_iter().filter(|item_id| {
+            matches!(
+                self.tcx.def_kind(*item_id),
+                DefKind::Fn | DefKind::Const | DefKind::Static { .. }
+            )
+        });
+        for body_owner in body_items.chain(const_blocks) {
+            self.extend_with_mir(body_owner, basic_blocks, local_decls);
+        }
+    }
+
+    // A free const or static is analyzed like a fn, from the MIR of its initializer.
+    fn visit_const_body(&mut self, item: &'tcx rustc_hir::Item<'tcx>) {
+        let id = item.owner_id.def_id;
+        let id_str = format!("{:?}", id);
+        let def_id = id.to_def_id();
+        let mut fn_name = self.tcx.crate_name(def_id.krate).to_string();
+        fn_name.push_str(&self.tcx.def_path(def_id).to_string_no_crate_verbose());
+        debug!("Visiting const or static: {}, name: {}", id_str, fn_name);
+
+        let fn_source = SourceInfo::from_span(item.span, self.tcx.sess.source_map());
+        if let Err(err) = check_valid_code(&fn_source.get_string()) {
+            warn!("Skip because it is not valid code: {}", err);
+            self.skipped.insert(fn_name, err);
+            return;
+        }
+        let visible = self.is_accessible_from_crate(def_id, &fn_source);
+
+        let mut basic_blocks = Vec::new();
+        let mut local_decls = Vec::new();
+        self.extend_with_mir(id, &mut basic_blocks, &mut local_decls);
+        self.collect_closure_bodies(&mut basic_blocks, &mut local_decls);
+
+        let data = VisitorData {
+            id: id_str,
+            def_id: id,
+            fn_name,
+            doc: String::new(),
+            has_ret: true,
+            mod_info: self.mod_infos.last().unwrap().clone(),
+            visible,
+            fn_source,
+            basic_blocks,
+            local_decls,
+        };
+        self.result.push(data);
+    }
+
     fn is_accessible_from_crate(
         &self,
         def_id: rustc_hir::def_id::DefId,
@@ -77,16 +220,25 @@ impl<'tcx> Visitor<'tcx> for HirVisitor<'tcx> {
         let mod_source = SourceInfo::from_span(_s, self.tcx.sess.source_map());
         let def_id = n.owner.to_def_id();
         let module_name = self.tcx.def_path_str(def_id);
-        info!("Visiting module: {}, {:?}", module_name, mod_source);
+        debug!("Visiting module: {}, {:?}", module_name, mod_source);
         self.mod_infos.push(ModInfo {
             name: module_name.clone(),
             loc: mod_source,
         });
         intravisit::walk_mod(self, m, n);
-        info!("Leaving module: {}", module_name);
+        debug!("Leaving module: {}", module_name);
         self.mod_infos.pop();
     }
 
+    fn visit_item(&mut self, item: &'tcx rustc_hir::Item<'tcx>) -> Self::Result {
+        if let rustc_hir::ItemKind::Const(..) | rustc_hir::ItemKind::Static(..) = item.kind {
+            if focal_consts() && item.ident.name != kw::Underscore {
+                self.visit_const_body(item);
+            }
+        }
+        intravisit::walk_item(self, item);
+    }
+
     fn visit_fn(
         &mut self,
         _fk: intravisit::FnKind<'tcx>,
@@ -99,7 +251,7 @@ impl<'tcx> Visitor<'tcx> for HirVisitor<'tcx> {
         let def_id = id.to_def_id();
         let mut fn_name = self.tcx.crate_name(def_id.krate).to_string();
         fn_name.push_str(&self.tcx.def_path(def_id).to_string_no_crate_verbose());
-        info!("Visiting function: {}, name: {}", id_str, fn_name);
+        debug!("Visiting function: {}, name: {}", id_str, fn_name);
 
         let mod_info = self.mod_infos.last().unwrap();
         let has_ret = matches!(_fd.output, rustc_hir::FnRetTy::Return(_));
@@ -116,12 +268,26 @@ impl<'tcx> Visitor<'tcx> for HirVisitor<'tcx> {
             }
         }
 
+        // Fns rewritten by attribute macros (e.g. `#[tokio::main]`) have no source of
+        // their own, so they are located by the attribute and kept.
+        let is_attr_expansion = span
+            .macro_backtrace()
+            .any(|expn_data| matches!(expn_data.kind, ExpnKind::Macro(MacroKind::Attr, _)));
+        let fn_span = if is_attr_expansion {
+            span.source_callsite()
+        } else {
+            span
+        };
+
         // Skip functions that are not valid code
-        let fn_source = SourceInfo::from_span(span, self.tcx.sess.source_map());
+        let fn_source = SourceInfo::from_span(fn_span, self.tcx.sess.source_map());
         let code = fn_source.get_string();
-        if !is_valid_code(&code) {
-            warn!("Skip because it is not valid code");
-            return;
+        if !is_attr_expansion {
+            if let Err(err) = check_valid_code(&code) {
+                warn!("Skip because it is not valid code: {}", err);
+                self.skipped.insert(fn_name, err);
+                return;
+            }
         }
 
         // write function source code to file
@@ -132,8 +298,6 @@ impl<'tcx> Visitor<'tcx> for HirVisitor<'tcx> {
         // file.write_all(code.as_bytes()).unwrap();
 
         let hir = self.hir_map.body(b);
-        let mir = self.tcx.mir_built(id).borrow();
-
         // write HIR to file
         // let dir_path = format!("./rbrinfo/{}", id_str);
         // let file_path = format!("{}/hir.txt", dir_path);
@@ -173,16 +337,23 @@ impl<'tcx> Visitor<'tcx> for HirVisitor<'tcx> {
             }
         }
 
+        let mut basic_blocks = Vec::new();
+        let mut local_decls = Vec::new();
+        self.extend_with_mir(id, &mut basic_blocks, &mut local_decls);
+        self.collect_nested_bodies(b, &mut basic_blocks, &mut local_decls);
+        self.collect_closure_bodies(&mut basic_blocks, &mut local_decls);
+
         let data = VisitorData {
             id: id_str,
+            def_id: id,
             fn_name,
             doc,
             has_ret,
             mod_info: mod_info.clone(),
             visible,
             fn_source,
-            basic_blocks: mir.basic_blocks.raw.to_vec(),
-            local_decls: mir.local_decls.raw.to_vec(),
+            basic_blocks,
+            local_decls,
         };
 
         self.result.push(data);
@@ -0,0 +1,277 @@
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{BodyId, ConstBlock, Expr, ExprKind, HirId, ItemId, Path, QPath, Stmt};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::{Instance, ParamEnv, TyCtxt, TyKind, TypeckResults};
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+use rustc_span::Span;
+use std::collections::HashSet;
+
+/// Collects the def paths of consts and statics referenced inside HIR bodies,
+/// such as the anon const of a const generic default.
+pub struct PathVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    paths: HashSet<String>,
+}
+
+impl<'tcx> PathVisitor<'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+        PathVisitor {
+            tcx,
+            typeck_results: None,
+            paths: HashSet::new(),
+        }
+    }
+
+    /// Also resolves type-relative paths such as `Self::BUFFER_SIZE`, which
+    /// only the typeck results of the body owner know about.
+    pub fn with_typeck_results(
+        tcx: TyCtxt<'tcx>,
+        typeck_results: &'tcx TypeckResults<'tcx>,
+    ) -> Self {
+        PathVisitor {
+            tcx,
+            typeck_results: Some(typeck_results),
+            paths: HashSet::new(),
+        }
+    }
+
+    pub fn visit_body_id(&mut self, body_id: BodyId) {
+        let body = self.tcx.hir().body(body_id);
+        intravisit::walk_body(self, body);
+    }
+
+    pub fn move_paths(self) -> HashSet<String> {
+        self.paths
+    }
+
+    fn insert_res(&mut self, res: Res) {
+        if let Res::Def(DefKind::Const | DefKind::AssocConst | DefKind::Static { .. }, def_id) = res
+        {
+            self.paths.insert(self.tcx.def_path_str(def_id));
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for PathVisitor<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_qpath(&mut self, qpath: &'tcx QPath<'tcx>, id: HirId, _span: Span) -> Self::Result {
+        if let (QPath::TypeRelative(..), Some(typeck_results)) = (qpath, self.typeck_results) {
+            self.insert_res(typeck_results.qpath_res(qpath, id));
+        }
+        intravisit::walk_qpath(self, qpath, id);
+    }
+
+    fn visit_path(&mut self, path: &Path<'tcx>, _id: HirId) -> Self::Result {
+        self.insert_res(path.res);
+        intravisit::walk_path(self, path);
+    }
+}
+
+/// Collects the def paths of local types and traits named inside the expansions of
+/// function-like macros in a HIR body, such as the `$t` of `std::mem::size_of::<$t>()`,
+/// which MIR keeps at most as a generic arg of a call.
+pub struct MacroPathVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    expansion_depth: usize,
+    paths: HashSet<String>,
+}
+
+impl<'tcx> MacroPathVisitor<'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+        MacroPathVisitor {
+            tcx,
+            expansion_depth: 0,
+            paths: HashSet::new(),
+        }
+    }
+
+    pub fn visit_body_id(&mut self, body_id: BodyId) {
+        let body = self.tcx.hir().body(body_id);
+        intravisit::walk_body(self, body);
+    }
+
+    pub fn move_paths(self) -> HashSet<String> {
+        self.paths
+    }
+
+    // Desugarings such as `for` loops and `?` are expansions too, but not of a macro.
+    fn is_bang_expansion(span: Span) -> bool {
+        matches!(
+            span.ctxt().outer_expn_data().kind,
+            ExpnKind::Macro(MacroKind::Bang, _)
+        )
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for MacroPathVisitor<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_stmt(&mut self, stmt: &'tcx Stmt<'tcx>) -> Self::Result {
+        let is_expansion = Self::is_bang_expansion(stmt.span);
+        self.expansion_depth += is_expansion as usize;
+        intravisit::walk_stmt(self, stmt);
+        self.expansion_depth -= is_expansion as usize;
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) -> Self::Result {
+        let is_expansion = Self::is_bang_expansion(expr.span);
+        self.expansion_depth += is_expansion as usize;
+        intravisit::walk_expr(self, expr);
+        self.expansion_depth -= is_expansion as usize;
+    }
+
+    fn visit_path(&mut self, path: &Path<'tcx>, _id: HirId) -> Self::Result {
+        if self.expansion_depth > 0 {
+            if let Res::Def(
+                DefKind::Struct
+                | DefKind::Enum
+                | DefKind::Union
+                | DefKind::TyAlias
+                | DefKind::Trait,
+                def_id,
+            ) = path.res
+            {
+                if def_id.is_local() {
+                    self.paths.insert(self.tcx.def_path_str(def_id));
+                }
+            }
+        }
+        intravisit::walk_path(self, path);
+    }
+}
+
+/// Collects the methods called and the structs whose fields are accessed in a HIR
+/// body, resolving trait methods to the impl of the receiver where it is known.
+pub struct MethodCallVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    typeck_results: &'tcx TypeckResults<'tcx>,
+    calls: HashSet<String>,
+    paths: HashSet<String>,
+}
+
+impl<'tcx> MethodCallVisitor<'tcx> {
+    pub fn new(
+        tcx: TyCtxt<'tcx>,
+        param_env: ParamEnv<'tcx>,
+        typeck_results: &'tcx TypeckResults<'tcx>,
+    ) -> Self {
+        MethodCallVisitor {
+            tcx,
+            param_env,
+            typeck_results,
+            calls: HashSet::new(),
+            paths: HashSet::new(),
+        }
+    }
+
+    pub fn visit_body_id(&mut self, body_id: BodyId) {
+        let body = self.tcx.hir().body(body_id);
+        intravisit::walk_body(self, body);
+    }
+
+    pub fn move_result(self) -> (HashSet<String>, HashSet<String>) {
+        (self.calls, self.paths)
+    }
+
+    fn insert_method_call(&mut self, expr: &'tcx Expr<'tcx>) {
+        let Some(def_id) = self.typeck_results.type_dependent_def_id(expr.hir_id) else {
+            return;
+        };
+        let args = self.typeck_results.node_args(expr.hir_id);
+        let Ok(args) = self.tcx.try_normalize_erasing_regions(self.param_env, args) else {
+            return;
+        };
+        let (def_id, args) = match Instance::try_resolve(self.tcx, self.param_env, def_id, args) {
+            Ok(Some(instance)) => (instance.def_id(), instance.args),
+            _ => (def_id, args),
+        };
+        self.calls
+            .insert(self.tcx.def_path_str_with_args(def_id, args));
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for MethodCallVisitor<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) -> Self::Result {
+        match expr.kind {
+            ExprKind::MethodCall(..) => self.insert_method_call(expr),
+            ExprKind::Field(base, _) => {
+                let base_ty = self.typeck_results.expr_ty_adjusted(base).peel_refs();
+                if let TyKind::Adt(adt, _) = base_ty.kind() {
+                    if adt.did().is_local() {
+                        self.paths.insert(self.tcx.def_path_str(adt.did()));
+                    }
+                }
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
+
+/// Collects the items declared in a HIR body, such as `const X: Foo = ...;`, a local struct or
+/// a nested fn, and its inline `const { ... }` blocks, whose bodies are separate from the body's.
+/// The bodies of the nested items are searched as well.
+pub struct NestedItemVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    items: Vec<LocalDefId>,
+    const_blocks: Vec<LocalDefId>,
+}
+
+impl<'tcx> NestedItemVisitor<'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+        NestedItemVisitor {
+            tcx,
+            items: Vec::new(),
+            const_blocks: Vec::new(),
+        }
+    }
+
+    pub fn visit_body_id(&mut self, body_id: BodyId) {
+        let body = self.tcx.hir().body(body_id);
+        intravisit::walk_body(self, body);
+    }
+
+    pub fn move_result(self) -> (Vec<LocalDefId>, Vec<LocalDefId>) {
+        (self.items, self.const_blocks)
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for NestedItemVisitor<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_item(&mut self, id: ItemId) -> Self::Result {
+        let def_id = id.owner_id.def_id;
+        self.items.push(def_id);
+        if let Some(body) = self.tcx.hir().maybe_body_owned_by(def_id) {
+            intravisit::walk_body(self, body);
+        }
+    }
+
+    fn visit_inline_const(&mut self, constant: &'tcx ConstBlock) -> Self::Result {
+        self.const_blocks.push(constant.def_id);
+        intravisit::walk_inline_const(self, constant);
+    }
+}
@@ -57,9 +57,20 @@ fn main() {
             // Add this to support analyzing no_std libraries
             // rustc_args.push("-Clink-arg=-nostartfiles".to_owned());
 
-            // Disable unwind to simplify the CFG
-            rustc_args.push("-Cpanic=abort".to_owned());
+            // Disable unwind to simplify the CFG, unless the profile of the crate already sets
+            // a panic strategy (cargo passes it as `-C panic=...`) or CALL_CHAIN_KEEP_UNWIND is set
+            let has_panic_strategy = rustc_args
+                .iter()
+                .any(|e| e.starts_with("panic=") || e.starts_with("-Cpanic="));
+            if !has_panic_strategy && env::var_os("CALL_CHAIN_KEEP_UNWIND").is_none() {
+                rustc_args.push("-Cpanic=abort".to_owned());
+                // The test harness refuses to be built with panic=abort unless told to
+                if rustc_args.iter().any(|e| e == "--test") {
+                    rustc_args.push("-Zpanic_abort_tests".to_owned());
+                }
+            }
 
+            utils::init_logger();
             let mut callbacks = analysis::callback::MirCheckerCallbacks::new();
 
             let run_compiler = rustc_driver::RunCompiler::new(&rustc_args, &mut callbacks);
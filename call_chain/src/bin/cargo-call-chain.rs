@@ -1,8 +1,10 @@
+use call_chain::analysis::exporter::{BIN_OUTPUT_DIR, EXAMPLE_OUTPUT_DIR, TEST_OUTPUT_DIR};
 use call_chain::utils;
 use serde_json;
 use std::ffi::OsString;
-use std::path::Path;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
 
 const CARGO_RBRINFO_HELP: &str = r#"Call chain parser for rust program
 
@@ -57,8 +59,8 @@ fn get_arg_flag_value(name: &str) -> Option<String> {
     }
 }
 
-// Get the top level crate that we need to analyze
-fn current_crate() -> cargo_metadata::Package {
+// Get the top level crate that we need to analyze, and the target directory of its workspace
+fn current_crate() -> (cargo_metadata::Package, PathBuf) {
     // We need to get the manifest, and then the metadata, to enumerate targets.
 
     // Path to the `Cargo.toml` file
@@ -101,7 +103,7 @@ fn current_crate() -> cargo_metadata::Package {
         });
     let package = metadata.packages.remove(package_index);
 
-    package
+    (package, metadata.target_directory.into_std_path_buf())
 }
 
 fn call_chain() -> Command {
@@ -110,6 +112,43 @@ fn call_chain() -> Command {
     Command::new(path)
 }
 
+// Upper bound on how many targets are analyzed at the same time.
+// Read from `CALL_CHAIN_MAX_PARALLEL`, defaults to the number of available cores.
+fn max_parallel_targets() -> usize {
+    match std::env::var("CALL_CHAIN_MAX_PARALLEL") {
+        Ok(value) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|max_parallel| *max_parallel > 0)
+            .unwrap_or_else(|| {
+                show_error(format!("invalid CALL_CHAIN_MAX_PARALLEL value: {}", value))
+            }),
+        Err(_) => std::thread::available_parallelism()
+            .map(|cores| cores.get())
+            .unwrap_or(1),
+    }
+}
+
+// Waits for a spawned target and records how long it took. If it failed, the targets still
+// running are killed and waited for before exiting, so none of them is left behind.
+fn wait_target(
+    name: String,
+    start: Instant,
+    mut child: Child,
+    running: &mut Vec<(String, Instant, Child)>,
+    timings: &mut Vec<(String, Duration)>,
+) {
+    let exit_status = child.wait().expect("failed to wait for cargo?");
+    if !exit_status.success() {
+        for (_, _, mut child) in running.drain(..) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        std::process::exit(exit_status.code().unwrap_or(-1))
+    }
+    timings.push((name, start.elapsed()));
+}
+
 fn cargo() -> Command {
     Command::new(std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")))
 }
@@ -154,12 +193,25 @@ fn main() {
 // And set the following environment variables:
 // `RUSTC_WRAPPER` is set to `cargo-mir-checker` itself so the execution will come back to the second branch as described above
 // `MIR_CHECKER_ARGS` is set to the user-provided arguments for `mir-checker`
-// `MIR_CHEKCER_TOP_CRATE_NAME` is set to the name of the crate being analyzed
+// `MIR_CHEKCER_TOP_CRATE_NAME` is set to the name of the crate being analyzed, which is the
+// target's own name for `test` and `example` targets
+// `MIR_CHECKER_TOP_CRATE_VERSION` is set to its version, as resolved by cargo metadata
 // `MIR_CHECKER_VERBOSE` is set if `-v` is provided
+// `CALL_CHAIN_OUTPUT_SUBDIR` is set for a bin next to a lib, which would overwrite its outputs,
+// and for `test` and `example` targets
+// At most `CALL_CHAIN_MAX_PARALLEL` targets are analyzed at the same time. Cargo locks the
+// target directory for a build, so each of them then gets its own, named after the target.
 fn in_cargo_mir_checker() {
     let verbose = has_arg_flag("-v");
 
-    let current_crate = current_crate();
+    let (current_crate, target_directory) = current_crate();
+    let max_parallel = max_parallel_targets();
+    let mut running: Vec<(String, Instant, Child)> = Vec::new();
+    let mut timings: Vec<(String, Duration)> = Vec::new();
+    let has_lib = current_crate
+        .targets
+        .iter()
+        .any(|target| target.kind.iter().any(|kind| kind == "lib"));
 
     // Now run the command.
     for target in current_crate.targets.into_iter() {
@@ -172,17 +224,50 @@ fn in_cargo_mir_checker() {
         // Now we run `cargo rustc $FLAGS $ARGS`, giving the user the
         // chance to add additional arguments. `FLAGS` is set to identify
         // this target.  The user gets to control what gets actually passed to mir-checker.
+        let target_name = target.name.clone();
         let mut cmd = cargo();
+        if max_parallel > 1 {
+            cmd.env(
+                "CARGO_TARGET_DIR",
+                target_directory
+                    .join("call-chain")
+                    .join(format!("{}-{}", kind, target.name)),
+            );
+        }
         cmd.arg("check"); // using `check` may speed up the analysis than using `rustc`
-        match kind.as_str() {
+        let top_crate_name = match kind.as_str() {
             "bin" => {
-                cmd.arg("--bin").arg(target.name);
+                cmd.arg("--bin").arg(&target.name);
+                if has_lib {
+                    cmd.env("CALL_CHAIN_OUTPUT_SUBDIR", BIN_OUTPUT_DIR);
+                }
+                current_crate.name.clone()
             }
+            // The test profile checks the lib with cfg(test), so its unit tests are analyzed too.
             "lib" => {
-                cmd.arg("--lib");
+                cmd.arg("--lib").arg("--profile").arg("test");
+                current_crate.name.clone()
+            }
+            // Integration tests and examples are crates of their own, named after the target,
+            // and the lib they use is only a dependency of them.
+            "test" => {
+                cmd.arg("--test").arg(&target.name);
+                cmd.env(
+                    "CALL_CHAIN_OUTPUT_SUBDIR",
+                    Path::new(TEST_OUTPUT_DIR).join(&target.name),
+                );
+                target.name.clone()
+            }
+            "example" => {
+                cmd.arg("--example").arg(&target.name);
+                cmd.env(
+                    "CALL_CHAIN_OUTPUT_SUBDIR",
+                    Path::new(EXAMPLE_OUTPUT_DIR).join(&target.name),
+                );
+                target.name.clone()
             }
             _ => continue,
-        }
+        };
 
         // Add cargo args until first `--`.
         while let Some(arg) = args.next() {
@@ -205,7 +290,11 @@ fn in_cargo_mir_checker() {
             "MIR_CHECKER_ARGS",
             serde_json::to_string(&args_vec).expect("failed to serialize args"),
         );
-        cmd.env("MIR_CHECKER_TOP_CRATE_NAME", current_crate.name.clone());
+        cmd.env("MIR_CHECKER_TOP_CRATE_NAME", top_crate_name);
+        cmd.env(
+            "MIR_CHECKER_TOP_CRATE_VERSION",
+            current_crate.version.to_string(),
+        );
 
         // Replace the rustc executable through RUSTC_WRAPPER environment variable
         let path = std::env::current_exe().expect("current executable path invalid");
@@ -216,16 +305,22 @@ fn in_cargo_mir_checker() {
             eprintln!("+ {:?}", cmd);
         }
 
-        // Execute cmd
-        let exit_status = cmd
-            .spawn()
-            .expect("could not run cargo")
-            .wait()
-            .expect("failed to wait for cargo?");
-
-        if !exit_status.success() {
-            std::process::exit(exit_status.code().unwrap_or(-1))
+        // Execute cmd, waiting for the oldest target first if too many are running
+        if running.len() >= max_parallel {
+            let (name, start, child) = running.remove(0);
+            wait_target(name, start, child, &mut running, &mut timings);
         }
+        let child = cmd.spawn().expect("could not run cargo");
+        running.push((target_name, Instant::now(), child));
+    }
+    while !running.is_empty() {
+        let (name, start, child) = running.remove(0);
+        wait_target(name, start, child, &mut running, &mut timings);
+    }
+
+    eprintln!("Analyzed {} target(s):", timings.len());
+    for (name, duration) in timings.iter() {
+        eprintln!("    {}: {:.2?}", name, duration);
     }
 }
 
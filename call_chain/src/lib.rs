@@ -24,6 +24,7 @@ pub mod analysis {
     pub mod callback;
     pub mod exporter;
     pub mod hirvisitor;
+    pub mod pathvisitor;
     pub mod sourceinfo;
 }
 
@@ -1,3 +1,7 @@
+use std::env;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
 /// Copied from Miri
 /// Returns the "default sysroot" if no `--sysroot` flag is set.
 /// Should be a compile-time constant.
@@ -20,3 +24,34 @@ pub fn compile_time_sysroot() -> Option<String> {
             .to_owned(),
     })
 }
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Logs to stderr at the level in `CALL_CHAIN_LOG`, or else in `RUST_LOG`, like `info` or `off`.
+/// Only warnings are logged when neither is set, or set to something else, like a filter per module.
+pub fn init_logger() {
+    let level = env::var("CALL_CHAIN_LOG")
+        .or_else(|_| env::var("RUST_LOG"))
+        .ok()
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::Warn);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -1,37 +1,191 @@
 use std::{
-    cell::RefCell,
-    collections::{HashMap, HashSet},
+    cell::{Cell, OnceCell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs::{self, read_to_string, File},
+    hash::{DefaultHasher, Hash, Hasher},
     io::Write,
-    path::PathBuf,
-    process,
+    path::{Path, PathBuf},
+    process::{self, Command},
     rc::Rc,
 };
 
-use syn::parse_file;
+use call_chain::analysis::exporter::{
+    CallsAndTypes, CrateVersion, Profile, BIN_OUTPUT_DIR, EXAMPLE_OUTPUT_DIR, TEST_OUTPUT_DIR,
+};
+use quote::quote;
+use serde::{Deserialize, Serialize};
+use syn::{parse_file, Attribute, Type};
 use toml::Value;
 
 use super::{
     items_context::MyVisibility,
     mod_context::{ModContext, ModInfo, ModModInfo},
-    result::{FnData, StructData},
+    options::{ContextOptions, FocalKind},
+    result::{
+        ContextStats, FnData, FnType, IndexEntry, ReExport, SourceMapEntry, StructData, StructType,
+    },
+    syntax_context::{write_impl_context, write_trait_context},
 };
 
+/// The files of a collected context, each with its layer, which is 0 without --layered-output.
+pub type ContextFiles = Vec<(usize, syn::File)>;
+
 #[derive(Debug, Clone)]
 pub struct CrateContext {
     crate_name: String,
+    crate_version: Option<String>,
+    git_commit: Option<String>,
+    edition: Option<Value>,
+    dependencies: toml::Table,
     crate_path: PathBuf,
+    output_path: PathBuf,
     entry_file_paths: Vec<PathBuf>,
     main_mod_contexts: Vec<Rc<RefCell<ModContext>>>,
+    options: ContextOptions,
+    inner_attrs: Vec<Attribute>,
+    fanouts: RefCell<Vec<(String, usize)>>,
+    rendered_contexts: Cell<usize>,
+    missing_analyses: Cell<usize>,
+    skipped_focals: Cell<usize>,
+    truncated_contexts: Cell<usize>,
+    below_min_apps: Cell<usize>,
+    above_max_apps: Cell<usize>,
+    partial_contexts: Cell<usize>,
+    unresolved_contexts: Cell<usize>,
+    focal_matches: Cell<usize>,
+    type_aliases: OnceCell<Vec<(String, Type)>>,
+    mod_files: OnceCell<HashMap<String, PathBuf>>,
+    re_exports: OnceCell<Vec<ReExport>>,
+    imports: OnceCell<Vec<ReExport>>,
+    source_map: RefCell<BTreeMap<String, Vec<SourceMapEntry>>>,
+    name_map: RefCell<BTreeMap<String, String>>,
+    index: RefCell<BTreeMap<String, IndexEntry>>,
+    dependency_graph: RefCell<BTreeSet<(String, String)>>,
+    context_hashes: RefCell<BTreeMap<String, u64>>,
+    unchanged_contexts: Cell<usize>,
+    collected_contexts: RefCell<Option<BTreeMap<String, ContextFiles>>>,
+    context_stats: RefCell<Vec<ContextStats>>,
+}
+
+// The parsed mods of the crate, reused while none of their source files changed.
+#[derive(Serialize, Deserialize)]
+struct ContextCache {
+    rfocxt_version: String,
+    sources: BTreeMap<PathBuf, u64>,
+    // All inner attributes of the entry files, not only the `#![feature(...)]`s.
+    features: String,
+    mod_contexts: Vec<Rc<RefCell<ModContext>>>,
+}
+
+// The hash of every context written by an incremental run, by its file name.
+#[derive(Serialize, Deserialize)]
+struct IncrementalCache {
+    rfocxt_version: String,
+    contexts: BTreeMap<String, u64>,
+}
+
+const INCREMENTAL_CACHE: &str = ".rfocxt_cache";
+const TOP_FANOUTS: usize = 10;
+pub const SYSROOT_CRATES: [&str; 5] = ["std", "core", "alloc", "proc_macro", "test"];
+// The fill colour of the nodes of deps.dot by item kind, other kinds stay white.
+const GRAPH_COLORS: [(&str, &str); 5] = [
+    ("fn", "lightblue"),
+    ("struct", "palegreen"),
+    ("enum", "khaki"),
+    ("trait", "plum"),
+    ("impl", "lightsalmon"),
+];
+
+// Names like `<Foo as Bar>::baz` are quoted, so only quotes and backslashes need escaping.
+fn escape_dot(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Impl fns and impl consts are coloured as the impl they are in.
+fn get_item_kind(
+    name: &str,
+    fns: &HashMap<String, FnData>,
+    structs: &HashMap<String, StructData>,
+) -> &'static str {
+    if let Some(fn_data) = fns.get(name) {
+        return match fn_data.fn_type {
+            FnType::Fn(_) => "fn",
+            FnType::ImplFn(..) => "impl",
+            FnType::TraitFn(..) => "trait",
+        };
+    }
+    match structs
+        .get(name)
+        .map(|struct_data| &struct_data.struct_type)
+    {
+        Some(StructType::Struct(_)) => "struct",
+        Some(StructType::Enum(_)) => "enum",
+        Some(StructType::Union(_)) => "union",
+        Some(StructType::Trait(_)) => "trait",
+        Some(StructType::Const(_)) => "const",
+        Some(StructType::Static(_)) => "static",
+        Some(StructType::Macro(_)) => "macro",
+        Some(StructType::ImplConst(..)) => "impl",
+        None => "unknown",
+    }
+}
+
+// Groups the items of other crates by their crate, either the sysroot crates or the others.
+fn group_by_crate(external: &[String], sysroot: bool) -> BTreeMap<String, BTreeSet<String>> {
+    let mut external_items: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for external_item in external.iter() {
+        let dependency_name = external_item
+            .split("::")
+            .next()
+            .unwrap()
+            .trim_start_matches('<');
+        if SYSROOT_CRATES.contains(&dependency_name) != sysroot {
+            continue;
+        }
+        external_items
+            .entry(dependency_name.to_string())
+            .or_default()
+            .insert(external_item.clone());
+    }
+    external_items
 }
 
 impl CrateContext {
-    pub fn new(crate_path: &PathBuf) -> Self {
+    pub fn new(crate_path: &PathBuf, output_path: &Path) -> Self {
         let mut crate_context = CrateContext {
             crate_name: String::new(),
+            crate_version: None,
+            git_commit: None,
+            edition: None,
+            dependencies: toml::Table::new(),
             crate_path: PathBuf::new(),
+            output_path: output_path.to_path_buf(),
             entry_file_paths: Vec::new(),
             main_mod_contexts: Vec::new(),
+            options: ContextOptions::new(),
+            inner_attrs: Vec::new(),
+            fanouts: RefCell::new(Vec::new()),
+            rendered_contexts: Cell::new(0),
+            missing_analyses: Cell::new(0),
+            skipped_focals: Cell::new(0),
+            truncated_contexts: Cell::new(0),
+            below_min_apps: Cell::new(0),
+            above_max_apps: Cell::new(0),
+            partial_contexts: Cell::new(0),
+            unresolved_contexts: Cell::new(0),
+            focal_matches: Cell::new(0),
+            type_aliases: OnceCell::new(),
+            mod_files: OnceCell::new(),
+            re_exports: OnceCell::new(),
+            imports: OnceCell::new(),
+            source_map: RefCell::new(BTreeMap::new()),
+            name_map: RefCell::new(BTreeMap::new()),
+            index: RefCell::new(BTreeMap::new()),
+            dependency_graph: RefCell::new(BTreeSet::new()),
+            context_hashes: RefCell::new(BTreeMap::new()),
+            unchanged_contexts: Cell::new(0),
+            collected_contexts: RefCell::new(None),
+            context_stats: RefCell::new(Vec::new()),
         };
         let toml_path = crate_path.join("Cargo.toml");
         if fs::exists(&toml_path).unwrap() {
@@ -47,15 +201,27 @@ impl CrateContext {
                     eprintln!("Can not get the crate name of the crate!");
                     process::exit(2);
                 }
+                // A version inherited from the workspace is only known through call_chain.
+                if let Some(version) = package.get("version").and_then(|version| version.as_str()) {
+                    crate_context.crate_version = Some(version.to_string());
+                }
+                crate_context.edition = package
+                    .get("edition")
+                    .filter(|edition| edition.is_str())
+                    .cloned();
             } else {
                 eprintln!("Can not get the package infomation of the crate!");
                 process::exit(3);
             }
+            if let Some(Value::Table(dependencies)) = toml_value.get("dependencies") {
+                crate_context.dependencies = dependencies.clone();
+            }
         } else {
             eprintln!("Can not find the Cargo.toml file of the crate!");
             process::exit(4);
         }
         crate_context.crate_path = crate_path.clone();
+        crate_context.insert_version_info();
         let main_path = crate_path.join("src/main.rs");
         let lib_path = crate_path.join("src/lib.rs");
         let mut has_entry = false;
@@ -71,15 +237,204 @@ impl CrateContext {
             eprintln!("Can not find the entry file of the crate!");
             process::exit(5);
         }
+        for target_dir in ["tests", "examples"] {
+            crate_context
+                .entry_file_paths
+                .extend(CrateContext::find_target_entry_files(
+                    &crate_path.join(target_dir),
+                ));
+        }
         crate_context
     }
 
+    // The integration tests or examples cargo finds in a directory: its `.rs` files and the
+    // `main.rs` of its subdirectories.
+    fn find_target_entry_files(directory_path: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(directory_path) else {
+            return Vec::new();
+        };
+        let mut entry_file_paths: Vec<PathBuf> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.is_dir() {
+                    Some(path.join("main.rs")).filter(|main_path| main_path.is_file())
+                } else {
+                    Some(path).filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+                }
+            })
+            .collect();
+        entry_file_paths.sort();
+        entry_file_paths
+    }
+
+    // Where the analysis of the target of an entry file and its contexts go, if not to the
+    // output directory itself, with the name of the target.
+    fn get_target_dir(&self, entry_file_path: &Path) -> Option<(&'static str, String)> {
+        let relative_path = entry_file_path.strip_prefix(&self.crate_path).ok()?;
+        let target_dir = if relative_path.starts_with("tests") {
+            TEST_OUTPUT_DIR
+        } else if relative_path.starts_with("examples") {
+            EXAMPLE_OUTPUT_DIR
+        } else {
+            return None;
+        };
+        let target_path = if entry_file_path.ends_with("main.rs") {
+            entry_file_path.parent().unwrap().file_name().unwrap()
+        } else {
+            entry_file_path.file_stem().unwrap()
+        };
+        Some((target_dir, target_path.to_string_lossy().to_string()))
+    }
+
+    // Tests and examples are crates of their own, named after the target.
+    fn get_entry_crate_name(&self, entry_file_path: &Path) -> String {
+        match self.get_target_dir(entry_file_path) {
+            Some((_, target_name)) => target_name.replace("-", "_"),
+            None => self.crate_name.clone(),
+        }
+    }
+
+    fn get_lib_index(&self) -> Option<usize> {
+        let lib_path = self.crate_path.join("src/lib.rs");
+        self.entry_file_paths
+            .iter()
+            .position(|entry_file_path| *entry_file_path == lib_path)
+    }
+
+    // Prefers the version cargo metadata resolved during the analysis over the one in Cargo.toml.
+    fn insert_version_info(&mut self) {
+        let version_path = self.output_path.join("version.json");
+        if let Ok(contents) = read_to_string(version_path) {
+            if let Ok(crate_version) = serde_json::from_str::<CrateVersion>(&contents) {
+                self.crate_version = Some(crate_version.version);
+            }
+        }
+        self.git_commit = Command::new("git")
+            .arg("-C")
+            .arg(&self.crate_path)
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+
+    pub fn get_version_header(&self) -> String {
+        let mut header = format!("// {}", self.crate_name);
+        if let Some(crate_version) = &self.crate_version {
+            header += &format!(" {}", crate_version);
+        }
+        if let Some(git_commit) = &self.git_commit {
+            header += &format!(" ({})", git_commit);
+        }
+        header + "\n"
+    }
+
+    // Only the dependencies of the crate that the context names are kept, with their specs.
+    // Path dependencies are made absolute, optional ones always built, and versions inherited
+    // from a workspace become "*".
+    pub fn get_context_manifest(&self, lib_path: &str, crate_roots: &BTreeSet<String>) -> String {
+        let mut dependencies = toml::Table::new();
+        for (dependency_name, spec) in self.dependencies.iter() {
+            if !crate_roots.contains(&dependency_name.replace("-", "_")) {
+                continue;
+            }
+            let mut spec = spec.clone();
+            if let Value::Table(spec_table) = &mut spec {
+                if spec_table.remove("workspace").is_some() && !spec_table.contains_key("version") {
+                    spec_table.insert("version".to_string(), Value::from("*"));
+                }
+                spec_table.remove("optional");
+                if let Some(Value::String(path)) = spec_table.get_mut("path") {
+                    *path = self.crate_path.join(&path).to_string_lossy().to_string();
+                }
+            }
+            dependencies.insert(dependency_name.clone(), spec);
+        }
+        let mut package = toml::Table::new();
+        package.insert(
+            "name".to_string(),
+            Value::from(format!("{}_context", self.crate_name)),
+        );
+        package.insert("version".to_string(), Value::from("0.1.0"));
+        if let Some(edition) = &self.edition {
+            package.insert("edition".to_string(), edition.clone());
+        }
+        let mut lib = toml::Table::new();
+        lib.insert("path".to_string(), Value::from(lib_path));
+        // An empty workspace keeps the context out of the workspace of the crate it sits in.
+        [
+            ("package", package),
+            ("lib", lib),
+            ("dependencies", dependencies),
+            ("workspace", toml::Table::new()),
+        ]
+        .into_iter()
+        .map(|(section_name, section)| {
+            let mut manifest_section = toml::Table::new();
+            manifest_section.insert(section_name.to_string(), Value::Table(section));
+            toml::to_string(&manifest_section).unwrap()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+    }
+
+    pub fn write_manifest(&self) {
+        let manifest = serde_json::json!({
+            "crate_name": self.crate_name,
+            "crate_version": self.crate_version,
+            "git_commit": self.git_commit,
+            "rfocxt_version": env!("CARGO_PKG_VERSION"),
+        });
+        let output_path = self.output_path.join("manifest.json");
+        fs::create_dir_all(output_path.parent().unwrap()).unwrap();
+        let mut file = File::create(&output_path).unwrap();
+        file.write_all(serde_json::to_string_pretty(&manifest).unwrap().as_bytes())
+            .unwrap();
+    }
+
+    pub fn insert_options(&mut self, options: &ContextOptions) {
+        self.options = options.clone();
+    }
+
+    pub fn get_options(&self) -> &ContextOptions {
+        &self.options
+    }
+
+    // The `#![feature(...)]` gates with --emit-features, and the inner attributes named by
+    // --inner-attrs, e.g. `#![allow(...)]`. Not every crate attribute makes sense in a context,
+    // like `#![no_std]` for one that uses std, so only the named ones are written.
+    pub fn get_inner_attrs(&self) -> Vec<Attribute> {
+        self.inner_attrs
+            .iter()
+            .filter(|attr| {
+                (self.options.emit_features && attr.path().is_ident("feature"))
+                    || self
+                        .options
+                        .inner_attrs
+                        .iter()
+                        .any(|name| attr.path().is_ident(name))
+            })
+            .cloned()
+            .collect()
+    }
+
+    // Keeps the union of the inner attributes of all entry files.
+    fn insert_inner_attrs(&mut self, attrs: &[Attribute]) {
+        for attr in attrs.iter() {
+            if !self.inner_attrs.contains(attr) {
+                self.inner_attrs.push(attr.clone());
+            }
+        }
+    }
+
     pub fn parse_crate(&mut self) {
-        for entry_file_path in self.entry_file_paths.iter() {
+        for entry_file_path in self.entry_file_paths.clone().iter() {
             let entry_code = read_to_string(entry_file_path).unwrap();
             let entry_syntax = parse_file(&entry_code).unwrap();
+            self.insert_inner_attrs(&entry_syntax.attrs);
             let mut mod_mod_info = ModModInfo::new();
-            mod_mod_info.insert_mod_name(&self.crate_name);
+            mod_mod_info.insert_mod_name(&self.get_entry_crate_name(entry_file_path));
             mod_mod_info.insert_parent_mod_tree(&String::new());
             mod_mod_info.insert_file_path(entry_file_path);
             mod_mod_info
@@ -95,15 +450,138 @@ impl CrateContext {
             );
             self.main_mod_contexts.push(mod_context);
         }
-        if self.entry_file_paths.len() == 2 {
-            self.main_mod_contexts[0]
-                .borrow_mut()
-                .add_use_mod(&self.main_mod_contexts[1]);
+        self.link_lib_mod(&self.main_mod_contexts);
+    }
+
+    // The bin, tests and examples of a package use its lib.
+    fn link_lib_mod(&self, mod_contexts: &[Rc<RefCell<ModContext>>]) {
+        if let Some(lib_index) = self.get_lib_index() {
+            for (index, mod_context) in mod_contexts.iter().enumerate() {
+                if index != lib_index {
+                    mod_context
+                        .borrow_mut()
+                        .add_use_mod(&mod_contexts[lib_index]);
+                }
+            }
         }
     }
 
     fn change_impl_name(&mut self) {}
 
+    fn get_source_hashes(mod_contexts: &[Rc<RefCell<ModContext>>]) -> BTreeMap<PathBuf, u64> {
+        let mut file_paths: BTreeSet<PathBuf> = BTreeSet::new();
+        for mod_context in mod_contexts.iter() {
+            mod_context.borrow().get_file_paths(&mut file_paths);
+        }
+        file_paths
+            .into_iter()
+            .filter_map(|file_path| {
+                let source = fs::read(&file_path).ok()?;
+                let mut hasher = DefaultHasher::new();
+                source.hash(&mut hasher);
+                Some((file_path, hasher.finish()))
+            })
+            .collect()
+    }
+
+    // Writes the mods after `change_all_names` to context.json, for other tools and later runs.
+    pub fn write_context_cache(&self) {
+        let features = &self.inner_attrs;
+        let context_cache = ContextCache {
+            rfocxt_version: env!("CARGO_PKG_VERSION").to_string(),
+            sources: CrateContext::get_source_hashes(&self.main_mod_contexts),
+            features: quote! {#(#features)*}.to_string(),
+            mod_contexts: self.main_mod_contexts.clone(),
+        };
+        fs::create_dir_all(&self.output_path).unwrap();
+        let file = File::create(self.output_path.join("context.json")).unwrap();
+        serde_json::to_writer(file, &context_cache).unwrap();
+    }
+
+    // Replaces `parse_crate` and `change_all_names` when context.json was written by this
+    // version of rfocxt and none of the source files changed since.
+    pub fn load_context_cache(&mut self) -> bool {
+        let Ok(contents) = read_to_string(self.output_path.join("context.json")) else {
+            return false;
+        };
+        let context_cache: ContextCache = match serde_json::from_str(&contents) {
+            Ok(context_cache) => context_cache,
+            Err(err) => {
+                eprintln!("Warning: ignoring the unreadable context.json ({})", err);
+                return false;
+            }
+        };
+        if context_cache.rfocxt_version != env!("CARGO_PKG_VERSION")
+            || context_cache.mod_contexts.len() != self.entry_file_paths.len()
+            || !self
+                .entry_file_paths
+                .iter()
+                .all(|entry_file_path| context_cache.sources.contains_key(entry_file_path))
+            || CrateContext::get_source_hashes(&context_cache.mod_contexts) != context_cache.sources
+        {
+            return false;
+        }
+        for mod_context in context_cache.mod_contexts.iter() {
+            ModContext::link_mods(mod_context, mod_context);
+        }
+        self.link_lib_mod(&context_cache.mod_contexts);
+        self.insert_inner_attrs(&parse_file(&context_cache.features).unwrap().attrs);
+        self.main_mod_contexts = context_cache.mod_contexts;
+        true
+    }
+
+    // A cache written by another version of rfocxt can't tell which of the outputs are current.
+    pub fn has_stale_incremental_cache(output_path: &Path) -> bool {
+        let Ok(contents) = read_to_string(output_path.join(INCREMENTAL_CACHE)) else {
+            return false;
+        };
+        !serde_json::from_str::<IncrementalCache>(&contents)
+            .is_ok_and(|cache| cache.rfocxt_version == env!("CARGO_PKG_VERSION"))
+    }
+
+    pub fn load_incremental_cache(&self) {
+        let Ok(contents) = read_to_string(self.output_path.join(INCREMENTAL_CACHE)) else {
+            return;
+        };
+        if let Ok(cache) = serde_json::from_str::<IncrementalCache>(&contents) {
+            if cache.rfocxt_version == env!("CARGO_PKG_VERSION") {
+                *self.context_hashes.borrow_mut() = cache.contexts;
+            }
+        }
+    }
+
+    // Records the hash of a context, and tells whether the previous run wrote the same one and
+    // its files are all still there, as they may have been removed since.
+    pub fn is_context_unchanged(
+        &self,
+        file_name: &str,
+        hash: u64,
+        output_files: &[PathBuf],
+    ) -> bool {
+        let previous_hash = self
+            .context_hashes
+            .borrow_mut()
+            .insert(file_name.to_string(), hash);
+        let is_unchanged = previous_hash == Some(hash)
+            && output_files.iter().all(|output_file| output_file.exists());
+        if is_unchanged {
+            self.unchanged_contexts
+                .set(self.unchanged_contexts.get() + 1);
+        }
+        is_unchanged
+    }
+
+    // Contexts not written by this run, e.g. with --focal, keep their hashes.
+    pub fn write_incremental_cache(&self) {
+        let cache = IncrementalCache {
+            rfocxt_version: env!("CARGO_PKG_VERSION").to_string(),
+            contexts: self.context_hashes.borrow().clone(),
+        };
+        fs::create_dir_all(&self.output_path).unwrap();
+        let file = File::create(self.output_path.join(INCREMENTAL_CACHE)).unwrap();
+        serde_json::to_writer_pretty(file, &cache).unwrap();
+    }
+
     pub fn change_all_names(&mut self) {
         for mod_context in self.main_mod_contexts.iter_mut() {
             mod_context
@@ -124,26 +602,411 @@ impl CrateContext {
         fns: &HashMap<String, FnData>,
         structs: &HashMap<String, StructData>,
     ) {
-        for mod_context in self.main_mod_contexts.iter() {
+        for (entry_file_path, mod_context) in self
+            .entry_file_paths
+            .iter()
+            .zip(self.main_mod_contexts.iter())
+        {
             mod_context.borrow().get_all_context(
-                &self.crate_path.join("rfocxt"),
+                &self.get_target_output_path(entry_file_path),
                 mod_trees,
                 fns,
                 structs,
                 self,
             );
         }
+        self.report_fanouts();
+        self.report_apps_filter();
+        self.report_limits();
+        if self.options.incremental {
+            eprintln!(
+                "{} contexts are unchanged since the last run and were not rewritten",
+                self.unchanged_contexts.get()
+            );
+        }
+        if self.options.report_external {
+            eprintln!(
+                "{} contexts refer to items of dependency crates",
+                self.partial_contexts.get()
+            );
+        }
+        if self.options.report_unresolved {
+            eprintln!(
+                "{} contexts have applications that resolve to no item of the crate",
+                self.unresolved_contexts.get()
+            );
+        }
+    }
+
+    // call_chain writes the analysis of a bin next to a lib to a directory of its own, as the
+    // two crates share their name, and the contexts of the bin go there as well. So do the ones
+    // of tests and examples, which could be named like the package.
+    fn get_target_output_path(&self, entry_file_path: &Path) -> PathBuf {
+        if let Some((target_dir, target_name)) = self.get_target_dir(entry_file_path) {
+            self.output_path.join(target_dir).join(target_name)
+        } else if self.get_lib_index().is_some() && entry_file_path.ends_with("src/main.rs") {
+            self.output_path.join(BIN_OUTPUT_DIR)
+        } else {
+            self.output_path.clone()
+        }
+    }
+
+    pub fn get_output_path(&self) -> &PathBuf {
+        &self.output_path
+    }
+
+    /// Returns the contexts keyed by the complete name of their focal fn instead of writing them,
+    /// one file per context, or one per layer with `layered_output`. The names of the fns of a
+    /// bin next to a lib start with `bin/`, and those of tests and examples with `test/<name>/`
+    /// and `example/<name>/`, as their files would.
+    pub fn collect_all_context(
+        &self,
+        mod_trees: &Vec<String>,
+        fns: &HashMap<String, FnData>,
+        structs: &HashMap<String, StructData>,
+    ) -> BTreeMap<String, ContextFiles> {
+        *self.collected_contexts.borrow_mut() = Some(BTreeMap::new());
+        self.parse_all_context(mod_trees, fns, structs);
+        self.collected_contexts.borrow_mut().take().unwrap()
+    }
+
+    /// Returns the context of a trait instead of writing it, or `None` if the trait doesn't exist.
+    pub fn collect_trait_context(
+        &self,
+        focal_trait: &String,
+        mod_trees: &Vec<String>,
+        fns: &HashMap<String, FnData>,
+        structs: &HashMap<String, StructData>,
+    ) -> Option<BTreeMap<String, ContextFiles>> {
+        *self.collected_contexts.borrow_mut() = Some(BTreeMap::new());
+        let has_trait = self.parse_trait_context(focal_trait, mod_trees, fns, structs);
+        let collected_contexts = self.collected_contexts.borrow_mut().take().unwrap();
+        has_trait.then_some(collected_contexts)
+    }
+
+    /// Returns the context of the impls of a type instead of writing it, or `None` if the type
+    /// doesn't exist.
+    pub fn collect_impl_context(
+        &self,
+        focal_impl: &String,
+        impl_trait: Option<&String>,
+        mod_trees: &Vec<String>,
+        fns: &HashMap<String, FnData>,
+        structs: &HashMap<String, StructData>,
+    ) -> Option<BTreeMap<String, ContextFiles>> {
+        *self.collected_contexts.borrow_mut() = Some(BTreeMap::new());
+        let has_type = self.parse_impl_context(focal_impl, impl_trait, mod_trees, fns, structs);
+        let collected_contexts = self.collected_contexts.borrow_mut().take().unwrap();
+        has_type.then_some(collected_contexts)
+    }
+
+    // While collecting, contexts are kept in memory and nothing is written to the output directory.
+    pub fn is_collecting(&self) -> bool {
+        self.collected_contexts.borrow().is_some()
+    }
+
+    pub fn insert_collected_context(&self, complete_function_name: &str, files: ContextFiles) {
+        if let Some(collected_contexts) = self.collected_contexts.borrow_mut().as_mut() {
+            collected_contexts.insert(complete_function_name.to_string(), files);
+        }
+    }
+
+    // Counts the contexts left out by --min-apps and --max-apps.
+    pub fn is_filtered_by_apps(&self, application_count: usize) -> bool {
+        if self
+            .options
+            .min_apps
+            .is_some_and(|min_apps| application_count < min_apps)
+        {
+            self.below_min_apps.set(self.below_min_apps.get() + 1);
+            return true;
+        }
+        if self
+            .options
+            .max_apps
+            .is_some_and(|max_apps| application_count > max_apps)
+        {
+            self.above_max_apps.set(self.above_max_apps.get() + 1);
+            return true;
+        }
+        false
+    }
+
+    fn report_apps_filter(&self) {
+        if let Some(min_apps) = self.options.min_apps {
+            eprintln!(
+                "Filtered out {} fns with fewer than {} applications",
+                self.below_min_apps.get(),
+                min_apps
+            );
+        }
+        if let Some(max_apps) = self.options.max_apps {
+            eprintln!(
+                "Filtered out {} fns with more than {} applications",
+                self.above_max_apps.get(),
+                max_apps
+            );
+        }
+    }
+
+    pub fn count_truncated_context(&self) {
+        self.truncated_contexts
+            .set(self.truncated_contexts.get() + 1);
+    }
+
+    fn report_limits(&self) {
+        if let Some(max_focals) = self.options.max_focals {
+            if self.skipped_focals.get() > 0 {
+                eprintln!(
+                    "Stopped after {} contexts, {} fns got none",
+                    max_focals,
+                    self.skipped_focals.get()
+                );
+            }
+        }
+        if let Some(max_items_per_focal) = self.options.max_items_per_focal {
+            eprintln!(
+                "Truncated {} contexts with more than {} items",
+                self.truncated_contexts.get(),
+                max_items_per_focal
+            );
+        }
+    }
+
+    // Items of the standard library are left out, a context is only partial without the others.
+    pub fn write_external(
+        &self,
+        output_path: &Path,
+        complete_function_name: &str,
+        external: &[String],
+    ) {
+        let external_items = group_by_crate(external, false);
+        if external_items.is_empty() {
+            return;
+        }
+        self.partial_contexts.set(self.partial_contexts.get() + 1);
+        let directory_path = output_path.join("external");
+        fs::create_dir_all(&directory_path).unwrap();
+        let file_path = directory_path.join(format!("{}.json", complete_function_name));
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(
+            serde_json::to_string_pretty(&external_items)
+                .unwrap()
+                .as_bytes(),
+        )
+        .unwrap();
+    }
+
+    // Items of the standard library are never part of a context, nor reported as unresolved.
+    pub fn write_std(
+        &self,
+        output_path: &Path,
+        complete_function_name: &str,
+        external: &[String],
+    ) {
+        let std_items = group_by_crate(external, true);
+        if std_items.is_empty() {
+            return;
+        }
+        let directory_path = output_path.join("std");
+        fs::create_dir_all(&directory_path).unwrap();
+        let file_path = directory_path.join(format!("{}.json", complete_function_name));
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(serde_json::to_string_pretty(&std_items).unwrap().as_bytes())
+            .unwrap();
+    }
+
+    pub fn write_unresolved(
+        &self,
+        output_path: &Path,
+        complete_function_name: &str,
+        unresolved: &[String],
+    ) {
+        if unresolved.is_empty() {
+            return;
+        }
+        self.unresolved_contexts
+            .set(self.unresolved_contexts.get() + 1);
+        let directory_path = output_path.join("unresolved");
+        fs::create_dir_all(&directory_path).unwrap();
+        let file_path = directory_path.join(format!("{}.json", complete_function_name));
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(serde_json::to_string_pretty(unresolved).unwrap().as_bytes())
+            .unwrap();
+    }
+
+    // Tallies the applications of a fn, to find the ones that blow up their contexts.
+    pub fn check_fanout(&self, complete_function_name: &str, data: &CallsAndTypes) {
+        if let Some(warn_fanout) = self.options.warn_fanout {
+            let fanout = data.calls.len() + data.types.len();
+            if fanout > warn_fanout {
+                eprintln!(
+                    "Warning: {} has {} applications (more than {})",
+                    complete_function_name, fanout, warn_fanout
+                );
+            }
+            self.fanouts
+                .borrow_mut()
+                .push((complete_function_name.to_string(), fanout));
+        }
+    }
+
+    pub fn report_fanouts(&self) {
+        if self.options.warn_fanout.is_none() {
+            return;
+        }
+        let mut fanouts = self.fanouts.borrow().clone();
+        fanouts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        eprintln!("Highest fanout fns:");
+        for (complete_function_name, fanout) in fanouts.iter().take(TOP_FANOUTS) {
+            eprintln!("  {:>6}  {}", fanout, complete_function_name);
+        }
+    }
+
+    pub fn insert_stats(&self, context_stats: ContextStats) {
+        self.context_stats.borrow_mut().push(context_stats);
+    }
+
+    // Largest first, as the contexts that pull in most of the crate are the ones to look at.
+    pub fn report_stats(&self) {
+        let mut context_stats = self.context_stats.borrow().clone();
+        context_stats.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.name.cmp(&b.name)));
+        println!(
+            "{:>6}  {:>10}  {:>6}  context",
+            "items", "unresolved", "lines"
+        );
+        for stats in context_stats.iter() {
+            println!(
+                "{:>6}  {:>10}  {:>6}  {}",
+                stats.items, stats.unresolved, stats.lines, stats.name
+            );
+        }
+    }
+
+    pub fn should_render(&self, focal_kind: FocalKind, complete_function_name: &String) -> bool {
+        let should_render = self
+            .options
+            .should_render(focal_kind, complete_function_name);
+        if should_render && self.options.focal.is_some() {
+            self.focal_matches.set(self.focal_matches.get() + 1);
+        }
+        // The fns past --max-focals are left out before their applications are parsed.
+        if should_render
+            && self
+                .options
+                .max_focals
+                .is_some_and(|max_focals| self.rendered_contexts.get() >= max_focals)
+        {
+            self.skipped_focals.set(self.skipped_focals.get() + 1);
+            return false;
+        }
+        should_render
+    }
+
+    pub fn has_focal_match(&self) -> bool {
+        self.focal_matches.get() > 0
+    }
+
+    pub fn get_focal_match_count(&self) -> usize {
+        self.focal_matches.get()
+    }
+
+    // A focal fn without a callsandtypes file wasn't analyzed by call_chain, e.g. when cfg'd out.
+    pub fn count_context(&self, is_rendered: bool) {
+        if is_rendered {
+            self.rendered_contexts.set(self.rendered_contexts.get() + 1);
+        } else {
+            self.missing_analyses.set(self.missing_analyses.get() + 1);
+        }
+    }
+
+    // call_chain leaves out fns whose source syn can't parse, which would otherwise go
+    // unnoticed next to the ones that are cfg'd out.
+    pub fn count_missing_analysis(&self, output_path: &Path, complete_function_name: &str) {
+        self.count_context(false);
+        let skipped_file_path = output_path
+            .join("skipped")
+            .join(format!("{}.json", complete_function_name));
+        if let Ok(contents) = read_to_string(skipped_file_path) {
+            let err: String = serde_json::from_str(&contents).unwrap_or(contents);
+            eprintln!(
+                "Warning: {} got no context, call_chain could not parse its source: {}",
+                complete_function_name, err
+            );
+        }
+    }
+
+    // Merges the phase timings of rfocxt with the ones call_chain wrote for each compiled crate.
+    pub fn write_profile(&self, phase_micros: &[(&str, u128)]) {
+        let output_path = self.output_path.clone();
+        let mut call_chain_profiles: Vec<Profile> = Vec::new();
+        if let Ok(entries) = fs::read_dir(output_path.join("profile")) {
+            for entry in entries {
+                let contents = read_to_string(entry.unwrap().path()).unwrap();
+                call_chain_profiles.push(serde_json::from_str(&contents).unwrap());
+            }
+        }
+        call_chain_profiles.sort_by(|a, b| a.crate_name.cmp(&b.crate_name));
+        let phases: serde_json::Map<String, serde_json::Value> = phase_micros
+            .iter()
+            .map(|(phase, micros)| (phase.to_string(), serde_json::Value::from(*micros as u64)))
+            .collect();
+        let profile = serde_json::json!({
+            "phase_micros": phases,
+            "rendered_contexts": self.rendered_contexts.get(),
+            "missing_analyses": self.missing_analyses.get(),
+            "call_chain": call_chain_profiles,
+        });
+        let mut file = File::create(output_path.join("profile.json")).unwrap();
+        file.write_all(serde_json::to_string_pretty(&profile).unwrap().as_bytes())
+            .unwrap();
+    }
+
+    pub fn parse_trait_context(
+        &self,
+        focal_trait: &String,
+        mod_trees: &Vec<String>,
+        fns: &HashMap<String, FnData>,
+        structs: &HashMap<String, StructData>,
+    ) -> bool {
+        write_trait_context(
+            &self.output_path,
+            focal_trait,
+            mod_trees,
+            fns,
+            structs,
+            self,
+        )
+    }
+
+    pub fn parse_impl_context(
+        &self,
+        focal_impl: &String,
+        impl_trait: Option<&String>,
+        mod_trees: &Vec<String>,
+        fns: &HashMap<String, FnData>,
+        structs: &HashMap<String, StructData>,
+    ) -> bool {
+        write_impl_context(
+            &self.output_path,
+            focal_impl,
+            impl_trait,
+            mod_trees,
+            fns,
+            structs,
+            self,
+        )
     }
 
     pub fn cout_in_one_file_for_test(&self) {
-        let output_path = self.crate_path.join("rfocxt/context.txt");
+        let output_path = self.output_path.join("context.txt");
         fs::create_dir_all(output_path.parent().unwrap()).unwrap();
         let mut file = File::create(&output_path).unwrap();
         file.write_all(format!("{:#?}", self).as_bytes()).unwrap();
     }
 
     pub fn cout_all_mod_trees_in_on_file_for_test(&self, out_mod_trees: &mut HashSet<String>) {
-        let output_path = self.crate_path.join("rfocxt/mod_trees");
+        let output_path = self.output_path.join("mod_trees");
         fs::create_dir_all(&output_path).unwrap();
         let mut num = 0;
         for mod_context in self.main_mod_contexts.iter() {
@@ -161,7 +1024,7 @@ impl CrateContext {
     }
 
     pub fn cout_complete_function_name_in_on_file_for_test(&self) {
-        let output_path = self.crate_path.join("rfocxt/functions");
+        let output_path = self.output_path.join("functions");
         fs::create_dir_all(&output_path).unwrap();
         let mut num = 0;
         for mod_context in self.main_mod_contexts.iter() {
@@ -178,6 +1041,188 @@ impl CrateContext {
         }
     }
 
+    // The type aliases of all modules, by name, collected on first use.
+    pub fn get_type_aliases(&self) -> &Vec<(String, Type)> {
+        self.type_aliases.get_or_init(|| {
+            let mut type_aliases: Vec<(String, Type)> = Vec::new();
+            for main_mod_context in self.main_mod_contexts.iter() {
+                main_mod_context
+                    .borrow()
+                    .get_type_aliases(&mut type_aliases);
+            }
+            type_aliases
+        })
+    }
+
+    // The `pub use`s of all modules whose targets were resolved to a path in the crate, collected
+    // on first use. Ones of other crates, or that only name the module they are in, are left out.
+    // Only the uses of items of the crate from another module are kept.
+    fn collect_uses(&self, get_uses: impl Fn(&ModContext, &mut Vec<ReExport>)) -> Vec<ReExport> {
+        let mut uses: Vec<ReExport> = Vec::new();
+        for main_mod_context in self.main_mod_contexts.iter() {
+            let crate_name = main_mod_context.borrow().get_mod_name();
+            let mut mod_uses: Vec<ReExport> = Vec::new();
+            get_uses(&main_mod_context.borrow(), &mut mod_uses);
+            uses.extend(mod_uses.into_iter().filter(|re_export| {
+                re_export.target.starts_with(&(crate_name.clone() + "::"))
+                    && re_export
+                        .target
+                        .rsplit_once("::")
+                        .is_some_and(|(module, _)| module != re_export.module)
+            }));
+        }
+        uses
+    }
+
+    pub fn get_re_exports(&self) -> &Vec<ReExport> {
+        self.re_exports
+            .get_or_init(|| self.collect_uses(ModContext::get_re_exports))
+    }
+
+    pub fn get_imports(&self) -> &Vec<ReExport> {
+        self.imports
+            .get_or_init(|| self.collect_uses(ModContext::get_imports))
+    }
+
+    // The file of every module by its mod tree, collected on first use.
+    pub fn get_mod_files(&self) -> &HashMap<String, PathBuf> {
+        self.mod_files.get_or_init(|| {
+            let mut mod_files: HashMap<String, PathBuf> = HashMap::new();
+            for main_mod_context in self.main_mod_contexts.iter() {
+                main_mod_context
+                    .borrow()
+                    .get_mod_files(Path::new(""), &mut mod_files);
+            }
+            mod_files
+        })
+    }
+
+    pub fn insert_source_map(&self, file_name: &str, entries: Vec<SourceMapEntry>) {
+        self.source_map
+            .borrow_mut()
+            .insert(file_name.to_string(), entries);
+    }
+
+    // Maps the file name of every written context to where its items are in the crate.
+    pub fn write_source_map(&self) {
+        fs::create_dir_all(&self.output_path).unwrap();
+        let file = File::create(self.output_path.join("source_map.json")).unwrap();
+        serde_json::to_writer_pretty(file, &*self.source_map.borrow()).unwrap();
+    }
+
+    // Two fns whose names encode to the same file would overwrite each other's context.
+    pub fn insert_file_name(&self, file_name: &str, complete_function_name: &str) {
+        let mut name_map = self.name_map.borrow_mut();
+        if let Some(original_name) = name_map.get(file_name) {
+            if !original_name.eq(complete_function_name) {
+                eprintln!(
+                    "The contexts of {} and {} would both be written to {}! Try another --name-sep.",
+                    original_name, complete_function_name, file_name
+                );
+                process::exit(13);
+            }
+        }
+        name_map.insert(file_name.to_string(), complete_function_name.to_string());
+    }
+
+    // name_map.json maps the complete name of every focal fn to the file name of its context,
+    // relative to the output directory and without its extension, and name_map_reverse.json
+    // maps it back. A run that only writes some contexts, e.g. with --focal, keeps the entries
+    // of the others from earlier runs.
+    pub fn write_name_map(&self) {
+        let reverse_file_path = self.output_path.join("name_map_reverse.json");
+        let mut reverse_name_map: BTreeMap<String, String> = BTreeMap::new();
+        if self.options.render_only.is_some()
+            || self.options.focal.is_some()
+            || !self.options.only_kinds.is_empty()
+        {
+            if let Ok(contents) = read_to_string(&reverse_file_path) {
+                reverse_name_map = serde_json::from_str(&contents).unwrap_or_default();
+            }
+        }
+        reverse_name_map.extend(self.name_map.borrow().clone());
+        let name_map: BTreeMap<&String, &String> = reverse_name_map
+            .iter()
+            .map(|(file_name, complete_function_name)| (complete_function_name, file_name))
+            .collect();
+        fs::create_dir_all(&self.output_path).unwrap();
+        let file = File::create(self.output_path.join("name_map.json")).unwrap();
+        serde_json::to_writer_pretty(file, &name_map).unwrap();
+        let file = File::create(reverse_file_path).unwrap();
+        serde_json::to_writer_pretty(file, &reverse_name_map).unwrap();
+    }
+
+    pub fn insert_index_entry(&self, index_entry: IndexEntry) {
+        self.index
+            .borrow_mut()
+            .insert(index_entry.file_name.clone(), index_entry);
+    }
+
+    // Lists every written context for a harness to go through, by file name. Like the name
+    // map, a run that only writes some contexts keeps the entries of the others.
+    pub fn write_index(&self) {
+        let file_path = self.output_path.join("index.json");
+        let mut index: BTreeMap<String, IndexEntry> = BTreeMap::new();
+        if self.options.render_only.is_some()
+            || self.options.focal.is_some()
+            || !self.options.only_kinds.is_empty()
+        {
+            if let Ok(contents) = read_to_string(&file_path) {
+                let entries: Vec<IndexEntry> = serde_json::from_str(&contents).unwrap_or_default();
+                index.extend(
+                    entries
+                        .into_iter()
+                        .map(|index_entry| (index_entry.file_name.clone(), index_entry)),
+                );
+            }
+        }
+        index.extend(self.index.borrow().clone());
+        fs::create_dir_all(&self.output_path).unwrap();
+        let file = File::create(file_path).unwrap();
+        serde_json::to_writer_pretty(file, &index.into_values().collect::<Vec<IndexEntry>>())
+            .unwrap();
+    }
+
+    pub fn insert_graph_edges(&self, edges: Vec<(String, String)>) {
+        self.dependency_graph.borrow_mut().extend(edges);
+    }
+
+    // Writes the references between the items of all written contexts to deps.dot, from each
+    // focal fn to the items of its context and from impl fns to their self types.
+    pub fn write_dependency_graph(
+        &self,
+        fns: &HashMap<String, FnData>,
+        structs: &HashMap<String, StructData>,
+    ) {
+        let dependency_graph = self.dependency_graph.borrow();
+        let mut nodes: BTreeSet<&String> = BTreeSet::new();
+        for (from, to) in dependency_graph.iter() {
+            nodes.insert(from);
+            nodes.insert(to);
+        }
+        let mut graph = String::from("digraph deps {\n    node [shape=box, style=filled];\n");
+        for node in nodes {
+            let kind = get_item_kind(node, fns, structs);
+            let color = GRAPH_COLORS
+                .iter()
+                .find(|(color_kind, _)| color_kind.eq(&kind))
+                .map_or("white", |(_, color)| color);
+            graph += &format!(
+                "    \"{}\" [fillcolor={}, tooltip=\"{}\"];\n",
+                escape_dot(node),
+                color,
+                kind
+            );
+        }
+        for (from, to) in dependency_graph.iter() {
+            graph += &format!("    \"{}\" -> \"{}\";\n", escape_dot(from), escape_dot(to));
+        }
+        graph += "}\n";
+        fs::create_dir_all(&self.output_path).unwrap();
+        let mut file = File::create(self.output_path.join("deps.dot")).unwrap();
+        file.write_all(graph.as_bytes()).unwrap();
+    }
+
     pub fn get_result(
         &self,
         fns: &mut HashMap<String, FnData>,
@@ -186,13 +1231,106 @@ impl CrateContext {
         for main_mod_context in self.main_mod_contexts.iter() {
             main_mod_context.borrow().get_result(fns, structs);
         }
+        for main_mod_context in self.main_mod_contexts.iter() {
+            main_mod_context.borrow().get_empty_impls(structs);
+        }
     }
+}
 
-    pub fn get_relative_types_for_struct(&self, name: &String, relative_types: &mut Vec<String>) {
-        for main_mod_context in self.main_mod_contexts.iter() {
-            main_mod_context
-                .borrow()
-                .get_relative_types_for_struct(name, relative_types);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A package with a lib, a bin, two tests (one named like the package) and an example.
+    fn create_package(name: &str) -> PathBuf {
+        let crate_path = std::env::temp_dir().join(format!("rfocxt-{}-{}", name, process::id()));
+        for file_path in [
+            "src/lib.rs",
+            "src/main.rs",
+            "tests/my-pkg.rs",
+            "tests/multi/main.rs",
+            "tests/multi/helper.rs",
+            "tests/data.txt",
+            "examples/demo.rs",
+        ] {
+            let file_path = crate_path.join(file_path);
+            fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            fs::write(file_path, "").unwrap();
         }
+        fs::write(
+            crate_path.join("Cargo.toml"),
+            "[package]\nname = \"my-pkg\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        crate_path
+    }
+
+    #[test]
+    fn finds_tests_and_examples() {
+        let crate_path = create_package("entries");
+        let crate_context = CrateContext::new(&crate_path, &crate_path.join("rfocxt"));
+        fs::remove_dir_all(&crate_path).unwrap();
+        let entry_file_paths: Vec<PathBuf> = [
+            "src/main.rs",
+            "src/lib.rs",
+            "tests/multi/main.rs",
+            "tests/my-pkg.rs",
+            "examples/demo.rs",
+        ]
+        .iter()
+        .map(|file_path| crate_path.join(file_path))
+        .collect();
+        assert_eq!(crate_context.entry_file_paths, entry_file_paths);
+        assert_eq!(crate_context.get_lib_index(), Some(1));
+    }
+
+    #[test]
+    fn context_with_a_missing_file_is_rewritten() {
+        let crate_path = create_package("unchanged");
+        let crate_context = CrateContext::new(&crate_path, &crate_path.join("rfocxt"));
+        let context_file = crate_path.join("src/lib.rs");
+        let meta_file = crate_path.join("src/lib.meta.json");
+        let output_files = [context_file.clone(), meta_file];
+        assert!(!crate_context.is_context_unchanged("lib", 1, &output_files[..1]));
+        assert!(crate_context.is_context_unchanged("lib", 1, &output_files[..1]));
+        assert!(!crate_context.is_context_unchanged("lib", 1, &output_files));
+        fs::remove_file(&context_file).unwrap();
+        assert!(!crate_context.is_context_unchanged("lib", 1, &output_files[..1]));
+        fs::remove_dir_all(&crate_path).unwrap();
+    }
+
+    #[test]
+    fn namespaces_targets_sharing_the_crate_name() {
+        let crate_path = create_package("outputs");
+        let output_path = crate_path.join("rfocxt");
+        let crate_context = CrateContext::new(&crate_path, &output_path);
+        fs::remove_dir_all(&crate_path).unwrap();
+        let names_and_outputs = |file_path: &str| {
+            let entry_file_path = crate_path.join(file_path);
+            (
+                crate_context.get_entry_crate_name(&entry_file_path),
+                crate_context.get_target_output_path(&entry_file_path),
+            )
+        };
+        assert_eq!(
+            names_and_outputs("src/lib.rs"),
+            ("my_pkg".to_string(), output_path.clone())
+        );
+        assert_eq!(
+            names_and_outputs("src/main.rs"),
+            ("my_pkg".to_string(), output_path.join("bin"))
+        );
+        assert_eq!(
+            names_and_outputs("tests/my-pkg.rs"),
+            ("my_pkg".to_string(), output_path.join("test/my-pkg"))
+        );
+        assert_eq!(
+            names_and_outputs("tests/multi/main.rs"),
+            ("multi".to_string(), output_path.join("test/multi"))
+        );
+        assert_eq!(
+            names_and_outputs("examples/demo.rs"),
+            ("demo".to_string(), output_path.join("example/demo"))
+        );
     }
 }
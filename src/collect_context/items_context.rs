@@ -1,14 +1,16 @@
 use std::{cell::RefCell, rc::Rc};
 
+use serde::{Deserialize, Serialize};
 use syn::{
-    ImplItemConst, ImplItemFn, ImplItemType, Item, ItemConst, ItemEnum, ItemFn, ItemImpl, ItemMod,
-    ItemStatic, ItemStruct, ItemTrait, ItemTraitAlias, ItemType, ItemUnion, ItemUse,
-    TraitItemConst, TraitItemFn, TraitItemType,
+    visit_mut::{self, VisitMut},
+    ImplItemConst, ImplItemFn, ImplItemType, Item, ItemConst, ItemEnum, ItemFn, ItemImpl,
+    ItemMacro, ItemMod, ItemStatic, ItemStruct, ItemTrait, ItemTraitAlias, ItemType, ItemUnion,
+    ItemUse, Path, ReturnType, TraitItemConst, TraitItemFn, TraitItemType, Type, TypePath,
 };
 
-use super::mod_context::ModContext;
+use super::{mod_context::ModContext, serde_tokens};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MyVisibility {
     PubT,
     PubS,
@@ -16,7 +18,7 @@ pub enum MyVisibility {
     Pri,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MyPath {
     name: String,
     next: Option<Box<MyPath>>,
@@ -306,7 +308,60 @@ impl MyPath {
     // }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Rewrites the paths in a type to the import names of the items they name from a module, the
+// way the self types of impls are resolved, e.g. `Foo` to `my_crate::a::Foo`. Paths through
+// `Self` and the ones that don't resolve, like generic params or items of other crates, are
+// kept as they are written.
+struct ImportNameVisitor<'a> {
+    mod_context: &'a Rc<RefCell<ModContext>>,
+}
+
+impl VisitMut for ImportNameVisitor<'_> {
+    fn visit_type_path_mut(&mut self, type_path: &mut TypePath) {
+        visit_mut::visit_type_path_mut(self, type_path);
+        if type_path.qself.is_some() || type_path.path.segments[0].ident == "Self" {
+            return;
+        }
+        let names: Vec<String> = type_path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let path_string = names.join("::");
+        let mut name = Name::new(names.last().unwrap());
+        name.insert_import_name(&path_string);
+        name.change_name_for_impl_struct_name(self.mod_context);
+        let mut import_name = name.get_import_name().to_string();
+        // A type alias of the module isn't found among its structs.
+        if import_name.is_empty()
+            && names.len() == 1
+            && self
+                .mod_context
+                .borrow()
+                .has_type_alias_in_syntax(&names[0])
+        {
+            import_name = self.mod_context.borrow().get_mod_tree().to_string() + "::" + &names[0];
+        }
+        if import_name.is_empty() || import_name.eq(&path_string) {
+            return;
+        }
+        let Ok(mut path) = syn::parse_str::<Path>(&import_name) else {
+            return;
+        };
+        path.segments.last_mut().unwrap().arguments =
+            type_path.path.segments.last().unwrap().arguments.clone();
+        type_path.path = path;
+    }
+}
+
+pub fn get_import_type(ty: &Type, mod_context: &Rc<RefCell<ModContext>>) -> Type {
+    let mut import_type = ty.clone();
+    ImportNameVisitor { mod_context }.visit_type_mut(&mut import_type);
+    import_type
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Name {
     name: String,
     complete_name: String,
@@ -435,7 +490,7 @@ impl Name {
 //     }
 // }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FunctionItem {
     FnItem(FnItem),
     ImplFnItem(ImplFnItem),
@@ -464,8 +519,10 @@ impl FunctionItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConstItem {
+    const_name: Name,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemConst>,
     visibility: MyVisibility,
 }
@@ -473,11 +530,16 @@ pub struct ConstItem {
 impl ConstItem {
     pub fn new() -> Self {
         ConstItem {
+            const_name: Name::none(),
             item: None,
             visibility: MyVisibility::Pri,
         }
     }
 
+    pub fn insert_const_name(&mut self, const_name: &String) {
+        self.const_name = Name::new(const_name);
+    }
+
     pub fn insert_item(&mut self, item: &ItemConst) {
         self.item = Some(item.clone());
     }
@@ -493,10 +555,65 @@ impl ConstItem {
     pub fn insert_visibility(&mut self, visibility: MyVisibility) {
         self.visibility = visibility;
     }
+
+    pub fn insert_parent_mod_tree(&mut self, mod_tree: &String) {
+        self.const_name
+            .insert_parent_mod_tree_for_fn_struct_enum_union_trait(mod_tree);
+    }
+
+    pub fn get_const_name(&self) -> &Name {
+        &self.const_name
+    }
+
+    pub fn get_name(&self) -> String {
+        self.const_name.get_name()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroItem {
+    macro_name: Name,
+    #[serde(with = "serde_tokens::option")]
+    item: Option<ItemMacro>,
+}
+
+impl MacroItem {
+    pub fn new() -> Self {
+        MacroItem {
+            macro_name: Name::none(),
+            item: None,
+        }
+    }
+
+    pub fn insert_macro_name(&mut self, macro_name: &String) {
+        self.macro_name = Name::new(macro_name);
+    }
+
+    pub fn insert_item(&mut self, item: &ItemMacro) {
+        self.item = Some(item.clone());
+    }
+
+    pub fn to_item(&self) -> Item {
+        Item::Macro(self.item.clone().unwrap())
+    }
+
+    pub fn insert_parent_mod_tree(&mut self, mod_tree: &String) {
+        self.macro_name
+            .insert_parent_mod_tree_for_fn_struct_enum_union_trait(mod_tree);
+    }
+
+    pub fn get_macro_name(&self) -> &Name {
+        &self.macro_name
+    }
+
+    pub fn get_name(&self) -> String {
+        self.macro_name.get_name()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TraitAliasItem {
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemTraitAlias>,
     visibility: MyVisibility,
 }
@@ -526,8 +643,9 @@ impl TraitAliasItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UseItem {
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemUse>,
     visibility: MyVisibility,
 }
@@ -557,12 +675,14 @@ impl UseItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ModItem {
     mod_name: String,
     file_name: Option<String>,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemMod>,
     // inline: bool,
+    #[serde(with = "serde_tokens::vec")]
     inside_items: Vec<Item>,
     visibility: MyVisibility,
 }
@@ -637,8 +757,10 @@ impl ModItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StaticItem {
+    static_name: Name,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemStatic>,
     visibility: MyVisibility,
 }
@@ -646,11 +768,16 @@ pub struct StaticItem {
 impl StaticItem {
     pub fn new() -> Self {
         StaticItem {
+            static_name: Name::none(),
             item: None,
             visibility: MyVisibility::Pri,
         }
     }
 
+    pub fn insert_static_name(&mut self, static_name: &String) {
+        self.static_name = Name::new(static_name);
+    }
+
     pub fn insert_item(&mut self, item: &ItemStatic) {
         self.item = Some(item.clone());
     }
@@ -666,12 +793,30 @@ impl StaticItem {
     pub fn insert_visibility(&mut self, visibility: MyVisibility) {
         self.visibility = visibility;
     }
+
+    pub fn insert_parent_mod_tree(&mut self, mod_tree: &String) {
+        self.static_name
+            .insert_parent_mod_tree_for_fn_struct_enum_union_trait(mod_tree);
+    }
+
+    pub fn get_static_name(&self) -> &Name {
+        &self.static_name
+    }
+
+    pub fn get_name(&self) -> String {
+        self.static_name.get_name()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TypeItem {
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemType>,
     visibility: MyVisibility,
+    import_name: String,
+    // The aliased type, with the paths in it resolved from the module of the alias.
+    #[serde(with = "serde_tokens::option")]
+    import_type: Option<Type>,
 }
 
 impl TypeItem {
@@ -679,9 +824,28 @@ impl TypeItem {
         TypeItem {
             item: None,
             visibility: MyVisibility::Pri,
+            import_name: String::new(),
+            import_type: None,
         }
     }
 
+    pub fn change_name(&mut self, mod_context: &Rc<RefCell<ModContext>>) {
+        let item = self.get_item();
+        self.import_name =
+            mod_context.borrow().get_mod_tree().to_string() + "::" + &item.ident.to_string();
+        self.import_type = Some(get_import_type(&item.ty, mod_context));
+    }
+
+    // The import name of the alias and the type it stands for, once names are resolved.
+    pub fn get_type_alias(&self) -> Option<(String, Type)> {
+        let import_type = self.import_type.clone()?;
+        Some((self.import_name.clone(), import_type))
+    }
+
+    pub fn get_name(&self) -> String {
+        self.get_item().ident.to_string()
+    }
+
     pub fn insert_item(&mut self, item: &ItemType) {
         self.item = Some(item.clone());
     }
@@ -699,12 +863,14 @@ impl TypeItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FnItem {
     fn_name: Name,
     complete_name_in_file: String,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemFn>,
     // has_items: bool,
+    #[serde(with = "serde_tokens::vec")]
     inside_items: Vec<Item>,
     // application: Applications,
     visibility: MyVisibility,
@@ -768,6 +934,10 @@ impl FnItem {
             .insert_parent_mod_tree_for_fn_struct_enum_union_trait(mod_tree);
     }
 
+    pub fn get_fn_name(&self) -> &Name {
+        &self.fn_name
+    }
+
     pub fn get_name(&self) -> String {
         self.fn_name.get_name()
     }
@@ -801,15 +971,29 @@ impl FnItem {
         Item::Fn(self.item.clone().unwrap())
     }
 
+    pub fn is_const(&self) -> bool {
+        self.item.as_ref().unwrap().sig.constness.is_some()
+    }
+
+    // Leaves `{}`, which doesn't type-check for a fn returning a value.
+    pub fn clear_body(&mut self) {
+        if let Some(item) = self.item.as_mut() {
+            item.block.stmts.clear();
+        }
+    }
+
     pub fn insert_visibility(&mut self, visibility: MyVisibility) {
         self.visibility = visibility;
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImplTypeItem {
+    #[serde(with = "serde_tokens::option")]
     item: Option<ImplItemType>,
     visibility: MyVisibility,
+    #[serde(with = "serde_tokens::option")]
+    import_type: Option<Type>,
 }
 
 impl ImplTypeItem {
@@ -817,9 +1001,24 @@ impl ImplTypeItem {
         ImplTypeItem {
             item: None,
             visibility: MyVisibility::Pri,
+            import_type: None,
         }
     }
 
+    pub fn change_name(&mut self, mod_context: &Rc<RefCell<ModContext>>) {
+        let import_type = get_import_type(&self.get_item().ty, mod_context);
+        self.insert_import_type(&import_type);
+    }
+
+    pub fn insert_import_type(&mut self, import_type: &Type) {
+        self.import_type = Some(import_type.clone());
+    }
+
+    // The associated type, with the paths in it resolved from the module of the impl.
+    pub fn get_import_type(&self) -> Option<&Type> {
+        self.import_type.as_ref()
+    }
+
     pub fn insert_item(&mut self, item: &ImplItemType) {
         self.item = Some(item.clone());
     }
@@ -833,20 +1032,101 @@ impl ImplTypeItem {
     }
 }
 
-#[derive(Debug, Clone)]
+// Associated items of an impl are named the way rustc prints their def paths.
+fn get_impl_item_path(
+    mod_context: &Rc<RefCell<ModContext>>,
+    struct_name: &Name,
+    self_reference: &str,
+    trait_name: &Option<Name>,
+    item_name: &str,
+) -> String {
+    if let None = trait_name {
+        let mod_path = mod_context.borrow().get_mod_tree();
+        let struct_path = struct_name.get_import_name();
+        let mut up_struct_path = struct_path.clone();
+        up_struct_path.up();
+        if mod_path == up_struct_path {
+            let mut item_path = struct_path.clone();
+            item_path.down(&item_name.to_string());
+            item_path.to_string()
+        } else {
+            let mut item_path = mod_path.clone();
+            let struct_path_string = struct_path.to_string();
+            item_path.down(&format!("<impl {}>", struct_path_string));
+            item_path.down(&item_name.to_string());
+            item_path.to_string()
+        }
+    } else {
+        let struct_path = struct_name.get_import_name();
+        let trait_path = trait_name.as_ref().unwrap().get_import_name();
+        format!(
+            "<{}{} as {}>",
+            self_reference,
+            struct_path.to_string(),
+            trait_path.to_string()
+        ) + "::"
+            + item_name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImplConstItem {
+    const_name: Name,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ImplItemConst>,
     visibility: MyVisibility,
+    // The names in its type and initializer.
+    relative_types: Vec<String>,
 }
 
 impl ImplConstItem {
     pub fn new() -> Self {
         ImplConstItem {
+            const_name: Name::none(),
             item: None,
             visibility: MyVisibility::Pri,
+            relative_types: Vec::new(),
         }
     }
 
+    pub fn insert_relative_types(&mut self, relative_types: Vec<String>) {
+        self.relative_types = relative_types;
+    }
+
+    pub fn get_relative_types(&self) -> Vec<String> {
+        self.relative_types.clone()
+    }
+
+    pub fn insert_const_name(&mut self, const_name: &str) {
+        self.const_name = Name::new(&const_name.to_string());
+    }
+
+    pub fn change_name(
+        &mut self,
+        mod_context: &Rc<RefCell<ModContext>>,
+        struct_name: &Name,
+        self_reference: &str,
+        trait_name: &Option<Name>,
+    ) {
+        let complete_name = get_impl_item_path(
+            mod_context,
+            struct_name,
+            self_reference,
+            trait_name,
+            &self.const_name.get_name(),
+        );
+        self.const_name.insert_complete_name(&complete_name);
+        self.const_name.insert_import_name(&complete_name);
+    }
+
+    pub fn get_name(&self) -> String {
+        self.const_name.get_name()
+    }
+
+    pub fn get_complete_name(&self) -> String {
+        self.const_name.get_import_name().to_string()
+    }
+
     pub fn insert_item(&mut self, item: &ImplItemConst) {
         self.item = Some(item.clone());
     }
@@ -860,14 +1140,20 @@ impl ImplConstItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImplFnItem {
     fn_name: Name,
     complete_name_in_file: String,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ImplItemFn>,
     // has_items: bool,
+    #[serde(with = "serde_tokens::vec")]
     inside_items: Vec<Item>,
     visibility: MyVisibility,
+    relative_types: Vec<String>,
+    // The return type, with the paths in it resolved from the module of the impl.
+    #[serde(with = "serde_tokens::option")]
+    import_output: Option<Type>,
 }
 
 impl ImplFnItem {
@@ -878,6 +1164,8 @@ impl ImplFnItem {
             item: None,
             inside_items: Vec::new(),
             visibility: MyVisibility::Pri,
+            relative_types: Vec::new(),
+            import_output: None,
         }
     }
 
@@ -921,6 +1209,25 @@ impl ImplFnItem {
         self.item.clone().unwrap()
     }
 
+    pub fn is_const(&self) -> bool {
+        self.item.as_ref().unwrap().sig.constness.is_some()
+    }
+
+    // Leaves `{}`, which doesn't type-check for a fn returning a value.
+    pub fn clear_body(&mut self) {
+        if let Some(item) = self.item.as_mut() {
+            item.block.stmts.clear();
+        }
+    }
+
+    pub fn insert_relative_types(&mut self, relative_types: Vec<String>) {
+        self.relative_types = relative_types;
+    }
+
+    pub fn get_relative_types(&self) -> Vec<String> {
+        self.relative_types.clone()
+    }
+
     pub fn insert_visibility(&mut self, visibility: MyVisibility) {
         self.visibility = visibility;
     }
@@ -929,42 +1236,36 @@ impl ImplFnItem {
         &mut self,
         mod_context: &Rc<RefCell<ModContext>>,
         struct_name: &Name,
+        self_reference: &str,
         trait_name: &Option<Name>,
     ) {
-        if let None = trait_name {
-            let mod_path = mod_context.borrow().get_mod_tree();
-            let struct_path = struct_name.get_import_name();
-            let mut up_struct_path = struct_path.clone();
-            up_struct_path.up();
-            if mod_path == up_struct_path {
-                let mut fn_path = struct_path.clone();
-                fn_path.down(&self.fn_name.get_name());
-                let complete_name = fn_path.to_string();
-                self.fn_name.insert_complete_name(&complete_name);
-                self.fn_name.insert_import_name(&complete_name);
-            } else {
-                let mut fn_path = mod_path.clone();
-                let struct_path_string = struct_path.to_string();
-                fn_path.down(&format!("<impl {}>", struct_path_string));
-                fn_path.down(&self.fn_name.get_name());
-                let complete_name = fn_path.to_string();
-                self.fn_name.insert_complete_name(&complete_name);
-                self.fn_name.insert_import_name(&complete_name);
-            }
-        } else {
-            let struct_path = struct_name.get_import_name();
-            let trait_path = trait_name.as_ref().unwrap().get_import_name();
-            let fn_path_string = format!(
-                "<{} as {}>",
-                struct_path.to_string(),
-                trait_path.to_string()
-            ) + "::"
-                + &self.fn_name.get_name();
-            self.fn_name.insert_complete_name(&fn_path_string);
-            self.fn_name.insert_import_name(&fn_path_string);
+        let complete_name = get_impl_item_path(
+            mod_context,
+            struct_name,
+            self_reference,
+            trait_name,
+            &self.fn_name.get_name(),
+        );
+        self.insert_complete_name(&complete_name);
+        if let ReturnType::Type(_, output) = &self.get_item().sig.output {
+            let import_output = get_import_type(output, mod_context);
+            self.insert_import_output(&import_output);
         }
     }
 
+    pub fn insert_complete_name(&mut self, complete_name: &String) {
+        self.fn_name.insert_complete_name(complete_name);
+        self.fn_name.insert_import_name(complete_name);
+    }
+
+    pub fn insert_import_output(&mut self, import_output: &Type) {
+        self.import_output = Some(import_output.clone());
+    }
+
+    pub fn get_import_output(&self) -> Option<&Type> {
+        self.import_output.as_ref()
+    }
+
     pub fn get_name(&self) -> String {
         self.fn_name.get_name()
     }
@@ -974,15 +1275,22 @@ impl ImplFnItem {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImplItem {
     impl_num: i32,
     struct_name: Name,
+    self_reference: String,
     trait_name: Option<Name>,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemImpl>,
+    #[serde(with = "serde_tokens::option")]
+    original_item: Option<ItemImpl>,
     types: Vec<ImplTypeItem>,
     consts: Vec<ImplConstItem>,
     functions: Vec<ImplFnItem>,
+    relative_types: Vec<String>,
+    // The module the impl is written in, which a blanket impl's self type doesn't tell.
+    mod_tree: String,
     // applications: Applications,
 }
 
@@ -991,11 +1299,15 @@ impl ImplItem {
         ImplItem {
             impl_num: 0,
             struct_name: Name::none(),
+            self_reference: String::new(),
             trait_name: None,
             item: None,
+            original_item: None,
             types: Vec::new(),
             consts: Vec::new(),
             functions: Vec::new(),
+            relative_types: Vec::new(),
+            mod_tree: String::new(),
             // applications: Applications::new(),
         }
     }
@@ -1012,6 +1324,10 @@ impl ImplItem {
         self.struct_name.insert_import_name(import_name);
     }
 
+    pub fn insert_self_reference(&mut self, self_reference: &str) {
+        self.self_reference = self_reference.to_string();
+    }
+
     pub fn change_struct_name(&mut self, name: &Name) {
         self.struct_name = name.clone();
     }
@@ -1035,6 +1351,10 @@ impl ImplItem {
         self.item = Some(item.clone());
     }
 
+    pub fn insert_original_item(&mut self, item: &ItemImpl) {
+        self.original_item = Some(item.clone());
+    }
+
     pub fn insert_type(&mut self, item: &ImplTypeItem) {
         self.types.push(item.clone());
     }
@@ -1051,6 +1371,14 @@ impl ImplItem {
         return self.impl_num;
     }
 
+    // Impl blocks with the same header, e.g. two `impl Foo` split across a file or in
+    // different modules, are told apart by their number in their module.
+    pub fn is_same_impl(&self, other: &ImplItem) -> bool {
+        self.impl_num == other.impl_num
+            && self.mod_tree == other.mod_tree
+            && self.item == other.item
+    }
+
     pub fn get_item(&self) -> &ItemImpl {
         self.item.as_ref().unwrap()
     }
@@ -1072,12 +1400,17 @@ impl ImplItem {
                 .items
                 .push(syn::ImplItem::Type(impl_type_item.get_item()));
         }
-        for impl_const_item in self.consts.iter() {
+        // The consts and fns are added as they are reached, so they are sorted by name.
+        let mut consts: Vec<&ImplConstItem> = self.consts.iter().collect();
+        consts.sort_by_key(|impl_const_item| impl_const_item.get_item().ident.to_string());
+        for impl_const_item in consts {
             item_impl
                 .items
                 .push(syn::ImplItem::Const(impl_const_item.get_item()));
         }
-        for impl_fn_item in self.functions.iter() {
+        let mut functions: Vec<&ImplFnItem> = self.functions.iter().collect();
+        functions.sort_by_key(|impl_fn_item| impl_fn_item.get_complete_name());
+        for impl_fn_item in functions {
             item_impl
                 .items
                 .push(syn::ImplItem::Fn(impl_fn_item.get_item()));
@@ -1085,10 +1418,26 @@ impl ImplItem {
         Item::Impl(item_impl)
     }
 
+    pub fn to_original_item(&self) -> Item {
+        Item::Impl(self.original_item.clone().unwrap())
+    }
+
     pub fn get_fns(&self) -> &Vec<ImplFnItem> {
         &self.functions
     }
 
+    pub fn get_types(&self) -> &Vec<ImplTypeItem> {
+        &self.types
+    }
+
+    pub fn clear_fn_bodies(&mut self, should_clear: impl Fn(&ImplFnItem) -> bool) {
+        for impl_fn_item in self.functions.iter_mut() {
+            if should_clear(impl_fn_item) {
+                impl_fn_item.clear_body();
+            }
+        }
+    }
+
     pub fn get_struct_name(&self) -> &Name {
         &self.struct_name
     }
@@ -1097,18 +1446,55 @@ impl ImplItem {
         &self.trait_name
     }
 
+    pub fn get_mod_tree(&self) -> Option<String> {
+        (!self.mod_tree.is_empty()).then(|| self.mod_tree.clone())
+    }
+
     pub fn change_function_name(&mut self, mod_context: &Rc<RefCell<ModContext>>) {
+        self.mod_tree = mod_context.borrow().get_mod_tree().to_string();
+        for impl_type_item in self.types.iter_mut() {
+            impl_type_item.change_name(mod_context);
+        }
+        for impl_const_item in self.consts.iter_mut() {
+            impl_const_item.change_name(
+                mod_context,
+                &self.struct_name,
+                &self.self_reference,
+                &self.trait_name,
+            );
+        }
         for function in self.functions.iter_mut() {
-            function.change_name(mod_context, &self.struct_name, &self.trait_name);
+            function.change_name(
+                mod_context,
+                &self.struct_name,
+                &self.self_reference,
+                &self.trait_name,
+            );
         }
     }
 
+    pub fn get_consts(&self) -> &Vec<ImplConstItem> {
+        &self.consts
+    }
+
+    // Associated types are kept, since a trait impl doesn't compile without all of them.
     pub fn clear(&mut self) {
-        self.types.clear();
         self.consts.clear();
         self.functions.clear();
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.consts.is_empty() && self.functions.is_empty()
+    }
+
+    pub fn insert_relative_types(&mut self, relative_types: Vec<String>) {
+        self.relative_types = relative_types;
+    }
+
+    pub fn get_relative_types(&self) -> Vec<String> {
+        self.relative_types.clone()
+    }
+
     // pub fn insert_applications(&mut self, applications: &Vec<String>) {
     //     self.applications.insert_applications(applications);
     // }
@@ -1134,9 +1520,10 @@ impl ImplItem {
     // }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StructItem {
     struct_name: Name,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemStruct>,
     // applications: Applications,
     visibility: MyVisibility,
@@ -1208,9 +1595,10 @@ impl StructItem {
     // }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EnumItem {
     enum_name: Name,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemEnum>,
     // applications: Applications,
     visibility: MyVisibility,
@@ -1282,9 +1670,10 @@ impl EnumItem {
     // }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnionItem {
     union_name: Name,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemUnion>,
     // applications: Applications,
     visibility: MyVisibility,
@@ -1356,14 +1745,28 @@ impl UnionItem {
     // }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraitTypeItem {
+    #[serde(with = "serde_tokens::option")]
     item: Option<TraitItemType>,
+    // The names in its own generics and where clause, its bounds and its default type.
+    relative_types: Vec<String>,
 }
 
 impl TraitTypeItem {
     pub fn new() -> Self {
-        TraitTypeItem { item: None }
+        TraitTypeItem {
+            item: None,
+            relative_types: Vec::new(),
+        }
+    }
+
+    pub fn insert_relative_types(&mut self, relative_types: Vec<String>) {
+        self.relative_types = relative_types;
+    }
+
+    pub fn get_relative_types(&self) -> Vec<String> {
+        self.relative_types.clone()
     }
 
     pub fn insert_item(&mut self, item: &TraitItemType) {
@@ -1375,14 +1778,28 @@ impl TraitTypeItem {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraitConstItem {
+    #[serde(with = "serde_tokens::option")]
     item: Option<TraitItemConst>,
+    // The names in its type and default value.
+    relative_types: Vec<String>,
 }
 
 impl TraitConstItem {
     pub fn new() -> Self {
-        TraitConstItem { item: None }
+        TraitConstItem {
+            item: None,
+            relative_types: Vec::new(),
+        }
+    }
+
+    pub fn insert_relative_types(&mut self, relative_types: Vec<String>) {
+        self.relative_types = relative_types;
+    }
+
+    pub fn get_relative_types(&self) -> Vec<String> {
+        self.relative_types.clone()
     }
 
     pub fn insert_item(&mut self, item: &TraitItemConst) {
@@ -1394,12 +1811,14 @@ impl TraitConstItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TraitFnItem {
     fn_name: Name,
     complete_name_in_file: String,
+    #[serde(with = "serde_tokens::option")]
     item: Option<TraitItemFn>,
     // has_items: bool,
+    #[serde(with = "serde_tokens::vec")]
     inside_items: Vec<Item>,
 }
 
@@ -1461,6 +1880,12 @@ impl TraitFnItem {
         self.fn_name.get_import_name().to_string()
     }
 
+    pub fn clear_body(&mut self) {
+        if let Some(default) = self.item.as_mut().and_then(|item| item.default.as_mut()) {
+            default.stmts.clear();
+        }
+    }
+
     pub fn change_name(&mut self, trait_name: &Name) {
         let struct_path = " ";
         let trait_path = trait_name.get_import_name();
@@ -1472,15 +1897,20 @@ impl TraitFnItem {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraitItem {
     trait_name: Name,
+    #[serde(with = "serde_tokens::option")]
     item: Option<ItemTrait>,
+    #[serde(with = "serde_tokens::option")]
+    original_item: Option<ItemTrait>,
     types: Vec<TraitTypeItem>,
     consts: Vec<TraitConstItem>,
     functions: Vec<TraitFnItem>,
     // applications: Applications,
     visibility: MyVisibility,
+    // The names in its supertraits and generics, e.g. `Foo` of `trait Sub: Iterator<Item = Foo>`.
+    relative_types: Vec<String>,
 }
 
 impl TraitItem {
@@ -1488,11 +1918,13 @@ impl TraitItem {
         TraitItem {
             trait_name: Name::none(),
             item: None,
+            original_item: None,
             types: Vec::new(),
             consts: Vec::new(),
             functions: Vec::new(),
             // applications: Applications::new(),
             visibility: MyVisibility::Pri,
+            relative_types: Vec::new(),
         }
     }
 
@@ -1504,6 +1936,10 @@ impl TraitItem {
         self.item = Some(item.clone());
     }
 
+    pub fn insert_original_item(&mut self, item: &ItemTrait) {
+        self.original_item = Some(item.clone());
+    }
+
     pub fn insert_type(&mut self, item: &TraitTypeItem) {
         self.types.push(item.clone());
     }
@@ -1546,7 +1982,10 @@ impl TraitItem {
                 .items
                 .push(syn::TraitItem::Const(trait_const_item.get_item()));
         }
-        for trait_fn_item in self.functions.iter() {
+        // The fns are added as they are reached, so they are sorted by name.
+        let mut functions: Vec<&TraitFnItem> = self.functions.iter().collect();
+        functions.sort_by_key(|trait_fn_item| trait_fn_item.get_complete_name());
+        for trait_fn_item in functions {
             item_trait
                 .items
                 .push(syn::TraitItem::Fn(trait_fn_item.get_item()));
@@ -1554,10 +1993,34 @@ impl TraitItem {
         Item::Trait(item_trait)
     }
 
+    pub fn to_original_item(&self) -> Item {
+        Item::Trait(self.original_item.clone().unwrap())
+    }
+
     pub fn get_fns(&self) -> &Vec<TraitFnItem> {
         &self.functions
     }
 
+    pub fn insert_relative_types(&mut self, relative_types: Vec<String>) {
+        self.relative_types = relative_types;
+    }
+
+    // With the names of its consts and associated types, which are written with it.
+    pub fn get_relative_types(&self) -> Vec<String> {
+        let mut relative_types = self.relative_types.clone();
+        relative_types.extend(
+            self.consts
+                .iter()
+                .flat_map(|trait_const_item| trait_const_item.get_relative_types()),
+        );
+        relative_types.extend(
+            self.types
+                .iter()
+                .flat_map(|trait_type_item| trait_type_item.get_relative_types()),
+        );
+        relative_types
+    }
+
     pub fn insert_visibility(&mut self, visibility: MyVisibility) {
         self.visibility = visibility;
     }
@@ -1571,12 +2034,19 @@ impl TraitItem {
         self.trait_name.get_name()
     }
 
+    // Associated types and consts are kept, as every impl and every use of the trait relies on them.
     pub fn clear(&mut self) {
-        self.types.clear();
-        self.consts.clear();
         self.functions.clear();
     }
 
+    pub fn clear_fn_bodies(&mut self, should_clear: impl Fn(&TraitFnItem) -> bool) {
+        for trait_fn_item in self.functions.iter_mut() {
+            if should_clear(trait_fn_item) {
+                trait_fn_item.clear_body();
+            }
+        }
+    }
+
     pub fn get_item(&self) -> &ItemTrait {
         self.item.as_ref().unwrap()
     }
@@ -1604,7 +2074,7 @@ impl TraitItem {
     // }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UseTree {
     use_name: String,
     alias_name: Option<String>,
@@ -1660,3 +2130,32 @@ impl UseTree {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn impl_item(impl_num: i32, mod_tree: &str, item: ItemImpl) -> ImplItem {
+        let mut impl_item = ImplItem::new();
+        impl_item.insert_impl_num(impl_num);
+        impl_item.insert_item(&item);
+        impl_item.mod_tree = mod_tree.to_string();
+        impl_item
+    }
+
+    #[test]
+    fn impls_with_the_same_header_are_told_apart() {
+        let first = impl_item(0, "my_crate::a", parse_quote!(impl Foo {}));
+        assert!(first.is_same_impl(&impl_item(0, "my_crate::a", parse_quote!(impl Foo {}))));
+        // A second `impl Foo` in the same module, or the first one of another module.
+        assert!(!first.is_same_impl(&impl_item(1, "my_crate::a", parse_quote!(impl Foo {}))));
+        assert!(!first.is_same_impl(&impl_item(0, "my_crate::b", parse_quote!(impl Foo {}))));
+        assert!(!first.is_same_impl(&impl_item(
+            0,
+            "my_crate::a",
+            parse_quote!(impl Clone for Foo {})
+        )));
+    }
+}
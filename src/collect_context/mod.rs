@@ -1,5 +1,7 @@
 pub mod crate_context;
 mod items_context;
 mod mod_context;
+pub mod options;
 pub mod result;
+mod serde_tokens;
 mod syntax_context;
@@ -1,23 +1,24 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     fmt,
     fs::{self, read_to_string},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
     rc::Rc,
 };
 
-use syn::{parse_file, token::Else, Item};
+use serde::{Deserialize, Serialize};
+use syn::{parse_file, token::Else, Item, Type};
 
 use super::{
     crate_context::{self, CrateContext},
     items_context::{MyPath, MyVisibility, Name, UseTree},
-    result::{FnData, StructData},
+    result::{FnData, ReExport, StructData},
     syntax_context::SyntaxContext,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModModInfo {
     mod_name: String,
     mod_tree: MyPath,
@@ -77,6 +78,10 @@ impl ModModInfo {
         return self.mod_name.clone();
     }
 
+    pub fn get_file_path(&self) -> PathBuf {
+        self.file_path.clone()
+    }
+
     fn get_parent_directory_path(&self) -> PathBuf {
         return self.parent_directory_path.clone();
     }
@@ -86,7 +91,7 @@ impl ModModInfo {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionModInfo {
     function_name: String,
     mod_tree: MyPath,
@@ -129,7 +134,7 @@ impl FunctionModInfo {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ModInfo {
     Mod(ModModInfo),
     Fn(FunctionModInfo),
@@ -172,13 +177,17 @@ impl ModInfo {
     }
 }
 
-#[derive(Clone)]
+// The links back to other mods are left out of the serialized tree and restored by `link_mods`.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ModContext {
     mod_info: ModInfo,
     syntax_context: SyntaxContext,
     sub_mods: Vec<Rc<RefCell<ModContext>>>,
+    #[serde(skip)]
     parent_mod: Option<Rc<RefCell<ModContext>>>,
+    #[serde(skip)]
     crate_mod: Option<Rc<RefCell<ModContext>>>,
+    #[serde(skip)]
     lib_mod: Option<Rc<RefCell<ModContext>>>,
 }
 
@@ -208,6 +217,42 @@ impl ModContext {
         self.lib_mod = Some(Rc::clone(lib_mod));
     }
 
+    // Restores the links of a deserialized tree the way `parse_from_items` sets them.
+    pub fn link_mods(this: &Rc<RefCell<ModContext>>, crate_mod: &Rc<RefCell<ModContext>>) {
+        for sub_mod in this.borrow().sub_mods.iter() {
+            sub_mod.borrow_mut().parent_mod = Some(Rc::clone(this));
+            sub_mod.borrow_mut().crate_mod = Some(Rc::clone(crate_mod));
+            ModContext::link_mods(sub_mod, crate_mod);
+        }
+    }
+
+    // Inline mods and mods in fns are in the file of their parent.
+    pub fn get_mod_files(&self, parent_file_path: &Path, mod_files: &mut HashMap<String, PathBuf>) {
+        let mut file_path = parent_file_path.to_path_buf();
+        if let ModInfo::Mod(mod_mod_info) = &self.mod_info {
+            if !mod_mod_info.get_file_path().as_os_str().is_empty() {
+                file_path = mod_mod_info.get_file_path();
+            }
+        }
+        mod_files.insert(self.get_mod_tree().to_string(), file_path.clone());
+        for sub_mod in self.sub_mods.iter() {
+            sub_mod.borrow().get_mod_files(&file_path, mod_files);
+        }
+    }
+
+    // Inline mods and mods in fns have no file of their own.
+    pub fn get_file_paths(&self, file_paths: &mut BTreeSet<PathBuf>) {
+        if let ModInfo::Mod(mod_mod_info) = &self.mod_info {
+            let file_path = mod_mod_info.get_file_path();
+            if !file_path.as_os_str().is_empty() {
+                file_paths.insert(file_path);
+            }
+        }
+        for sub_mod in self.sub_mods.iter() {
+            sub_mod.borrow().get_file_paths(file_paths);
+        }
+    }
+
     pub fn parse_from_items(
         parent: &Rc<RefCell<ModContext>>,
         items: &Vec<Item>,
@@ -545,6 +590,10 @@ impl ModContext {
         self.syntax_context.get_struct_enum_union_name(name)
     }
 
+    pub fn has_type_alias_in_syntax(&self, name: &str) -> bool {
+        self.syntax_context.has_type_alias(name)
+    }
+
     pub fn get_trait_name_from_syntax(&self, name: &String) -> Name {
         self.syntax_context.get_trait_name(name)
     }
@@ -560,13 +609,45 @@ impl ModContext {
         }
     }
 
-    pub fn get_relative_types_for_struct(&self, name: &String, relative_types: &mut Vec<String>) {
-        self.syntax_context
-            .get_relative_types_for_struct(name, relative_types);
+    pub fn get_type_aliases(&self, type_aliases: &mut Vec<(String, Type)>) {
+        self.syntax_context.get_type_aliases(type_aliases);
         for sub_mod in self.sub_mods.iter() {
-            sub_mod
-                .borrow()
-                .get_relative_types_for_struct(name, relative_types);
+            sub_mod.borrow().get_type_aliases(type_aliases);
+        }
+    }
+
+    fn push_uses(&self, use_trees: &[UseTree], uses: &mut Vec<ReExport>) {
+        let mod_tree = self.mod_info.get_mod_tree().to_string();
+        for use_tree in use_trees.iter() {
+            let target = use_tree.get_use_tree().to_string();
+            let name = use_tree.get_alias().as_ref().unwrap_or(use_tree.get_name());
+            uses.push(ReExport {
+                module: mod_tree.clone(),
+                name: name.clone(),
+                target,
+            });
+        }
+    }
+
+    pub fn get_re_exports(&self, re_exports: &mut Vec<ReExport>) {
+        self.push_uses(&self.get_pub_use(), re_exports);
+        for sub_mod in self.sub_mods.iter() {
+            sub_mod.borrow().get_re_exports(re_exports);
+        }
+    }
+
+    // Every `use` of the module, whatever its visibility.
+    pub fn get_imports(&self, imports: &mut Vec<ReExport>) {
+        self.push_uses(self.syntax_context.get_use_trees(), imports);
+        for sub_mod in self.sub_mods.iter() {
+            sub_mod.borrow().get_imports(imports);
+        }
+    }
+
+    pub fn get_empty_impls(&self, structs: &mut HashMap<String, StructData>) {
+        self.syntax_context.get_empty_impls(structs);
+        for sub_mod in self.sub_mods.iter() {
+            sub_mod.borrow().get_empty_impls(structs);
         }
     }
 
@@ -0,0 +1,213 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocalKind {
+    Fn,
+    Method,
+    TraitFn,
+    Const,
+    Static,
+}
+
+// Which fns of a context keep their bodies. `Auto` leaves it to --elide-bodies and --max-depth.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BodyMode {
+    Auto,
+    Full,
+    Signatures,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContextOptions {
+    pub nested_output: bool,
+    pub render_only: Option<String>,
+    pub focal: Option<String>,
+    pub only_kinds: Vec<FocalKind>,
+    pub const_only: bool,
+    pub name_sep: String,
+    pub verbatim_containers: bool,
+    pub emit_features: bool,
+    pub inner_attrs: Vec<String>,
+    pub layered_output: bool,
+    pub warn_fanout: Option<usize>,
+    pub emit_summaries: bool,
+    pub emit_meta: bool,
+    pub allow_trait_impls: Vec<String>,
+    pub deny_trait_impls: Vec<String>,
+    pub api_surface: bool,
+    pub min_apps: Option<usize>,
+    pub max_apps: Option<usize>,
+    pub max_items_per_focal: Option<usize>,
+    pub max_focals: Option<usize>,
+    pub stub_all: bool,
+    pub elide_bodies: bool,
+    pub max_depth: Option<usize>,
+    pub bodies: BodyMode,
+    pub version_header: bool,
+    pub emit_cargo_toml: bool,
+    pub json_output: bool,
+    pub bundle_mods: bool,
+    pub file_tree: bool,
+    pub source_map: bool,
+    pub report_external: bool,
+    pub include_std: bool,
+    pub report_unresolved: bool,
+    pub emit_graph: bool,
+    pub incremental: bool,
+    pub focal_consts: bool,
+    pub stats: bool,
+    pub trace: bool,
+    pub excludes: Vec<String>,
+}
+
+impl ContextOptions {
+    pub fn new() -> Self {
+        ContextOptions {
+            nested_output: false,
+            render_only: None,
+            focal: None,
+            only_kinds: Vec::new(),
+            const_only: false,
+            name_sep: String::from("::"),
+            verbatim_containers: false,
+            emit_features: false,
+            inner_attrs: Vec::new(),
+            layered_output: false,
+            warn_fanout: None,
+            emit_summaries: false,
+            emit_meta: false,
+            allow_trait_impls: Vec::new(),
+            deny_trait_impls: Vec::new(),
+            api_surface: false,
+            min_apps: None,
+            max_apps: None,
+            max_items_per_focal: None,
+            max_focals: None,
+            stub_all: false,
+            elide_bodies: false,
+            max_depth: None,
+            bodies: BodyMode::Auto,
+            version_header: false,
+            emit_cargo_toml: false,
+            json_output: false,
+            bundle_mods: false,
+            file_tree: false,
+            source_map: false,
+            report_external: false,
+            include_std: false,
+            report_unresolved: false,
+            emit_graph: false,
+            incremental: false,
+            focal_consts: false,
+            stats: false,
+            trace: false,
+            excludes: Vec::new(),
+        }
+    }
+
+    // A trait is matched by its name or by its path as written in the impl.
+    pub fn is_trait_impl_excluded(&self, trait_name: &str, trait_path: &str) -> bool {
+        let matches = |names: &Vec<String>| {
+            names
+                .iter()
+                .any(|name| name.eq(trait_name) || name.eq(trait_path))
+        };
+        (!self.allow_trait_impls.is_empty() && !matches(&self.allow_trait_impls))
+            || matches(&self.deny_trait_impls)
+    }
+
+    // The options that change how a context is written rather than which contexts are, so
+    // that an incremental run with e.g. --focal leaves the hashes of the others valid.
+    pub fn get_render_fingerprint(&self) -> String {
+        let mut options = self.clone();
+        options.render_only = None;
+        options.focal = None;
+        options.only_kinds = Vec::new();
+        options.api_surface = false;
+        options.min_apps = None;
+        options.max_apps = None;
+        options.max_focals = None;
+        options.warn_fanout = None;
+        options.incremental = false;
+        format!("{:?}", options)
+    }
+
+    // A prefix excludes whole path segments, e.g. `my_crate::gen` excludes `my_crate::gen::Foo`
+    // and `<my_crate::gen::Foo as Clone>::clone` but not `my_crate::generic`. Trait fns, named
+    // like `<  as Trait>::f`, are excluded by their trait.
+    pub fn is_excluded(&self, name: &str) -> bool {
+        let name = name.trim_start_matches(['<', '&']).trim_start();
+        let name = name.strip_prefix("as ").unwrap_or(name);
+        self.excludes.iter().any(|exclude| {
+            name.strip_prefix(exclude.as_str()).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with("::") || rest.starts_with([' ', '<', '>'])
+            })
+        })
+    }
+
+    pub fn file_name(&self, complete_function_name: &str) -> String {
+        complete_function_name.replace("::", &self.name_sep)
+    }
+
+    // The extension of a context file, layered or not.
+    pub fn context_extension(&self) -> &'static str {
+        if self.json_output {
+            "json"
+        } else {
+            "rs"
+        }
+    }
+
+    pub fn should_render(&self, focal_kind: FocalKind, complete_function_name: &String) -> bool {
+        if !self.only_kinds.is_empty() && !self.only_kinds.contains(&focal_kind) {
+            return false;
+        }
+        if !self.is_focal(complete_function_name) {
+            return false;
+        }
+        match &self.render_only {
+            Some(render_only) => render_only.eq(complete_function_name),
+            None => true,
+        }
+    }
+
+    // The focal fn is given by its complete name or by the name of its context file.
+    pub fn is_focal(&self, complete_function_name: &str) -> bool {
+        match &self.focal {
+            Some(focal) => {
+                focal.eq(complete_function_name)
+                    || focal.eq(&self.file_name(complete_function_name))
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_whole_path_segments() {
+        let mut options = ContextOptions::new();
+        options.excludes = vec![String::from("my_crate::gen")];
+        assert!(options.is_excluded("my_crate::gen"));
+        assert!(options.is_excluded("my_crate::gen::Foo"));
+        assert!(options.is_excluded("<my_crate::gen::Foo as Clone>::clone"));
+        assert!(options.is_excluded("<&my_crate::gen::Foo as Clone>::clone"));
+        assert!(options.is_excluded("my_crate::gen<u8>"));
+        assert!(!options.is_excluded("my_crate::generic"));
+        assert!(!options.is_excluded("other::my_crate::gen"));
+    }
+
+    #[test]
+    fn excludes_trait_fns_by_their_trait() {
+        let mut options = ContextOptions::new();
+        options.excludes = vec![String::from("my_crate::gen::Gen")];
+        assert!(options.is_excluded("<  as my_crate::gen::Gen>::f"));
+        assert!(!options.is_excluded("<my_crate::Foo as my_crate::gen::Gen>::f"));
+    }
+}
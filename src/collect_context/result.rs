@@ -1,7 +1,13 @@
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
 
 use super::items_context::{
-    EnumItem, FnItem, ImplFnItem, ImplItem, StructItem, TraitFnItem, TraitItem, UnionItem,
+    ConstItem, EnumItem, FnItem, ImplConstItem, ImplFnItem, ImplItem, MacroItem, StaticItem,
+    StructItem, TraitFnItem, TraitItem, UnionItem,
 };
 
 #[derive(Debug, Clone)]
@@ -25,6 +31,10 @@ pub enum StructType {
     Enum(EnumItem),
     Union(UnionItem),
     Trait(TraitItem),
+    Const(ConstItem),
+    Static(StaticItem),
+    Macro(MacroItem),
+    ImplConst(ImplConstItem, ImplItem),
 }
 
 #[derive(Debug, Clone)]
@@ -33,4 +43,85 @@ pub struct StructData {
     pub struct_name: String,
     pub complete_struct_name: String,
     pub struct_type: StructType,
+    pub empty_impls: Vec<ImplItem>,
+}
+
+/// An item of a context, as written with `--json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextEntry {
+    pub module: Option<String>,
+    pub kind: String,
+    pub name: Option<String>,
+    pub code: String,
+}
+
+/// Where an item of a context comes from in the crate, as written with `--source-map`.
+/// Lines and columns start at 1, the end column is the one after the item.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceMapEntry {
+    pub module: Option<String>,
+    pub kind: String,
+    pub name: Option<String>,
+    pub file: Option<PathBuf>,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// A `use` of an item of the crate from another module, e.g. `pub use inner::Bar;` in
+/// `my_crate::a` with the target `my_crate::a::inner::Bar`. A glob's target ends with `::*`.
+/// The `pub` ones are the re-exports of the crate.
+#[derive(Debug, Clone)]
+pub struct ReExport {
+    pub module: String,
+    pub name: String,
+    pub target: String,
+}
+
+/// A written context, as listed in `index.json`. The kind is named like the values of
+/// `--only-kind`, or `trait` and `impl` for --focal-trait and --focal-impl, and the files
+/// are relative to the output directory. The reachability counts, for each item the direct
+/// applications of the focal fn resolve to, how many of them do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub file_name: String,
+    pub files: Vec<String>,
+    pub module: String,
+    pub kind: String,
+    pub items: usize,
+    #[serde(default)]
+    pub reachability: BTreeMap<String, usize>,
+}
+
+/// The size of a context, as printed with `--stats` instead of writing it.
+#[derive(Debug, Clone)]
+pub struct ContextStats {
+    pub name: String,
+    pub items: usize,
+    pub unresolved: usize,
+    pub lines: usize,
+}
+
+/// The signature of a focal fn, as written to `<fn>.meta.json` with `--emit-meta`.
+/// Types, generic params and where predicates are written as Rust source.
+#[derive(Debug, Clone, Serialize)]
+pub struct FocalSignature {
+    pub name: String,
+    pub module: String,
+    pub is_const: bool,
+    pub is_async: bool,
+    pub is_unsafe: bool,
+    pub receiver: Option<String>,
+    pub params: Vec<FocalParam>,
+    pub output: Option<String>,
+    pub generics: Vec<String>,
+    pub where_clauses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FocalParam {
+    pub name: String,
+    pub ty: String,
 }
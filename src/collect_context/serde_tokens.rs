@@ -0,0 +1,55 @@
+use quote::ToTokens;
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+use syn::parse::Parse;
+
+// syn doesn't implement serde, so its nodes are written as their source tokens
+// and parsed back when read. Spans are lost on the way.
+fn to_source(value: &impl ToTokens) -> String {
+    value.to_token_stream().to_string()
+}
+
+fn from_source<T: Parse, E: Error>(source: &str) -> Result<T, E> {
+    syn::parse_str(source).map_err(|err| E::custom(format!("{}: {}", err, source)))
+}
+
+pub mod option {
+    use super::*;
+
+    pub fn serialize<T: ToTokens, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&to_source(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T: Parse, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|source| from_source(&source))
+            .transpose()
+    }
+}
+
+pub mod vec {
+    use super::*;
+
+    pub fn serialize<T: ToTokens, S: Serializer>(
+        values: &[T],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(to_source))
+    }
+
+    pub fn deserialize<'de, T: Parse, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|source| from_source(source))
+            .collect()
+    }
+}
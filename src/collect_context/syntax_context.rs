@@ -1,34 +1,45 @@
 use std::{
     cell::RefCell,
-    collections::{HashMap, HashSet},
-    fs::{create_dir_all, File},
-    io::{Read, Write},
-    path::PathBuf,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::{copy, create_dir_all, read_to_string, File},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, BufWriter, Read, Write},
+    path::{self, PathBuf},
     process::exit,
     rc::Rc,
 };
 
 use call_chain::analysis::exporter::CallsAndTypes;
 use prettyplease::unparse;
-use quote::quote;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use syn::{
-    parse2,
+    parse2, parse_quote, parse_str,
+    spanned::Spanned,
     visit::{self, Visit},
-    Attribute, Expr, Fields, FieldsNamed, GenericParam, Generics, Item, Lit, Meta, Path, Stmt,
-    Type, TypeParamBound, UseTree as SynUseTree, Visibility,
+    visit_mut::VisitMut,
+    Attribute, Block, Expr, Fields, FieldsNamed, FnArg, GenericParam, Generics, Ident, ImplItemFn,
+    Item, ItemFn, ItemImpl, Lit, Meta, Path, ReturnType, Signature, Stmt, TraitBound, TraitItemFn,
+    Type, TypeImplTrait, TypeParamBound, TypePath, UseTree as SynUseTree, Visibility,
+    WherePredicate,
 };
 
 use super::{
-    crate_context::CrateContext,
+    crate_context::{CrateContext, SYSROOT_CRATES},
     items_context::{
         ConstItem, EnumItem, FnItem, FunctionItem, ImplConstItem, ImplFnItem, ImplItem,
-        ImplTypeItem, ModItem, MyPath, MyVisibility, Name, StaticItem, StructItem, TraitAliasItem,
-        TraitConstItem, TraitFnItem, TraitItem, TraitTypeItem, TypeItem, UnionItem, UseItem,
-        UseTree,
+        ImplTypeItem, MacroItem, ModItem, MyPath, MyVisibility, Name, StaticItem, StructItem,
+        TraitAliasItem, TraitConstItem, TraitFnItem, TraitItem, TraitTypeItem, TypeItem, UnionItem,
+        UseItem, UseTree,
     },
     mod_context::ModContext,
-    result::{FnData, FnType, StructData, StructType},
+    options::{BodyMode, ContextOptions, FocalKind},
+    result::{
+        ContextEntry, ContextStats, FnData, FnType, FocalParam, FocalSignature, IndexEntry,
+        ReExport, SourceMapEntry, StructData, StructType,
+    },
 };
 
 use syn::ImplItem as SynImplItem;
@@ -125,6 +136,453 @@ impl<'ast> Visit<'ast> for PathVisitor {
     }
 }
 
+// Clears fn bodies, leaving `{}`, so that only the signatures of a context are written. The
+// context still parses, but a stubbed fn with a return type other than `()` doesn't type-check.
+struct BodyStubber;
+
+impl VisitMut for BodyStubber {
+    fn visit_item_fn_mut(&mut self, item_fn: &mut ItemFn) {
+        item_fn.block.stmts.clear();
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, impl_item_fn: &mut ImplItemFn) {
+        impl_item_fn.block.stmts.clear();
+    }
+
+    fn visit_trait_item_fn_mut(&mut self, trait_item_fn: &mut TraitItemFn) {
+        if let Some(default) = trait_item_fn.default.as_mut() {
+            default.stmts.clear();
+        }
+    }
+}
+
+// Finds the self type of an impl in a type, e.g. in `Option<Self>` or
+// `Result<my_crate::Foo, Error>`. The paths of the type are resolved to import names first (see
+// `get_import_type`) and a path matches as a whole, so `SelfishThing`, `Self::Item` or a `Foo`
+// of another module don't.
+struct SelfTypeVisitor<'a> {
+    self_names: &'a BTreeSet<String>,
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for SelfTypeVisitor<'_> {
+    fn visit_type_path(&mut self, type_path: &'ast TypePath) {
+        let path_string = type_path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<String>>()
+            .join("::");
+        if type_path.qself.is_none() && self.self_names.contains(&path_string) {
+            self.found = true;
+        }
+        // The qself of `<Self as Trait>::Item` is left out, it names a projection.
+        for segment in type_path.path.segments.iter() {
+            visit::visit_path_arguments(self, &segment.arguments);
+        }
+    }
+}
+
+fn names_self_type(ty: &Type, self_names: &BTreeSet<String>) -> bool {
+    let mut visitor = SelfTypeVisitor {
+        self_names,
+        found: false,
+    };
+    visitor.visit_type(ty);
+    visitor.found
+}
+
+// `Self`, the import name of the self type and the type aliases of the crate and associated
+// types of the impl that stand for it, like `type Handle = Foo;`, by their import names and
+// `Self::Handle`. The aliased types are resolved like the types they are compared to.
+fn get_self_names(impl_item: &ImplItem, type_aliases: &[(String, Type)]) -> BTreeSet<String> {
+    let mut self_names = BTreeSet::from([
+        String::from("Self"),
+        impl_item.get_struct_name().get_import_name().to_string(),
+    ]);
+    let mut aliases: Vec<(String, Type)> = impl_item
+        .get_types()
+        .iter()
+        .filter_map(|impl_type_item| {
+            let name = format!("Self::{}", impl_type_item.get_item().ident);
+            Some((name, impl_type_item.get_import_type()?.clone()))
+        })
+        .collect();
+    aliases.extend(type_aliases.iter().cloned());
+    loop {
+        let new_names: Vec<String> = aliases
+            .iter()
+            .filter(|(name, ty)| !self_names.contains(name) && names_self_type(ty, &self_names))
+            .map(|(name, _)| name.clone())
+            .collect();
+        if new_names.is_empty() {
+            return self_names;
+        }
+        self_names.extend(new_names);
+    }
+}
+
+// The layer of an item in `split_layers`: the direct dependencies come first in each list.
+fn get_layer(is_focal: bool, index: usize, direct_count: usize) -> usize {
+    if is_focal {
+        0
+    } else if index < direct_count {
+        1
+    } else {
+        2
+    }
+}
+
+// Keeps the items of `layer` (see `get_layer`) that still fit in `budget`, in their order, and
+// the focal item whatever it costs.
+fn keep_within_budget<T>(
+    items: &[T],
+    kept: &mut Vec<bool>,
+    layer: usize,
+    direct_count: usize,
+    is_focal: impl Fn(&T) -> bool,
+    cost: impl Fn(&T) -> usize,
+    budget: &mut usize,
+) {
+    kept.resize(items.len(), false);
+    for (index, item) in items.iter().enumerate() {
+        let is_focal = is_focal(item);
+        if kept[index] || get_layer(is_focal, index, direct_count) != layer {
+            continue;
+        }
+        if is_focal || cost(item) <= *budget {
+            *budget = budget.saturating_sub(cost(item));
+            kept[index] = true;
+        }
+    }
+}
+
+// Returns how many of the direct dependencies are left.
+fn retain_kept<T>(items: &mut Vec<T>, kept: &[bool], direct_count: usize) -> usize {
+    let mut index = 0;
+    items.retain(|_| {
+        index += 1;
+        kept[index - 1]
+    });
+    kept.iter()
+        .take(direct_count)
+        .filter(|is_kept| **is_kept)
+        .count()
+}
+
+fn has_focal_trait_fn(trait_item: &TraitItem, focal_name: &str) -> bool {
+    trait_item
+        .get_fns()
+        .iter()
+        .any(|trait_fn_item| trait_fn_item.get_complete_name().eq(focal_name))
+}
+
+fn is_focal_trait(trait_item: &TraitItem, focal_name: &str) -> bool {
+    trait_item
+        .get_trait_name()
+        .get_import_name()
+        .to_string()
+        .eq(focal_name)
+        || has_focal_trait_fn(trait_item, focal_name)
+}
+
+fn has_focal_impl_fn(impl_item: &ImplItem, focal_name: &str) -> bool {
+    impl_item
+        .get_fns()
+        .iter()
+        .any(|impl_fn_item| impl_fn_item.get_complete_name().eq(focal_name))
+}
+
+// With --focal-impl, the focal name is the self type of the impls.
+fn is_focal_impl(impl_item: &ImplItem, focal_name: &str) -> bool {
+    impl_item
+        .get_struct_name()
+        .get_import_name()
+        .to_string()
+        .eq(focal_name)
+        || has_focal_impl_fn(impl_item, focal_name)
+}
+
+// With --focal-consts, the focal item is a const or a static.
+fn is_focal_const(const_item: &ConstItem, focal_name: &str) -> bool {
+    const_item
+        .get_const_name()
+        .get_import_name()
+        .to_string()
+        .eq(focal_name)
+}
+
+fn is_focal_static(static_item: &StaticItem, focal_name: &str) -> bool {
+    static_item
+        .get_static_name()
+        .get_import_name()
+        .to_string()
+        .eq(focal_name)
+}
+
+// Keeps the bodies of the focal fn and of the impl fns returning their self type, since
+// those show how the type is built, and leaves the other impl fns with `{}`.
+fn elide_impl_bodies(
+    syntax_context: &SyntaxContext,
+    focal_name: &str,
+    type_aliases: &[(String, Type)],
+) -> SyntaxContext {
+    let mut elided_context = syntax_context.clone();
+    for impl_item in elided_context.impls.iter_mut() {
+        if impl_item
+            .get_struct_name()
+            .get_import_name()
+            .to_string()
+            .eq(focal_name)
+        {
+            continue;
+        }
+        let self_names = get_self_names(impl_item, type_aliases);
+        impl_item.clear_fn_bodies(|impl_fn_item| {
+            let is_constructor = impl_fn_item
+                .get_import_output()
+                .is_some_and(|output| names_self_type(output, &self_names));
+            !is_constructor && !impl_fn_item.get_complete_name().eq(focal_name)
+        });
+    }
+    elided_context
+}
+
+// Collects the first segment of every path that may name another crate, like `serde_json::json!`.
+struct CrateRootVisitor {
+    roots: BTreeSet<String>,
+}
+
+impl CrateRootVisitor {
+    fn new() -> Self {
+        CrateRootVisitor {
+            roots: BTreeSet::new(),
+        }
+    }
+
+    fn insert_use_tree_roots(&mut self, use_tree: &SynUseTree) {
+        match use_tree {
+            SynUseTree::Path(use_path) => {
+                self.roots.insert(use_path.ident.to_string());
+            }
+            SynUseTree::Name(use_name) => {
+                self.roots.insert(use_name.ident.to_string());
+            }
+            SynUseTree::Rename(use_rename) => {
+                self.roots.insert(use_rename.ident.to_string());
+            }
+            SynUseTree::Group(use_group) => {
+                for use_tree in use_group.items.iter() {
+                    self.insert_use_tree_roots(use_tree);
+                }
+            }
+            SynUseTree::Glob(_) => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for CrateRootVisitor {
+    fn visit_path(&mut self, node: &'ast Path) {
+        if node.leading_colon.is_some() || node.segments.len() > 1 {
+            self.roots.insert(node.segments[0].ident.to_string());
+        }
+        visit::visit_path(self, node);
+    }
+
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        self.insert_use_tree_roots(&node.tree);
+    }
+}
+
+// The items of one module of a bundled context and the modules below it.
+#[derive(Default)]
+struct ModuleBundle {
+    items: Vec<Item>,
+    sub_mods: Vec<(String, ModuleBundle)>,
+}
+
+impl ModuleBundle {
+    fn get_sub_mod(&mut self, mod_name: &str) -> &mut ModuleBundle {
+        let position = match self.sub_mods.iter().position(|(name, _)| name.eq(mod_name)) {
+            Some(position) => position,
+            None => {
+                self.sub_mods
+                    .push((mod_name.to_string(), ModuleBundle::default()));
+                self.sub_mods.len() - 1
+            }
+        };
+        &mut self.sub_mods[position].1
+    }
+
+    // `macro_rules!` are textually scoped, so the macros of a module go before its sub mods,
+    // and the sub mods, which export their macros with `#[macro_use]`, before everything else.
+    fn into_items(self) -> Vec<Item> {
+        let (mut items, other_items): (Vec<Item>, Vec<Item>) = self
+            .items
+            .into_iter()
+            .partition(|item| matches!(item, Item::Macro(_)));
+        for (mod_name, sub_mod) in self.sub_mods {
+            let mod_ident = get_mod_ident(&mod_name);
+            let sub_items = sub_mod.into_items();
+            items.push(parse_quote! {
+                #[macro_use]
+                pub mod #mod_ident {
+                    #(#sub_items)*
+                }
+            });
+        }
+        items.extend(other_items);
+        items
+    }
+
+    // The file counterpart of `into_items`: the items go to `file_name` in `directory_path`,
+    // after `prefix`, and each sub mod is declared there and written to `<mod>/mod.rs`.
+    fn write_file_tree(
+        self,
+        directory_path: &PathBuf,
+        file_name: &str,
+        prefix: &str,
+    ) -> io::Result<()> {
+        let (mut items, other_items): (Vec<Item>, Vec<Item>) = self
+            .items
+            .into_iter()
+            .partition(|item| matches!(item, Item::Macro(_)));
+        create_dir_all(directory_path)?;
+        for (mod_name, sub_mod) in self.sub_mods {
+            let mod_ident = get_mod_ident(&mod_name);
+            let mod_directory = mod_ident.to_string().trim_start_matches("r#").to_string();
+            sub_mod.write_file_tree(&directory_path.join(mod_directory), "mod.rs", "")?;
+            items.push(parse_quote! {
+                #[macro_use]
+                pub mod #mod_ident;
+            });
+        }
+        items.extend(other_items);
+        let mut file = File::create(directory_path.join(file_name))?;
+        file.write_all(prefix.as_bytes())?;
+        file.write_all(unparse(&to_syn_file(items)).as_bytes())
+    }
+}
+
+// Module names like `{impl#0}` aren't identifiers, so they are sanitized.
+fn get_mod_ident(mod_name: &str) -> Ident {
+    let mut sanitized_name: String = mod_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !sanitized_name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        sanitized_name.insert(0, '_');
+    }
+    parse_str::<Ident>(&sanitized_name)
+        .unwrap_or_else(|_| Ident::new_raw(&sanitized_name, Span::call_site()))
+}
+
+fn get_module(name: &Name) -> Option<String> {
+    name.get_import_name()
+        .to_string()
+        .rsplit_once("::")
+        .map(|(module, _)| module.to_string())
+}
+
+// `pub use crate::a::inner::Bar as Baz;` for a re-export named `Baz`, with the crate name the
+// target starts with written as `crate`.
+fn get_re_export_item(re_export: &ReExport, visibility: &str) -> Option<Item> {
+    let (_, path) = re_export.target.split_once("::")?;
+    let rename = match path.rsplit("::").next() {
+        Some(last) if last != "*" && last != re_export.name => format!(" as {}", re_export.name),
+        _ => String::new(),
+    };
+    parse_str::<Item>(&format!("{}use crate::{}{};", visibility, path, rename)).ok()
+}
+
+// Collects the first segment of the trait paths in bounds, e.g. `Marker` in `where T: Marker`
+// or `other_mod` in `T: other_mod::Marker`, which resolve through the `use`s of the module.
+struct BoundVisitor {
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for BoundVisitor {
+    fn visit_trait_bound(&mut self, node: &'ast TraitBound) {
+        if node.path.leading_colon.is_none() {
+            if let Some(segment) = node.path.segments.first() {
+                self.names.insert(segment.ident.to_string());
+            }
+        }
+        visit::visit_trait_bound(self, node);
+    }
+}
+
+fn get_item_kind(item: &Item) -> &'static str {
+    match item {
+        Item::Const(_) => "const",
+        Item::Enum(_) => "enum",
+        Item::ExternCrate(_) => "extern_crate",
+        Item::Fn(_) => "fn",
+        Item::ForeignMod(_) => "foreign_mod",
+        Item::Impl(_) => "impl",
+        Item::Macro(_) => "macro",
+        Item::Mod(_) => "mod",
+        Item::Static(_) => "static",
+        Item::Struct(_) => "struct",
+        Item::Trait(_) => "trait",
+        Item::TraitAlias(_) => "trait_alias",
+        Item::Type(_) => "type",
+        Item::Union(_) => "union",
+        Item::Use(_) => "use",
+        _ => "verbatim",
+    }
+}
+
+fn get_last_segment(path: &Path) -> String {
+    path.segments
+        .last()
+        .map(|segment| segment.ident.to_string())
+        .unwrap_or_default()
+}
+
+// Impls are named like the fns in them, as `Type` or `<Type as Trait>`.
+fn get_item_name(item: &Item) -> Option<String> {
+    match item {
+        Item::Const(item_const) => Some(item_const.ident.to_string()),
+        Item::Enum(item_enum) => Some(item_enum.ident.to_string()),
+        Item::Fn(item_fn) => Some(item_fn.sig.ident.to_string()),
+        Item::Impl(item_impl) => {
+            let self_name = match item_impl.self_ty.as_ref() {
+                Type::Path(type_path) => get_last_segment(&type_path.path),
+                Type::Reference(type_reference) => match type_reference.elem.as_ref() {
+                    Type::Path(type_path) => get_last_segment(&type_path.path),
+                    elem => get_self_type_name(elem),
+                },
+                self_ty => get_self_type_name(self_ty),
+            };
+            match &item_impl.trait_ {
+                Some((_, trait_path, _)) => Some(format!(
+                    "<{} as {}>",
+                    self_name,
+                    get_last_segment(trait_path)
+                )),
+                None => Some(self_name),
+            }
+        }
+        Item::Macro(item_macro) => item_macro.ident.as_ref().map(|ident| ident.to_string()),
+        Item::Mod(item_mod) => Some(item_mod.ident.to_string()),
+        Item::Static(item_static) => Some(item_static.ident.to_string()),
+        Item::Struct(item_struct) => Some(item_struct.ident.to_string()),
+        Item::Trait(item_trait) => Some(item_trait.ident.to_string()),
+        Item::TraitAlias(item_trait_alias) => Some(item_trait_alias.ident.to_string()),
+        Item::Type(item_type) => Some(item_type.ident.to_string()),
+        Item::Union(item_union) => Some(item_union.ident.to_string()),
+        _ => None,
+    }
+}
+
 fn visit_generics(generics: &Generics, applications: &mut Vec<String>) {
     let mut visitor = PathVisitor::new();
     for genericparam in generics.params.iter() {
@@ -142,6 +600,46 @@ fn visit_generics(generics: &Generics, applications: &mut Vec<String>) {
             _ => {}
         }
     }
+    // Bounds in where clauses, e.g. `where T: From<Foo>`, carry types as well.
+    if let Some(where_clause) = &generics.where_clause {
+        for predicate in where_clause.predicates.iter() {
+            if let WherePredicate::Type(predicate_type) = predicate {
+                visitor.visit_type(&predicate_type.bounded_ty);
+                for bound in predicate_type.bounds.iter() {
+                    if let TypeParamBound::Trait(trait_bound) = bound {
+                        visitor.visit_path(&trait_bound.path);
+                    }
+                }
+            }
+        }
+    }
+    applications.extend(visitor.paths);
+    applications.sort();
+    applications.dedup();
+}
+
+// Collects the names in the bounds of `impl Trait` types, which are anonymous generics when
+// they are the type of an arg.
+struct ImplTraitVisitor {
+    paths: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for ImplTraitVisitor {
+    fn visit_type_impl_trait(&mut self, node: &'ast TypeImplTrait) {
+        let mut visitor = PathVisitor::new();
+        visitor.visit_type_impl_trait(node);
+        self.paths.extend(visitor.paths);
+    }
+}
+
+// An arg like `x: impl Into<MyType>` has its bounds in its type instead of the generics.
+fn visit_impl_trait_args(signature: &Signature, applications: &mut Vec<String>) {
+    let mut visitor = ImplTraitVisitor { paths: Vec::new() };
+    for input in signature.inputs.iter() {
+        if let FnArg::Typed(pat_type) = input {
+            visitor.visit_type(&pat_type.ty);
+        }
+    }
     applications.extend(visitor.paths);
     applications.sort();
     applications.dedup();
@@ -177,14 +675,184 @@ fn visit_fields_named(fields_named: &FieldsNamed, applications: &mut Vec<String>
     applications.dedup();
 }
 
+const PRIMITIVE_TYPES: [&str; 17] = [
+    "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+    "i128", "isize", "f32", "f64",
+];
+
+// Only a path self type can be resolved through the mod's items and imports;
+// primitives are named as written, like slices and tuples.
+fn has_path_self_type(item_impl: &ItemImpl) -> bool {
+    let self_ty = match item_impl.self_ty.as_ref() {
+        Type::Reference(type_reference) => type_reference.elem.as_ref(),
+        self_ty => self_ty,
+    };
+    match self_ty {
+        Type::Path(type_path) => !type_path
+            .path
+            .get_ident()
+            .is_some_and(|ident| PRIMITIVE_TYPES.contains(&ident.to_string().as_str())),
+        _ => false,
+    }
+}
+
+// Rewrites a matched `<Self as Trait>` of a call or type. Replacing its self type everywhere would
+// also hit the trait, e.g. the `P` of `<P as Pair>::first`.
+fn replace_as(text: &str, qualified_self: &str, self_type: &str, trait_path: &str) -> String {
+    text.replace(
+        qualified_self,
+        &format!("<{} as {}>", self_type, trait_path),
+    )
+}
+
+// Splits `&mut Coll` into `&mut ` and `Coll`, so that only the path gets mod tree prefixes.
+fn split_self_reference(self_type: &str) -> (String, String) {
+    for reference in ["&mut ", "&"] {
+        if let Some(self_path) = self_type.strip_prefix(reference) {
+            return (reference.to_string(), self_path.to_string());
+        }
+    }
+    (String::new(), self_type.to_string())
+}
+
+// Names a self type the way rustc prints it in def paths, e.g. `[u8]`, `(A, B)` or `[u8; 4]`,
+// so that impls on types that aren't paths match their calls. Paths keep only their segments.
+fn get_self_type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<String>>()
+            .join("::"),
+        Type::Reference(type_reference) => format!(
+            "{}{}",
+            if type_reference.mutability.is_some() {
+                "&mut "
+            } else {
+                "&"
+            },
+            get_self_type_name(&type_reference.elem)
+        ),
+        Type::Ptr(type_ptr) => format!(
+            "{}{}",
+            if type_ptr.mutability.is_some() {
+                "*mut "
+            } else {
+                "*const "
+            },
+            get_self_type_name(&type_ptr.elem)
+        ),
+        Type::Slice(type_slice) => format!("[{}]", get_self_type_name(&type_slice.elem)),
+        Type::Array(type_array) => format!(
+            "[{}; {}]",
+            get_self_type_name(&type_array.elem),
+            type_array
+                .len
+                .to_token_stream()
+                .to_string()
+                .replace(' ', "")
+        ),
+        Type::Tuple(type_tuple) if type_tuple.elems.len() == 1 => {
+            format!("({},)", get_self_type_name(&type_tuple.elems[0]))
+        }
+        Type::Tuple(type_tuple) => format!(
+            "({})",
+            type_tuple
+                .elems
+                .iter()
+                .map(get_self_type_name)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        Type::Paren(type_paren) => get_self_type_name(&type_paren.elem),
+        Type::Group(type_group) => get_self_type_name(&type_group.elem),
+        Type::Never(_) => String::from("!"),
+        _ => ty.to_token_stream().to_string(),
+    }
+}
+
+// Drops generic arguments written right after a type name,
+// e.g. `<Hold<Outer> as std::ops::Deref>::deref` -> `<Hold as std::ops::Deref>::deref`.
+fn strip_generic_args(path: &str) -> String {
+    let mut stripped = String::new();
+    let mut depth = 0;
+    let mut last_char: Option<char> = None;
+    for c in path.chars() {
+        if depth > 0 {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+        if c == '<'
+            && last_char.is_some_and(|last_char| last_char.is_alphanumeric() || last_char == '_')
+        {
+            depth = 1;
+            continue;
+        }
+        // turbofish, e.g. the synthetic args of `impl Trait` params in `f::<impl Trait>`
+        if c == '<' && stripped.len() > 2 && stripped.ends_with("::") {
+            stripped.truncate(stripped.len() - 2);
+            depth = 1;
+            continue;
+        }
+        stripped.push(c);
+        last_char = Some(c);
+    }
+    stripped
+}
+
+fn get_relative_types_for_struct(structs: &HashMap<String, StructData>, name: &str) -> Vec<String> {
+    match structs
+        .get(name)
+        .map(|struct_data| &struct_data.struct_type)
+    {
+        Some(StructType::Struct(struct_item)) => struct_item.get_relative_types(),
+        Some(StructType::Enum(enum_item)) => enum_item.get_relative_types(),
+        Some(StructType::Union(union_item)) => union_item.get_relative_types(),
+        _ => Vec::new(),
+    }
+}
+
 fn add_new_calls_and_types(data: &mut CallsAndTypes, mod_trees: &Vec<String>) {
+    let mut stripped_calls: Vec<String> = Vec::new();
+    for call in data.calls.iter() {
+        let stripped_call = strip_generic_args(call);
+        if !stripped_call.eq(call) && !data.calls.contains(&stripped_call) {
+            stripped_calls.push(stripped_call);
+        }
+    }
+    data.calls.extend(stripped_calls);
+    // The candidates grow with calls times mod trees, so membership goes through sets.
+    let mut known_calls: HashSet<String> = data.calls.iter().cloned().collect();
+    let mut known_types: HashSet<String> = data.types.iter().cloned().collect();
     let re_impl = Regex::new(r"<impl\s([^>]+)>").unwrap();
-    let re_as = Regex::new(r"<([^>\s]+)\sas\s([^>\s]+)>").unwrap();
+    // The self type may also be a tuple or an array, which rustc prints with spaces.
+    let re_as =
+        Regex::new(r"<((?:&(?:mut\s)?)?(?:\([^<>()]*\)|\[[^<>\[\]]*\]|[^>\s]+))\sas\s([^>\s]+)>")
+            .unwrap();
     let re_trait_bound = Regex::new(r"(::<[^>\s]+[,\s[^>\s]+]*>)").unwrap();
     let re_struct = Regex::new(r"(<[^>\s]+[,\s[^>\s]+]*>)").unwrap();
+    // Calls through a trait object, e.g. `<dyn Super as Super>::name`, go to the trait's fn,
+    // which is named like `<  as Super>::name`.
+    let re_dyn = Regex::new(r"<dyn\s[^>\s]+\sas\s([^>\s]+)>").unwrap();
     let mut new_calls: HashSet<String> = HashSet::new();
     let mut new_types: HashSet<String> = HashSet::new();
     for call in data.calls.iter() {
+        if let Some(caps) = re_dyn.captures(call) {
+            let trait_call =
+                |trait_path: &str| call.replace(&caps[0], &format!("<  as {}>", trait_path));
+            new_calls.insert(trait_call(&caps[1]));
+            let path = MyPath::new(&caps[1].to_string());
+            for mod_tree in mod_trees.iter() {
+                let mod_tree_path = MyPath::new(mod_tree);
+                new_calls.insert(trait_call(&mod_tree_path.connect(&path).to_string()));
+            }
+        }
         for caps in re_impl.captures_iter(&call) {
             let content = caps[1].to_string();
             let path = MyPath::new(&content);
@@ -198,32 +866,34 @@ fn add_new_calls_and_types(data: &mut CallsAndTypes, mod_trees: &Vec<String>) {
         for caps in re_as.captures_iter(&call) {
             let content1 = caps[1].to_string();
             let content2 = caps[2].to_string();
+            let with_as = |self_type: &str, trait_path: &str| {
+                replace_as(call, &caps[0], self_type, trait_path)
+            };
 
-            let new_call = call.replace(&content2, "");
-            new_calls.insert(new_call);
-            let path = MyPath::new(&content1);
+            new_calls.insert(with_as(&content1, ""));
+            let (self_reference, self_path) = split_self_reference(&content1);
+            let path = MyPath::new(&self_path);
             for mod_tree in mod_trees.iter() {
                 let mod_tree_path = MyPath::new(mod_tree);
-                let new_path = mod_tree_path.connect(&path).to_string();
-                let new_call = call.replace(&content1, &new_path);
-                new_calls.insert(new_call.to_string());
+                let new_path = self_reference.clone() + &mod_tree_path.connect(&path).to_string();
+                new_calls.insert(with_as(&new_path, &content2));
+
+                // a local trait implemented for a local type needs both paths prefixed
+                let trait_path = mod_tree_path.connect(&MyPath::new(&content2)).to_string();
+                new_calls.insert(with_as(&new_path, &trait_path));
 
-                let new_call = new_call.replace(&content2, "");
-                new_calls.insert(new_call);
+                new_calls.insert(with_as(&new_path, ""));
             }
 
-            let new_call = call.replace(&content1, " ");
-            new_calls.insert(new_call.to_string());
+            new_calls.insert(with_as(" ", &content2));
 
             let path = MyPath::new(&content2);
             for mod_tree in mod_trees.iter() {
                 let mod_tree_path = MyPath::new(mod_tree);
                 let new_path = mod_tree_path.connect(&path).to_string();
-                let new_call = call.replace(&content2, &new_path);
-                new_calls.insert(new_call.to_string());
+                new_calls.insert(with_as(&content1, &new_path));
 
-                let new_call = new_call.replace(&content1, " ");
-                new_calls.insert(new_call);
+                new_calls.insert(with_as(" ", &new_path));
             }
         }
         for caps in re_trait_bound.captures_iter(&call) {
@@ -234,7 +904,7 @@ fn add_new_calls_and_types(data: &mut CallsAndTypes, mod_trees: &Vec<String>) {
         }
     }
     for new_call in new_calls {
-        if !data.calls.contains(&new_call) {
+        if known_calls.insert(new_call.clone()) {
             data.calls.push(new_call);
         }
     }
@@ -261,32 +931,30 @@ fn add_new_calls_and_types(data: &mut CallsAndTypes, mod_trees: &Vec<String>) {
         for caps in re_as.captures_iter(&a_type) {
             let content1 = caps[1].to_string();
             let content2 = caps[2].to_string();
+            let with_as = |self_type: &str, trait_path: &str| {
+                replace_as(a_type, &caps[0], self_type, trait_path)
+            };
 
-            let new_type = a_type.replace(&content2, "");
-            new_types.insert(new_type);
-            let path = MyPath::new(&content1);
+            new_types.insert(with_as(&content1, ""));
+            let (self_reference, self_path) = split_self_reference(&content1);
+            let path = MyPath::new(&self_path);
             for mod_tree in mod_trees.iter() {
                 let mod_tree_path = MyPath::new(mod_tree);
-                let new_path = mod_tree_path.connect(&path).to_string();
-                let new_type = a_type.replace(&content1, &new_path);
-                new_types.insert(new_type.to_string());
+                let new_path = self_reference.clone() + &mod_tree_path.connect(&path).to_string();
+                new_types.insert(with_as(&new_path, &content2));
 
-                let new_type = new_type.replace(&content2, "");
-                new_types.insert(new_type);
+                new_types.insert(with_as(&new_path, ""));
             }
 
-            let new_type = a_type.replace(&content1, " ");
-            new_types.insert(new_type.to_string());
+            new_types.insert(with_as(" ", &content2));
 
             let path = MyPath::new(&content2);
             for mod_tree in mod_trees.iter() {
                 let mod_tree_path = MyPath::new(mod_tree);
                 let new_path = mod_tree_path.connect(&path).to_string();
-                let new_type = a_type.replace(&content2, &new_path);
-                new_types.insert(new_type.to_string());
+                new_types.insert(with_as(&content1, &new_path));
 
-                let new_type = new_type.replace(&content1, " ");
-                new_types.insert(new_type);
+                new_types.insert(with_as(" ", &new_path));
             }
         }
         for caps in re_trait_bound.captures_iter(&a_type) {
@@ -302,7 +970,7 @@ fn add_new_calls_and_types(data: &mut CallsAndTypes, mod_trees: &Vec<String>) {
         }
     }
     for new_type in new_types {
-        if !data.types.contains(&new_type) {
+        if known_types.insert(new_type.clone()) {
             data.types.push(new_type);
         }
     }
@@ -316,12 +984,12 @@ fn add_new_calls_and_types(data: &mut CallsAndTypes, mod_trees: &Vec<String>) {
         }
     }
     for new_call in new_calls {
-        if !data.calls.contains(&new_call) {
+        if known_calls.insert(new_call.clone()) {
             data.calls.push(new_call);
         }
     }
     for new_type in new_types {
-        if !data.types.contains(&new_type) {
+        if known_types.insert(new_type.clone()) {
             data.types.push(new_type);
         }
     }
@@ -332,10 +1000,14 @@ fn get_syntax(
     syntax_context: &mut SyntaxContext,
     fns: &HashMap<String, FnData>,
     structs: &HashMap<String, StructData>,
-) {
+    options: &ContextOptions,
+) -> Vec<String> {
+    // The names in the consts brought in, which their initializers may need.
+    let mut const_types: Vec<String> = Vec::new();
     for call in data.calls.iter() {
         let fn_data = fns.get(call);
         if let Some(fn_data) = fn_data {
+            syntax_context.insert_reach(&fn_data.complete_fn_name);
             match &fn_data.fn_type {
                 FnType::Fn(fn_item) => {
                     if !syntax_context.functions.contains(&fn_item) {
@@ -345,12 +1017,24 @@ fn get_syntax(
                 FnType::ImplFn(impl_fn_item, impl_item) => {
                     let mut has_impl = false;
                     for has_impl_item in syntax_context.impls.iter_mut() {
-                        if has_impl_item.get_item().eq(&impl_item.get_item()) {
-                            has_impl_item.insert_function(&impl_fn_item);
+                        if has_impl_item.is_same_impl(impl_item) {
+                            // A fn is called again through a cycle, e.g. of mutually recursive
+                            // methods back to the focal one, and is kept once.
+                            if !has_impl_item.get_fns().contains(impl_fn_item) {
+                                has_impl_item.insert_function(&impl_fn_item);
+                            }
                             has_impl = true;
                         }
                     }
                     if !has_impl {
+                        // A called fn keeps its impl, even when the trait is filtered out.
+                        if is_excluded_trait_impl(impl_item, options) {
+                            eprintln!(
+                                "Warning: keeping the impl of filtered trait {} since {} is called",
+                                impl_item.get_trait_name().as_ref().unwrap().get_name(),
+                                fn_data.complete_fn_name
+                            );
+                        }
                         let mut impl_item = impl_item.clone();
                         impl_item.insert_function(&impl_fn_item);
                         syntax_context.impls.push(impl_item);
@@ -359,6 +1043,7 @@ fn get_syntax(
                         impl_item.get_struct_name().get_import_name().to_string();
                     let struct_item = structs.get(&struct_item_string);
                     if let Some(struct_item) = struct_item {
+                        syntax_context.insert_reach(&struct_item.complete_struct_name);
                         match &struct_item.struct_type {
                             StructType::Struct(struct_item) => {
                                 if !syntax_context.structs.contains(&struct_item) {
@@ -387,13 +1072,16 @@ fn get_syntax(
                     let mut has_trait = false;
                     for has_trait_item in syntax_context.traits.iter_mut() {
                         if has_trait_item.get_item().eq(&trait_item.get_item()) {
-                            has_trait_item.insert_function(&trait_fn_item);
+                            if !has_trait_item.get_fns().contains(trait_fn_item) {
+                                has_trait_item.insert_function(&trait_fn_item);
+                            }
                             has_trait = true;
                         }
                     }
                     if !has_trait {
                         let mut trait_item = trait_item.clone();
                         trait_item.insert_function(&trait_fn_item);
+                        const_types.extend(trait_item.get_relative_types());
                         syntax_context.traits.push(trait_item);
                     }
                     // let trait_item_string =
@@ -433,6 +1121,7 @@ fn get_syntax(
         //     println!("1");
         // }
         if let Some(type_data) = type_data {
+            syntax_context.insert_reach(&type_data.complete_struct_name);
             match &type_data.struct_type {
                 StructType::Struct(struct_item) => {
                     // if a_type.eq("hashbrown::control::bitmask::BitMask") {
@@ -465,55 +1154,323 @@ fn get_syntax(
                     }
                     if !has_trait {
                         let mut trait_item = trait_item.clone();
+                        const_types.extend(trait_item.get_relative_types());
                         syntax_context.traits.push(trait_item);
                     }
                 }
+                StructType::Const(const_item) => {
+                    if !syntax_context.consts.contains(const_item) {
+                        syntax_context.consts.push(const_item.clone());
+                    }
+                }
+                StructType::Static(static_item) => {
+                    if !syntax_context.statics.contains(static_item) {
+                        syntax_context.statics.push(static_item.clone());
+                    }
+                }
+                StructType::Macro(macro_item) => {
+                    if !syntax_context.macros.contains(macro_item) {
+                        syntax_context.macros.push(macro_item.clone());
+                    }
+                }
+                // Only the consts an application refers to, e.g. `Self::BUFFER_SIZE`,
+                // are kept in their impl.
+                StructType::ImplConst(impl_const_item, impl_item) => {
+                    let mut has_impl = false;
+                    for has_impl_item in syntax_context.impls.iter_mut() {
+                        if has_impl_item.is_same_impl(impl_item) {
+                            if !has_impl_item.get_consts().contains(impl_const_item) {
+                                has_impl_item.insert_const(impl_const_item);
+                                const_types.extend(impl_const_item.get_relative_types());
+                            }
+                            has_impl = true;
+                        }
+                    }
+                    if !has_impl {
+                        let mut impl_item = impl_item.clone();
+                        impl_item.insert_const(impl_const_item);
+                        const_types.extend(impl_const_item.get_relative_types());
+                        syntax_context.impls.push(impl_item);
+                    }
+                }
             }
         }
     }
+    const_types
 }
 
-fn parse_callsandtypes(
-    data: &mut CallsAndTypes,
-    mod_trees: &Vec<String>,
+// Impls without items (e.g. marker trait impls) are never reached through a call,
+// so they come with their self type, and the traits in their header come with them.
+fn get_empty_impls(
+    data: &CallsAndTypes,
     syntax_context: &mut SyntaxContext,
-    fns: &HashMap<String, FnData>,
     structs: &HashMap<String, StructData>,
-) {
-    add_new_calls_and_types(data, mod_trees);
-    get_syntax(data, syntax_context, fns, structs);
+    options: &ContextOptions,
+) -> Vec<String> {
+    let mut impl_types: Vec<String> = Vec::new();
+    for a_type in data.types.iter() {
+        if let Some(type_data) = structs.get(a_type) {
+            for empty_impl_item in type_data.empty_impls.iter() {
+                if is_excluded_trait_impl(empty_impl_item, options) {
+                    continue;
+                }
+                let mut has_impl = false;
+                for has_impl_item in syntax_context.impls.iter() {
+                    if has_impl_item.is_same_impl(empty_impl_item) {
+                        has_impl = true;
+                        break;
+                    }
+                }
+                if !has_impl {
+                    syntax_context.impls.push(empty_impl_item.clone());
+                    if let Some(trait_name) = empty_impl_item.get_trait_name() {
+                        impl_types.push(trait_name.get_import_name().to_string());
+                    }
+                    impl_types.extend(empty_impl_item.get_relative_types());
+                }
+            }
+        }
+    }
+    impl_types
 }
 
-// struct PathVisitor {
-//     paths: Vec<String>,
-// }
+// A const fn can only call const fns, so any other fn in its context was over-approximated.
+fn warn_non_const_dependencies(complete_function_name: &String, syntax_context: &SyntaxContext) {
+    for fn_item in syntax_context.functions.iter() {
+        if !fn_item.is_const() {
+            eprintln!(
+                "Warning: const fn {} has non-const fn {} in its context",
+                complete_function_name,
+                fn_item.get_complete_name()
+            );
+        }
+    }
+    for impl_item in syntax_context.impls.iter() {
+        for impl_fn_item in impl_item.get_fns().iter() {
+            if !impl_fn_item.is_const() {
+                eprintln!(
+                    "Warning: const fn {} has non-const fn {} in its context",
+                    complete_function_name,
+                    impl_fn_item.get_complete_name()
+                );
+            }
+        }
+    }
+}
 
-// impl PathVisitor {
-//     fn new() -> Self {
-//         PathVisitor { paths: Vec::new() }
-//     }
-// }
+// A const or static initialized with a literal, e.g. `const N: usize = 4;`, has no context.
+fn is_literal_initializer(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(_) => true,
+        Expr::Unary(expr_unary) => is_literal_initializer(&expr_unary.expr),
+        Expr::Paren(expr_paren) => is_literal_initializer(&expr_paren.expr),
+        _ => false,
+    }
+}
 
-// impl<'ast> Visit<'ast> for PathVisitor {
-//     fn visit_path(&mut self, node: &'ast Path) {
-//         self.paths.extend(
-//             node.segments
-//                 .iter()
-//                 .map(|segment| segment.ident.to_string()),
-//         );
-//         visit::visit_path(self, node);
-//     }
-// }
+fn is_excluded_trait_impl(impl_item: &ImplItem, options: &ContextOptions) -> bool {
+    match impl_item.get_trait_name() {
+        Some(trait_name) => options.is_trait_impl_excluded(
+            &trait_name.get_name(),
+            &trait_name.get_import_name().to_string(),
+        ),
+        None => false,
+    }
+}
 
-// fn visit_stmts(stmts: &Vec<Stmt>, applications: &mut Vec<String>) {
-//     let mut visitor = PathVisitor::new();
-//     for stmt in stmts.iter() {
-//         visitor.visit_stmt(stmt);
-//     }
-//     applications.extend(visitor.paths);
-//     applications.sort();
-//     applications.dedup();
-// }
+// The applications of a focal fn that none of their candidate names resolve, e.g. derived
+// impls or items of dependencies, which the context can't compile without. Names that aren't
+// paths, such as primitives, generic params and closures, and sysroot items are left out.
+fn get_unresolved(
+    data: &CallsAndTypes,
+    mod_trees: &Vec<String>,
+    fns: &HashMap<String, FnData>,
+    structs: &HashMap<String, StructData>,
+    options: &ContextOptions,
+) -> Vec<String> {
+    let mut unresolved: BTreeSet<String> = BTreeSet::new();
+    for name in data.calls.iter().chain(data.types.iter()) {
+        let mut name = name.as_str();
+        while let Some(pointee) = ["&mut ", "&", "*const ", "*mut "]
+            .iter()
+            .find_map(|pointer| name.strip_prefix(pointer))
+        {
+            name = pointee;
+        }
+        let crate_name = name
+            .trim_start_matches('<')
+            .trim_start_matches("dyn ")
+            .split("::")
+            .next()
+            .unwrap();
+        if !name.contains("::")
+            || name.starts_with(['{', '[', '('])
+            || SYSROOT_CRATES.contains(&crate_name)
+            || unresolved.contains(name)
+        {
+            continue;
+        }
+        if options.is_excluded(name) {
+            unresolved.insert(name.to_string());
+            continue;
+        }
+        // Each name is expanded on its own, to know which one its candidates came from.
+        let mut name_data = CallsAndTypes {
+            mod_name: data.mod_name.clone(),
+            calls: vec![name.to_string()],
+            types: vec![name.to_string()],
+            exported: false,
+            external: Vec::new(),
+        };
+        add_new_calls_and_types(&mut name_data, mod_trees);
+        if !name_data
+            .calls
+            .iter()
+            .chain(name_data.types.iter())
+            .any(|candidate| fns.contains_key(candidate) || structs.contains_key(candidate))
+        {
+            unresolved.insert(name.to_string());
+        }
+    }
+    unresolved.into_iter().collect()
+}
+
+// How many distinct direct applications of a focal fn resolve to each item. An application
+// counts once per item, however many of its candidate names resolve to it, and an impl fn
+// reaches its self type too, as it is brought in with it. The items only brought in by the
+// passes over consts and empty impls aren't counted.
+fn get_reachability(
+    data: &CallsAndTypes,
+    mod_trees: &Vec<String>,
+    fns: &HashMap<String, FnData>,
+    structs: &HashMap<String, StructData>,
+    options: &ContextOptions,
+) -> BTreeMap<String, usize> {
+    let mut reachability: BTreeMap<String, usize> = BTreeMap::new();
+    let calls: BTreeSet<&String> = data.calls.iter().collect();
+    let types: BTreeSet<&String> = data.types.iter().collect();
+    for name in calls.union(&types) {
+        // Each application is expanded on its own, to know which one its candidates came from.
+        let mut name_data = CallsAndTypes {
+            mod_name: data.mod_name.clone(),
+            calls: calls
+                .contains(name)
+                .then(|| name.to_string())
+                .into_iter()
+                .collect(),
+            types: types
+                .contains(name)
+                .then(|| name.to_string())
+                .into_iter()
+                .collect(),
+            exported: false,
+            external: Vec::new(),
+        };
+        add_new_calls_and_types(&mut name_data, mod_trees);
+        remove_excluded(&mut name_data, options);
+        let mut items: BTreeSet<&String> = BTreeSet::new();
+        for call in name_data.calls.iter() {
+            let Some(fn_data) = fns.get(call) else {
+                continue;
+            };
+            items.insert(&fn_data.complete_fn_name);
+            if let FnType::ImplFn(_, impl_item) = &fn_data.fn_type {
+                let struct_name = impl_item.get_struct_name().get_import_name().to_string();
+                if let Some(struct_data) = structs.get(&struct_name) {
+                    items.insert(&struct_data.complete_struct_name);
+                }
+            }
+        }
+        for a_type in name_data.types.iter() {
+            if let Some(type_data) = structs.get(a_type) {
+                items.insert(&type_data.complete_struct_name);
+            }
+        }
+        for item in items {
+            *reachability.entry(item.clone()).or_insert(0) += 1;
+        }
+    }
+    reachability
+}
+
+// The applications in modules left out with --exclude are dropped before they are resolved.
+fn remove_excluded(data: &mut CallsAndTypes, options: &ContextOptions) {
+    if options.excludes.is_empty() {
+        return;
+    }
+    data.calls.retain(|call| !options.is_excluded(call));
+    data.types.retain(|a_type| !options.is_excluded(a_type));
+}
+
+fn parse_callsandtypes(
+    data: &mut CallsAndTypes,
+    mod_trees: &Vec<String>,
+    syntax_context: &mut SyntaxContext,
+    fns: &HashMap<String, FnData>,
+    structs: &HashMap<String, StructData>,
+    options: &ContextOptions,
+) {
+    let original_data = data.clone();
+    add_new_calls_and_types(data, mod_trees);
+    remove_excluded(data, options);
+    let const_types = get_syntax(data, syntax_context, fns, structs, options);
+    if options.report_unresolved || options.stats {
+        syntax_context.unresolved =
+            get_unresolved(&original_data, mod_trees, fns, structs, options);
+    }
+    syntax_context.reachability =
+        get_reachability(&original_data, mod_trees, fns, structs, options);
+    syntax_context.direct_counts = ItemCounts::from_syntax_context(syntax_context);
+    let mut impl_types = get_empty_impls(data, syntax_context, structs, options);
+    impl_types.extend(const_types);
+    // The consts brought in by a pass may refer to further consts, each name is resolved once.
+    let mut resolved_types: HashSet<String> = HashSet::new();
+    impl_types.retain(|impl_type| resolved_types.insert(impl_type.clone()));
+    while !impl_types.is_empty() {
+        let mut impl_data = CallsAndTypes {
+            mod_name: data.mod_name.clone(),
+            calls: Vec::new(),
+            types: impl_types,
+            exported: false,
+            external: Vec::new(),
+        };
+        add_new_calls_and_types(&mut impl_data, mod_trees);
+        remove_excluded(&mut impl_data, options);
+        impl_types = get_syntax(&impl_data, syntax_context, fns, structs, options);
+        impl_types.retain(|impl_type| resolved_types.insert(impl_type.clone()));
+    }
+}
+
+// struct PathVisitor {
+//     paths: Vec<String>,
+// }
+
+// impl PathVisitor {
+//     fn new() -> Self {
+//         PathVisitor { paths: Vec::new() }
+//     }
+// }
+
+// impl<'ast> Visit<'ast> for PathVisitor {
+//     fn visit_path(&mut self, node: &'ast Path) {
+//         self.paths.extend(
+//             node.segments
+//                 .iter()
+//                 .map(|segment| segment.ident.to_string()),
+//         );
+//         visit::visit_path(self, node);
+//     }
+// }
+
+// fn visit_stmts(stmts: &Vec<Stmt>, applications: &mut Vec<String>) {
+//     let mut visitor = PathVisitor::new();
+//     for stmt in stmts.iter() {
+//         visitor.visit_stmt(stmt);
+//     }
+//     applications.extend(visitor.paths);
+//     applications.sort();
+//     applications.dedup();
+// }
 
 // fn visit_fn_arg_or_return(types: &Vec<Type>, applications: &mut Vec<String>) {
 //     let mut visitor = PathVisitor::new();
@@ -631,6 +1588,736 @@ fn parse_callsandtypes(
 //     }
 // }
 
+fn write_context_file(
+    output_file_path: &PathBuf,
+    syntax_context: &SyntaxContext,
+    crate_context: &CrateContext,
+) {
+    let options = crate_context.get_options();
+    if options.json_output {
+        let file = File::create(output_file_path).unwrap();
+        serde_json::to_writer_pretty(file, &syntax_context.get_entries(options)).unwrap();
+        return;
+    }
+    let mut writer = BufWriter::new(File::create(output_file_path).unwrap());
+    if options.version_header {
+        writer
+            .write_all(crate_context.get_version_header().as_bytes())
+            .unwrap();
+    }
+    writer
+        .write_all(syntax_context.get_truncation_header().as_bytes())
+        .unwrap();
+    // All selected crate attributes are emitted, not only the ones the items in this context need.
+    let inner_attrs = crate_context.get_inner_attrs();
+    if !inner_attrs.is_empty() {
+        let features = syn::File {
+            shebang: None,
+            attrs: inner_attrs,
+            items: Vec::new(),
+        };
+        writer
+            .write_all((unparse(&features) + "\n").as_bytes())
+            .unwrap();
+    }
+    syntax_context
+        .write_items(&mut writer, crate_context)
+        .unwrap();
+}
+
+// Items are parsed back from their tokens before prettyplease writes them. One that syn can't
+// parse back, e.g. with syntax it doesn't model, is written as it was instead of aborting the run.
+fn to_syn_file(items: Vec<Item>) -> syn::File {
+    match parse2(quote! {#(#items)*}) {
+        Ok(file) => file,
+        Err(err) => {
+            let failed_item = items
+                .iter()
+                .find(|item| parse2::<syn::File>(quote! {#item}).is_err())
+                .map_or(String::from("an item"), |item| {
+                    format!(
+                        "{} {}",
+                        get_item_kind(item),
+                        get_item_name(item).unwrap_or_default()
+                    )
+                });
+            eprintln!(
+                "Warning: {} can't be parsed back ({}), writing it unchanged",
+                failed_item.trim_end(),
+                err
+            );
+            syn::File {
+                shebang: None,
+                attrs: Vec::new(),
+                items,
+            }
+        }
+    }
+}
+
+// With `file_tree`, a context is a directory laid out like the modules of the crate, which
+// `bundle_mods` would nest in one file. The header and the features go to `lib.rs`.
+fn write_context_tree(
+    directory_path: &PathBuf,
+    syntax_context: &SyntaxContext,
+    crate_context: &CrateContext,
+) {
+    let options = crate_context.get_options();
+    let mut prefix = String::new();
+    if options.version_header {
+        prefix += &crate_context.get_version_header();
+    }
+    prefix += &syntax_context.get_truncation_header();
+    let inner_attrs = crate_context.get_inner_attrs();
+    if !inner_attrs.is_empty() {
+        let features = syn::File {
+            shebang: None,
+            attrs: inner_attrs,
+            items: Vec::new(),
+        };
+        prefix += &(unparse(&features) + "\n");
+    }
+    syntax_context
+        .get_module_bundle(crate_context)
+        .write_file_tree(directory_path, "lib.rs", &prefix)
+        .unwrap();
+}
+
+// The in-memory counterpart of `write_context_file`, without the version header comment.
+fn to_context_file(syntax_context: &SyntaxContext, crate_context: &CrateContext) -> syn::File {
+    let options = crate_context.get_options();
+    let attrs = crate_context.get_inner_attrs();
+    let items: Vec<Item> = if options.bundle_mods {
+        syntax_context.get_bundled_items(crate_context)
+    } else {
+        syntax_context.get_items(options).collect()
+    };
+    let mut file = to_syn_file(items);
+    file.attrs = attrs;
+    file
+}
+
+// Covers everything a context's files are written from, so that an unchanged hash means
+// an incremental run can leave them as they are.
+fn get_context_hash(
+    syntax_context: &SyntaxContext,
+    data: &CallsAndTypes,
+    crate_context: &CrateContext,
+) -> u64 {
+    let options = crate_context.get_options();
+    let mut hasher = DefaultHasher::new();
+    options.get_render_fingerprint().hash(&mut hasher);
+    if options.version_header {
+        crate_context.get_version_header().hash(&mut hasher);
+    }
+    unparse(&to_context_file(syntax_context, crate_context)).hash(&mut hasher);
+    serde_json::to_string(data).unwrap().hash(&mut hasher);
+    hasher.finish()
+}
+
+// A short markdown overview of a context, for skimming the output without opening the `.rs` files.
+fn write_summary(
+    summary_file_path: &PathBuf,
+    mod_tree: &str,
+    focal_name: &str,
+    syntax_context: &SyntaxContext,
+) {
+    let mut summary = format!("# `{}`\n\n", focal_name);
+    summary += &format!("- Module: `{}`\n", mod_tree);
+    let type_names = syntax_context.get_type_names();
+    if type_names.is_empty() {
+        summary += "- Types: none\n";
+    } else {
+        let type_names: Vec<String> = type_names
+            .iter()
+            .map(|type_name| format!("`{}`", type_name))
+            .collect();
+        summary += &format!("- Types: {}\n", type_names.join(", "));
+    }
+    summary += &format!(
+        "- Dependencies: {}\n",
+        syntax_context.get_dependency_count(focal_name)
+    );
+    if let Some((visibility, signature)) = syntax_context.get_focal_signature(focal_name) {
+        let item_fn = ItemFn {
+            attrs: Vec::new(),
+            vis: visibility,
+            sig: signature,
+            block: Box::new(Block {
+                brace_token: Default::default(),
+                stmts: Vec::new(),
+            }),
+        };
+        let signature = unparse(&syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![Item::Fn(item_fn)],
+        });
+        let signature = signature.trim_end().trim_end_matches("{}").trim_end();
+        summary += &format!("\n```rust\n{}\n```\n", signature);
+    }
+    let mut file = File::create(summary_file_path).unwrap();
+    file.write_all(summary.as_bytes()).unwrap();
+}
+
+// prettyplease only writes whole files, so a fragment such as a type is written inside an
+// item and cut back out of it.
+fn unparse_fragment(item: Item, prefix: &str, suffix: &str) -> String {
+    let code = unparse(&syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item],
+    });
+    let code = code.split_whitespace().collect::<Vec<&str>>().join(" ");
+    code.strip_prefix(prefix)
+        .and_then(|code| code.strip_suffix(suffix))
+        .unwrap_or(&code)
+        .trim()
+        .to_string()
+}
+
+fn unparse_type(ty: &Type) -> String {
+    unparse_fragment(parse_quote! { type T = #ty; }, "type T =", ";")
+}
+
+fn write_meta(
+    meta_file_path: &PathBuf,
+    mod_tree: &str,
+    focal_name: &str,
+    syntax_context: &SyntaxContext,
+) {
+    let Some((_, signature)) = syntax_context.get_focal_signature(focal_name) else {
+        return;
+    };
+    let mut receiver = None;
+    let mut params = Vec::new();
+    for input in signature.inputs.iter() {
+        match input {
+            FnArg::Receiver(self_arg) => {
+                let mut self_arg = self_arg.clone();
+                self_arg.attrs.clear();
+                receiver = Some(unparse_fragment(
+                    parse_quote! { fn f(#self_arg) {} },
+                    "fn f(",
+                    ") {}",
+                ));
+            }
+            FnArg::Typed(pat_type) => {
+                let pat = &pat_type.pat;
+                params.push(FocalParam {
+                    name: unparse_fragment(parse_quote! { fn f(#pat: ()) {} }, "fn f(", ": ()) {}"),
+                    ty: unparse_type(&pat_type.ty),
+                });
+            }
+        }
+    }
+    let output = match &signature.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, ty) => Some(unparse_type(ty)),
+    };
+    let generics = signature
+        .generics
+        .params
+        .iter()
+        .map(|param| unparse_fragment(parse_quote! { struct S<#param>; }, "struct S<", ">;"))
+        .collect();
+    let where_clauses = match &signature.generics.where_clause {
+        Some(where_clause) => where_clause
+            .predicates
+            .iter()
+            .map(|predicate| {
+                unparse_fragment(
+                    parse_quote! { struct S where #predicate; },
+                    "struct S where",
+                    ";",
+                )
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let focal_signature = FocalSignature {
+        name: focal_name.to_string(),
+        module: mod_tree.to_string(),
+        is_const: signature.constness.is_some(),
+        is_async: signature.asyncness.is_some(),
+        is_unsafe: signature.unsafety.is_some(),
+        receiver,
+        params,
+        output,
+        generics,
+        where_clauses,
+    };
+    let mut file = File::create(meta_file_path).unwrap();
+    file.write_all(
+        serde_json::to_string_pretty(&focal_signature)
+            .unwrap()
+            .as_bytes(),
+    )
+    .unwrap();
+}
+
+fn write_context(
+    output_path: &path::Path,
+    mod_tree: &String,
+    function_name_in_file: &String,
+    focal_name: &str,
+    syntax_context: &SyntaxContext,
+    data: &CallsAndTypes,
+    crate_context: &CrateContext,
+) {
+    if crate_context.is_filtered_by_apps(syntax_context.get_dependency_count(focal_name)) {
+        return;
+    }
+    crate_context.count_context(true);
+    let options = crate_context.get_options();
+    let truncated_context;
+    let syntax_context = match options.max_items_per_focal {
+        Some(max_items) if syntax_context.get_dependency_count(focal_name) > max_items => {
+            crate_context.count_truncated_context();
+            truncated_context = syntax_context.truncate(focal_name, max_items);
+            eprintln!(
+                "Warning: the context of {} has {} items, {} were left out",
+                focal_name,
+                syntax_context.get_dependency_count(focal_name),
+                truncated_context.truncated
+            );
+            &truncated_context
+        }
+        _ => syntax_context,
+    };
+    let elided_context;
+    let syntax_context = if options.elide_bodies {
+        elided_context =
+            elide_impl_bodies(syntax_context, focal_name, crate_context.get_type_aliases());
+        &elided_context
+    } else {
+        syntax_context
+    };
+    let stubbed_context;
+    // Only the focal fn is at depth 0.
+    let max_depth = match options.bodies {
+        BodyMode::Signatures => Some(0),
+        BodyMode::Auto | BodyMode::Full => options.max_depth,
+    };
+    let syntax_context = match max_depth {
+        Some(max_depth) => {
+            stubbed_context = syntax_context.stub_beyond_depth(focal_name, max_depth);
+            &stubbed_context
+        }
+        None => syntax_context,
+    };
+    if crate_context.is_collecting() {
+        let files = if options.layered_output {
+            syntax_context
+                .split_layers(focal_name)
+                .iter()
+                .enumerate()
+                .filter(|(_, layer_context)| !layer_context.is_empty())
+                .map(|(layer, layer_context)| {
+                    (layer, to_context_file(layer_context, crate_context))
+                })
+                .collect()
+        } else {
+            vec![(0, to_context_file(syntax_context, crate_context))]
+        };
+        // Keyed like the files would be, as a fn of a bin next to a lib has the same name as
+        // one of the lib.
+        let collected_name = output_path
+            .join(mod_tree.clone() + "::" + function_name_in_file)
+            .strip_prefix(crate_context.get_output_path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        crate_context.insert_collected_context(&collected_name, files);
+        return;
+    }
+    if options.stats {
+        crate_context.insert_stats(ContextStats {
+            name: mod_tree.clone() + "::" + function_name_in_file,
+            items: syntax_context.get_dependency_count(focal_name),
+            unresolved: syntax_context.unresolved.len(),
+            lines: unparse(&to_context_file(syntax_context, crate_context))
+                .lines()
+                .count(),
+        });
+        return;
+    }
+    let complete_function_name =
+        options.file_name(&(mod_tree.clone() + "::" + function_name_in_file));
+    if options.source_map {
+        crate_context.insert_source_map(
+            &complete_function_name,
+            syntax_context.get_source_map(options, crate_context.get_mod_files()),
+        );
+    }
+    let (output_directory_path, output_file_name) = if options.nested_output {
+        let mut mod_directory_path = output_path.to_path_buf();
+        for mod_name in mod_tree.split("::") {
+            mod_directory_path.push(mod_name);
+        }
+        create_dir_all(&mod_directory_path).unwrap();
+        (mod_directory_path, options.file_name(function_name_in_file))
+    } else {
+        (output_path.to_path_buf(), complete_function_name.clone())
+    };
+    // Relative to the output directory of the crate, which a bin next to a lib is a directory in.
+    let relative_file_name = output_directory_path
+        .join(&output_file_name)
+        .strip_prefix(crate_context.get_output_path())
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    crate_context.insert_file_name(
+        &relative_file_name,
+        &(mod_tree.clone() + "::" + function_name_in_file),
+    );
+    let files = if options.layered_output {
+        syntax_context
+            .split_layers(focal_name)
+            .iter()
+            .enumerate()
+            .filter(|(_, layer_context)| !layer_context.is_empty())
+            .map(|(layer, _)| {
+                format!(
+                    "{}.layer{}.{}",
+                    relative_file_name,
+                    layer,
+                    options.context_extension()
+                )
+            })
+            .collect()
+    } else if options.file_tree {
+        vec![relative_file_name.clone() + "/lib.rs"]
+    } else {
+        vec![format!(
+            "{}.{}",
+            relative_file_name,
+            options.context_extension()
+        )]
+    };
+    let mut output_files: Vec<PathBuf> = files
+        .iter()
+        .map(|file| crate_context.get_output_path().join(file))
+        .collect();
+    if options.emit_cargo_toml {
+        output_files.push(
+            output_directory_path
+                .join(output_file_name.replace(':', "_"))
+                .join("Cargo.toml"),
+        );
+    }
+    if options.emit_meta {
+        output_files.push(output_directory_path.join(output_file_name.clone() + ".meta.json"));
+    }
+    if options.emit_summaries {
+        output_files.push(output_directory_path.join(output_file_name.clone() + ".md"));
+    }
+    crate_context.insert_index_entry(IndexEntry {
+        name: mod_tree.clone() + "::" + function_name_in_file,
+        file_name: relative_file_name.clone(),
+        files,
+        module: mod_tree.clone(),
+        kind: syntax_context.get_focal_kind(focal_name).to_string(),
+        items: syntax_context.get_dependency_count(focal_name),
+        reachability: syntax_context
+            .reachability
+            .iter()
+            .filter(|(item_name, _)| !item_name.as_str().eq(focal_name))
+            .map(|(item_name, count)| (item_name.clone(), *count))
+            .collect(),
+    });
+    if options.emit_graph {
+        crate_context.insert_graph_edges(syntax_context.get_graph_edges(focal_name));
+    }
+    if options.incremental
+        && crate_context.is_context_unchanged(
+            &relative_file_name,
+            get_context_hash(syntax_context, data, crate_context),
+            &output_files,
+        )
+    {
+        return;
+    }
+    if options.layered_output {
+        for (layer, layer_context) in syntax_context.split_layers(focal_name).iter().enumerate() {
+            if !layer_context.is_empty() {
+                let layer_file_path = output_directory_path.join(format!(
+                    "{}.layer{}.{}",
+                    output_file_name,
+                    layer,
+                    options.context_extension()
+                ));
+                write_context_file(&layer_file_path, layer_context, crate_context);
+            }
+        }
+    } else if options.file_tree {
+        write_context_tree(
+            &output_directory_path.join(&output_file_name),
+            syntax_context,
+            crate_context,
+        );
+    } else {
+        let output_file_path = output_directory_path.join(format!(
+            "{}.{}",
+            output_file_name,
+            options.context_extension()
+        ));
+        write_context_file(&output_file_path, syntax_context, crate_context);
+        if options.emit_cargo_toml {
+            // Cargo can't build in a path with `:`, which the default name separator brings.
+            let manifest_directory_path =
+                output_directory_path.join(output_file_name.replace(':', "_"));
+            create_dir_all(&manifest_directory_path).unwrap();
+            let manifest = crate_context.get_context_manifest(
+                &format!("../{}.rs", output_file_name),
+                &syntax_context.get_crate_roots(options),
+            );
+            let mut file = File::create(manifest_directory_path.join("Cargo.toml")).unwrap();
+            file.write_all(manifest.as_bytes()).unwrap();
+        }
+    }
+
+    // call_chain traces the names as it found them, before they are resolved to items.
+    if options.trace {
+        let trace_file_path = output_path
+            .join("trace")
+            .join(format!("{}::{}.json", mod_tree, function_name_in_file));
+        if trace_file_path.is_file() {
+            copy(
+                &trace_file_path,
+                output_directory_path.join(output_file_name.clone() + ".trace.json"),
+            )
+            .unwrap();
+        }
+    }
+
+    if options.emit_meta {
+        let meta_file_path = output_directory_path.join(output_file_name.clone() + ".meta.json");
+        write_meta(&meta_file_path, mod_tree, focal_name, syntax_context);
+    }
+
+    if options.emit_summaries {
+        let summary_file_path = output_directory_path.join(output_file_name + ".md");
+        write_summary(&summary_file_path, mod_tree, focal_name, syntax_context);
+    }
+
+    let directory_path = output_path.join("new_callsandtypes");
+    create_dir_all(&directory_path).unwrap();
+    let file_path = PathBuf::from(&directory_path).join(format!("{}.json", complete_function_name));
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(serde_json::to_string(data).unwrap().as_bytes())
+        .unwrap();
+
+    if options.report_external {
+        crate_context.write_external(output_path, &complete_function_name, &data.external);
+    }
+    if options.include_std {
+        crate_context.write_std(output_path, &complete_function_name, &data.external);
+    }
+    if options.report_unresolved {
+        crate_context.write_unresolved(
+            output_path,
+            &complete_function_name,
+            &syntax_context.unresolved,
+        );
+    }
+}
+
+fn is_impl_of_trait(impl_item: &ImplItem, focal_trait: &String) -> bool {
+    match impl_item.get_trait_name() {
+        Some(trait_name) => trait_name.get_import_name().to_string().eq(focal_trait),
+        None => false,
+    }
+}
+
+// Seeds one context with a trait, all of its fns and every impl of it across the crate.
+// Impls are found through their fns, so blanket impls come along as well.
+pub fn write_trait_context(
+    output_path: &path::Path,
+    focal_trait: &String,
+    mod_trees: &Vec<String>,
+    fns: &HashMap<String, FnData>,
+    structs: &HashMap<String, StructData>,
+    crate_context: &CrateContext,
+) -> bool {
+    match structs.get(focal_trait) {
+        Some(StructData {
+            struct_type: StructType::Trait(_),
+            ..
+        }) => {}
+        _ => return false,
+    }
+    let mut data = CallsAndTypes {
+        mod_name: String::new(),
+        calls: Vec::new(),
+        types: vec![focal_trait.clone()],
+        exported: false,
+        external: Vec::new(),
+    };
+    for fn_data in fns.values() {
+        match &fn_data.fn_type {
+            FnType::TraitFn(_, trait_item) => {
+                if trait_item
+                    .get_trait_name()
+                    .get_import_name()
+                    .to_string()
+                    .eq(focal_trait)
+                {
+                    data.calls.push(fn_data.complete_fn_name.clone());
+                }
+            }
+            FnType::ImplFn(_, impl_item) => {
+                if is_impl_of_trait(impl_item, focal_trait) {
+                    data.calls.push(fn_data.complete_fn_name.clone());
+                    let struct_name = impl_item.get_struct_name().get_import_name().to_string();
+                    if !data.types.contains(&struct_name) {
+                        data.types.push(struct_name);
+                    }
+                    for relative_type in impl_item.get_relative_types() {
+                        if !data.types.contains(&relative_type) {
+                            data.types.push(relative_type);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for struct_data in structs.values() {
+        for empty_impl_item in struct_data.empty_impls.iter() {
+            if is_impl_of_trait(empty_impl_item, focal_trait)
+                && !data.types.contains(&struct_data.complete_struct_name)
+            {
+                data.types.push(struct_data.complete_struct_name.clone());
+            }
+        }
+    }
+    let mut syntax_context = SyntaxContext::new();
+    parse_callsandtypes(
+        &mut data,
+        mod_trees,
+        &mut syntax_context,
+        fns,
+        structs,
+        crate_context.get_options(),
+    );
+    let (mod_tree, trait_name) = match focal_trait.rsplit_once("::") {
+        Some((mod_tree, trait_name)) => (mod_tree.to_string(), trait_name.to_string()),
+        None => (String::new(), focal_trait.clone()),
+    };
+    write_context(
+        output_path,
+        &mod_tree,
+        &trait_name,
+        focal_trait,
+        &syntax_context,
+        &data,
+        crate_context,
+    );
+    true
+}
+
+// Seeds one context with the impls of a type, optionally only the ones of a trait, merging the
+// analyses of all their fns so that what they share is resolved once.
+pub fn write_impl_context(
+    output_path: &path::Path,
+    focal_impl: &String,
+    impl_trait: Option<&String>,
+    mod_trees: &Vec<String>,
+    fns: &HashMap<String, FnData>,
+    structs: &HashMap<String, StructData>,
+    crate_context: &CrateContext,
+) -> bool {
+    match structs.get(focal_impl) {
+        Some(StructData {
+            struct_type: StructType::Struct(_) | StructType::Enum(_) | StructType::Union(_),
+            ..
+        }) => {}
+        _ => return false,
+    }
+    let mut data = CallsAndTypes {
+        mod_name: String::new(),
+        calls: Vec::new(),
+        types: vec![focal_impl.clone()],
+        exported: false,
+        external: Vec::new(),
+    };
+    data.types
+        .extend(get_relative_types_for_struct(structs, focal_impl));
+    let mut fn_datas: Vec<&FnData> = fns.values().collect();
+    fn_datas.sort_by(|a, b| a.complete_fn_name.cmp(&b.complete_fn_name));
+    for fn_data in fn_datas {
+        let FnType::ImplFn(impl_fn_item, impl_item) = &fn_data.fn_type else {
+            continue;
+        };
+        if !impl_item
+            .get_struct_name()
+            .get_import_name()
+            .to_string()
+            .eq(focal_impl)
+            || impl_trait.is_some_and(|impl_trait| !is_impl_of_trait(impl_item, impl_trait))
+        {
+            continue;
+        }
+        let call_file =
+            output_path.join(String::from("callsandtypes/") + &fn_data.complete_fn_name + ".json");
+        match read_to_string(call_file) {
+            Ok(contents) => {
+                let fn_calls_and_types: CallsAndTypes = serde_json::from_str(&contents).unwrap();
+                data.exported |= fn_calls_and_types.exported;
+                data.calls.extend(fn_calls_and_types.calls);
+                data.types.extend(fn_calls_and_types.types);
+                data.external.extend(fn_calls_and_types.external);
+            }
+            Err(_) => crate_context.count_missing_analysis(output_path, &fn_data.complete_fn_name),
+        }
+        data.calls.push(impl_fn_item.get_complete_name());
+        if let Some(trait_name) = impl_item.get_trait_name() {
+            data.types.push(trait_name.get_import_name().to_string());
+        }
+        data.types.extend(impl_item.get_relative_types());
+        data.types.extend(impl_fn_item.get_relative_types());
+    }
+    if let Some(struct_data) = structs.get(focal_impl) {
+        for empty_impl_item in struct_data.empty_impls.iter() {
+            if let Some(trait_name) = empty_impl_item.get_trait_name() {
+                if impl_trait.map_or(true, |impl_trait| {
+                    is_impl_of_trait(empty_impl_item, impl_trait)
+                }) {
+                    data.types.push(trait_name.get_import_name().to_string());
+                }
+            }
+        }
+    }
+    for names in [&mut data.calls, &mut data.types, &mut data.external] {
+        names.sort();
+        names.dedup();
+    }
+    let mut syntax_context = SyntaxContext::new();
+    parse_callsandtypes(
+        &mut data,
+        mod_trees,
+        &mut syntax_context,
+        fns,
+        structs,
+        crate_context.get_options(),
+    );
+    let (mod_tree, struct_name) = match focal_impl.rsplit_once("::") {
+        Some((mod_tree, struct_name)) => (mod_tree.to_string(), struct_name.to_string()),
+        None => (String::new(), focal_impl.clone()),
+    };
+    write_context(
+        output_path,
+        &mod_tree,
+        &struct_name,
+        focal_impl,
+        &syntax_context,
+        &data,
+        crate_context,
+    );
+    true
+}
+
 fn expand_use_tree(
     tree: &SynUseTree,
     visibility: &MyVisibility,
@@ -669,73 +2356,544 @@ fn expand_use_tree(
             } else {
                 path_str = "*".to_string();
             }
-            let use_tree = UseTree::new("*".to_string(), path_str, None, visibility.clone());
-            expanded_trees.push(use_tree);
+            let use_tree = UseTree::new("*".to_string(), path_str, None, visibility.clone());
+            expanded_trees.push(use_tree);
+        }
+        SynUseTree::Rename(use_rename) => {
+            let mut path_str = String::new();
+            if expand_path != String::new() {
+                path_str = expand_path + "::" + &use_rename.ident.to_string();
+                let use_tree = UseTree::new(
+                    use_rename.ident.to_string(),
+                    path_str,
+                    Some(use_rename.rename.to_string()),
+                    visibility.clone(),
+                );
+                expanded_trees.push(use_tree);
+            } else {
+                path_str = use_rename.ident.to_string();
+                let use_tree = UseTree::new(
+                    use_rename.ident.to_string(),
+                    path_str,
+                    Some(use_rename.rename.to_string()),
+                    visibility.clone(),
+                );
+                expanded_trees.push(use_tree);
+            }
+        }
+        SynUseTree::Group(use_group) => {
+            for item in use_group.items.iter() {
+                expand_use_tree(item, visibility, expand_path.clone(), expanded_trees);
+            }
+        }
+    }
+}
+
+// The number of items of each kind a context had when its direct applications were parsed.
+// Items are only appended afterwards, so the ones before these counts are direct dependencies.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ItemCounts {
+    macros: usize,
+    consts: usize,
+    statics: usize,
+    structs: usize,
+    enums: usize,
+    unions: usize,
+    traits: usize,
+    impls: usize,
+    functions: usize,
+}
+
+impl ItemCounts {
+    fn from_syntax_context(syntax_context: &SyntaxContext) -> Self {
+        ItemCounts {
+            macros: syntax_context.macros.len(),
+            consts: syntax_context.consts.len(),
+            statics: syntax_context.statics.len(),
+            structs: syntax_context.structs.len(),
+            enums: syntax_context.enums.len(),
+            unions: syntax_context.unions.len(),
+            traits: syntax_context.traits.len(),
+            impls: syntax_context.impls.len(),
+            functions: syntax_context.functions.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyntaxContext {
+    macros: Vec<MacroItem>,
+    consts: Vec<ConstItem>,
+    trait_aliases: Vec<TraitAliasItem>,
+    uses: Vec<UseItem>,
+    mods: Vec<ModItem>,
+    statics: Vec<StaticItem>,
+    types: Vec<TypeItem>,
+    structs: Vec<StructItem>,
+    enums: Vec<EnumItem>,
+    unions: Vec<UnionItem>,
+    impls: Vec<ImplItem>,
+    functions: Vec<FnItem>,
+    traits: Vec<TraitItem>,
+    use_trees: Vec<UseTree>,
+    // Every item the applications of the focal fn pulled into the context, directly or not.
+    reached: HashSet<String>,
+    // How many distinct direct applications of the focal fn reach each item.
+    reachability: BTreeMap<String, usize>,
+    direct_counts: ItemCounts,
+    // Only known while the context of a focal fn is built.
+    #[serde(skip)]
+    unresolved: Vec<String>,
+    // How many items --max-items-per-focal left out.
+    #[serde(skip)]
+    truncated: usize,
+}
+
+impl SyntaxContext {
+    pub fn new() -> Self {
+        SyntaxContext {
+            macros: Vec::new(),
+            consts: Vec::new(),
+            trait_aliases: Vec::new(),
+            uses: Vec::new(),
+            mods: Vec::new(),
+            statics: Vec::new(),
+            types: Vec::new(),
+            structs: Vec::new(),
+            enums: Vec::new(),
+            unions: Vec::new(),
+            impls: Vec::new(),
+            functions: Vec::new(),
+            traits: Vec::new(),
+            use_trees: Vec::new(),
+            reached: HashSet::new(),
+            reachability: BTreeMap::new(),
+            direct_counts: ItemCounts::default(),
+            unresolved: Vec::new(),
+            truncated: 0,
+        }
+    }
+
+    fn get_focal_kind(&self, focal_name: &str) -> &'static str {
+        if self
+            .functions
+            .iter()
+            .any(|function_item| function_item.get_complete_name().eq(focal_name))
+        {
+            return "fn";
+        }
+        for impl_item in self.impls.iter() {
+            if impl_item
+                .get_fns()
+                .iter()
+                .any(|impl_fn_item| impl_fn_item.get_complete_name().eq(focal_name))
+            {
+                return "method";
+            }
+        }
+        for trait_item in self.traits.iter() {
+            if trait_item
+                .get_trait_name()
+                .get_import_name()
+                .to_string()
+                .eq(focal_name)
+            {
+                return "trait";
+            }
+            if trait_item
+                .get_fns()
+                .iter()
+                .any(|trait_fn_item| trait_fn_item.get_complete_name().eq(focal_name))
+            {
+                return "trait-fn";
+            }
+        }
+        if self
+            .consts
+            .iter()
+            .any(|const_item| is_focal_const(const_item, focal_name))
+        {
+            return "const";
+        }
+        if self
+            .statics
+            .iter()
+            .any(|static_item| is_focal_static(static_item, focal_name))
+        {
+            return "static";
+        }
+        "impl"
+    }
+
+    fn get_focal_signature(&self, focal_name: &str) -> Option<(Visibility, Signature)> {
+        for function_item in self.functions.iter() {
+            if function_item.get_complete_name().eq(focal_name) {
+                if let Item::Fn(item_fn) = function_item.to_item() {
+                    return Some((item_fn.vis, item_fn.sig));
+                }
+            }
+        }
+        for impl_item in self.impls.iter() {
+            for impl_fn_item in impl_item.get_fns().iter() {
+                if impl_fn_item.get_complete_name().eq(focal_name) {
+                    let impl_item_fn = impl_fn_item.get_item();
+                    return Some((impl_item_fn.vis, impl_item_fn.sig));
+                }
+            }
+        }
+        for trait_item in self.traits.iter() {
+            for trait_fn_item in trait_item.get_fns().iter() {
+                if trait_fn_item.get_complete_name().eq(focal_name) {
+                    return Some((Visibility::Inherited, trait_fn_item.get_item().sig));
+                }
+            }
+        }
+        None
+    }
+
+    fn get_type_names(&self) -> Vec<String> {
+        let mut type_names: Vec<String> = Vec::new();
+        for struct_item in self.structs.iter() {
+            type_names.push(struct_item.get_struct_name().get_import_name().to_string());
+        }
+        for enum_item in self.enums.iter() {
+            type_names.push(enum_item.get_enum_name().get_import_name().to_string());
+        }
+        for union_item in self.unions.iter() {
+            type_names.push(union_item.get_union_name().get_import_name().to_string());
+        }
+        for trait_item in self.traits.iter() {
+            type_names.push(trait_item.get_trait_name().get_import_name().to_string());
+        }
+        type_names
+    }
+
+    // The focal fn, const or static is in the context, and isn't a dependency of itself.
+    fn has_focal_item(&self, focal_name: &str) -> bool {
+        self.get_focal_signature(focal_name).is_some()
+            || self
+                .consts
+                .iter()
+                .any(|const_item| is_focal_const(const_item, focal_name))
+            || self
+                .statics
+                .iter()
+                .any(|static_item| is_focal_static(static_item, focal_name))
+    }
+
+    // Every item and every fn of an impl or trait in the context, except the focal item.
+    fn get_dependency_count(&self, focal_name: &str) -> usize {
+        let impl_fn_count: usize = self
+            .impls
+            .iter()
+            .map(|impl_item| impl_item.get_fns().len())
+            .sum();
+        let trait_fn_count: usize = self
+            .traits
+            .iter()
+            .map(|trait_item| trait_item.get_fns().len())
+            .sum();
+        let count = self.macros.len()
+            + self.consts.len()
+            + self.statics.len()
+            + self.types.len()
+            + self.structs.len()
+            + self.enums.len()
+            + self.unions.len()
+            + self.traits.len()
+            + self.functions.len()
+            + impl_fn_count
+            + trait_fn_count;
+        if self.has_focal_item(focal_name) {
+            count - 1
+        } else {
+            count
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.macros.is_empty()
+            && self.consts.is_empty()
+            && self.trait_aliases.is_empty()
+            && self.uses.is_empty()
+            && self.mods.is_empty()
+            && self.statics.is_empty()
+            && self.types.is_empty()
+            && self.structs.is_empty()
+            && self.enums.is_empty()
+            && self.unions.is_empty()
+            && self.impls.is_empty()
+            && self.functions.is_empty()
+            && self.traits.is_empty()
+    }
+
+    // Splits the context by distance from the focal item: layer 0 holds the item of the focal fn
+    // (or the focal trait, const or static), layer 1 its direct dependencies and layer 2 what the impls found
+    // for them bring in.
+    fn split_layers(&self, focal_name: &str) -> Vec<SyntaxContext> {
+        let mut layers = vec![
+            SyntaxContext::new(),
+            SyntaxContext::new(),
+            SyntaxContext::new(),
+        ];
+        for (index, macro_item) in self.macros.iter().enumerate() {
+            let layer = get_layer(false, index, self.direct_counts.macros);
+            layers[layer].macros.push(macro_item.clone());
+        }
+        for (index, const_item) in self.consts.iter().enumerate() {
+            let is_focal = is_focal_const(const_item, focal_name);
+            let layer = get_layer(is_focal, index, self.direct_counts.consts);
+            layers[layer].consts.push(const_item.clone());
+        }
+        for (index, static_item) in self.statics.iter().enumerate() {
+            let is_focal = is_focal_static(static_item, focal_name);
+            let layer = get_layer(is_focal, index, self.direct_counts.statics);
+            layers[layer].statics.push(static_item.clone());
+        }
+        for (index, struct_item) in self.structs.iter().enumerate() {
+            let layer = get_layer(false, index, self.direct_counts.structs);
+            layers[layer].structs.push(struct_item.clone());
+        }
+        for (index, enum_item) in self.enums.iter().enumerate() {
+            let layer = get_layer(false, index, self.direct_counts.enums);
+            layers[layer].enums.push(enum_item.clone());
+        }
+        for (index, union_item) in self.unions.iter().enumerate() {
+            let layer = get_layer(false, index, self.direct_counts.unions);
+            layers[layer].unions.push(union_item.clone());
+        }
+        for (index, trait_item) in self.traits.iter().enumerate() {
+            let is_focal = is_focal_trait(trait_item, focal_name);
+            let layer = get_layer(is_focal, index, self.direct_counts.traits);
+            layers[layer].traits.push(trait_item.clone());
+        }
+        for (index, impl_item) in self.impls.iter().enumerate() {
+            let is_focal = is_focal_impl(impl_item, focal_name);
+            let layer = get_layer(is_focal, index, self.direct_counts.impls);
+            layers[layer].impls.push(impl_item.clone());
+        }
+        for (index, function_item) in self.functions.iter().enumerate() {
+            let is_focal = function_item.get_complete_name().eq(focal_name);
+            let layer = get_layer(is_focal, index, self.direct_counts.functions);
+            layers[layer].functions.push(function_item.clone());
+        }
+        layers[0].truncated = self.truncated;
+        layers
+    }
+
+    // Keeps the focal item and then the items closest to it while they fit in `max_items`,
+    // counted like in `get_dependency_count`.
+    fn truncate(&self, focal_name: &str, max_items: usize) -> SyntaxContext {
+        let mut budget = max_items + self.has_focal_item(focal_name) as usize;
+        let direct_counts = &self.direct_counts;
+        let (mut kept_macros, mut kept_consts, mut kept_statics) =
+            (Vec::new(), Vec::new(), Vec::new());
+        let (mut kept_structs, mut kept_enums, mut kept_unions) =
+            (Vec::new(), Vec::new(), Vec::new());
+        let (mut kept_traits, mut kept_impls, mut kept_functions) =
+            (Vec::new(), Vec::new(), Vec::new());
+        for layer in 0..3 {
+            keep_within_budget(
+                &self.macros,
+                &mut kept_macros,
+                layer,
+                direct_counts.macros,
+                |_| false,
+                |_| 1,
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.consts,
+                &mut kept_consts,
+                layer,
+                direct_counts.consts,
+                |const_item| is_focal_const(const_item, focal_name),
+                |_| 1,
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.statics,
+                &mut kept_statics,
+                layer,
+                direct_counts.statics,
+                |static_item| is_focal_static(static_item, focal_name),
+                |_| 1,
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.structs,
+                &mut kept_structs,
+                layer,
+                direct_counts.structs,
+                |_| false,
+                |_| 1,
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.enums,
+                &mut kept_enums,
+                layer,
+                direct_counts.enums,
+                |_| false,
+                |_| 1,
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.unions,
+                &mut kept_unions,
+                layer,
+                direct_counts.unions,
+                |_| false,
+                |_| 1,
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.traits,
+                &mut kept_traits,
+                layer,
+                direct_counts.traits,
+                |trait_item| is_focal_trait(trait_item, focal_name),
+                |trait_item| 1 + trait_item.get_fns().len(),
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.impls,
+                &mut kept_impls,
+                layer,
+                direct_counts.impls,
+                |impl_item| is_focal_impl(impl_item, focal_name),
+                |impl_item| impl_item.get_fns().len(),
+                &mut budget,
+            );
+            keep_within_budget(
+                &self.functions,
+                &mut kept_functions,
+                layer,
+                direct_counts.functions,
+                |function_item| function_item.get_complete_name().eq(focal_name),
+                |_| 1,
+                &mut budget,
+            );
+        }
+        let mut truncated_context = self.clone();
+        truncated_context.direct_counts = ItemCounts {
+            macros: retain_kept(
+                &mut truncated_context.macros,
+                &kept_macros,
+                direct_counts.macros,
+            ),
+            consts: retain_kept(
+                &mut truncated_context.consts,
+                &kept_consts,
+                direct_counts.consts,
+            ),
+            statics: retain_kept(
+                &mut truncated_context.statics,
+                &kept_statics,
+                direct_counts.statics,
+            ),
+            structs: retain_kept(
+                &mut truncated_context.structs,
+                &kept_structs,
+                direct_counts.structs,
+            ),
+            enums: retain_kept(
+                &mut truncated_context.enums,
+                &kept_enums,
+                direct_counts.enums,
+            ),
+            unions: retain_kept(
+                &mut truncated_context.unions,
+                &kept_unions,
+                direct_counts.unions,
+            ),
+            traits: retain_kept(
+                &mut truncated_context.traits,
+                &kept_traits,
+                direct_counts.traits,
+            ),
+            impls: retain_kept(
+                &mut truncated_context.impls,
+                &kept_impls,
+                direct_counts.impls,
+            ),
+            functions: retain_kept(
+                &mut truncated_context.functions,
+                &kept_functions,
+                direct_counts.functions,
+            ),
+        };
+        truncated_context.truncated = self.get_dependency_count(focal_name)
+            - truncated_context.get_dependency_count(focal_name);
+        truncated_context
+    }
+
+    fn get_truncation_header(&self) -> String {
+        if self.truncated == 0 {
+            String::new()
+        } else {
+            format!("// Truncated: {} items were left out\n", self.truncated)
+        }
+    }
+
+    // The fns of the layers past `max_depth` (see `split_layers`) keep only their signatures.
+    // The trait or impl of a focal method is in layer 0 with it, but at depth 0 only the focal
+    // method keeps its body, a default method of a trait included.
+    fn stub_beyond_depth(&self, focal_name: &str, max_depth: usize) -> SyntaxContext {
+        let mut stubbed_context = self.clone();
+        let direct_counts = &self.direct_counts;
+        for (index, trait_item) in stubbed_context.traits.iter_mut().enumerate() {
+            let is_focal = is_focal_trait(trait_item, focal_name);
+            if get_layer(is_focal, index, direct_counts.traits) > max_depth {
+                trait_item.clear_fn_bodies(|_| true);
+            } else if max_depth == 0 && has_focal_trait_fn(trait_item, focal_name) {
+                trait_item.clear_fn_bodies(|trait_fn_item| {
+                    !trait_fn_item.get_complete_name().eq(focal_name)
+                });
+            }
         }
-        SynUseTree::Rename(use_rename) => {
-            let mut path_str = String::new();
-            if expand_path != String::new() {
-                path_str = expand_path + "::" + &use_rename.ident.to_string();
-                let use_tree = UseTree::new(
-                    use_rename.ident.to_string(),
-                    path_str,
-                    Some(use_rename.rename.to_string()),
-                    visibility.clone(),
-                );
-                expanded_trees.push(use_tree);
-            } else {
-                path_str = use_rename.ident.to_string();
-                let use_tree = UseTree::new(
-                    use_rename.ident.to_string(),
-                    path_str,
-                    Some(use_rename.rename.to_string()),
-                    visibility.clone(),
-                );
-                expanded_trees.push(use_tree);
+        for (index, impl_item) in stubbed_context.impls.iter_mut().enumerate() {
+            let is_focal = is_focal_impl(impl_item, focal_name);
+            if get_layer(is_focal, index, direct_counts.impls) > max_depth {
+                impl_item.clear_fn_bodies(|_| true);
+            } else if max_depth == 0 && has_focal_impl_fn(impl_item, focal_name) {
+                impl_item.clear_fn_bodies(|impl_fn_item| {
+                    !impl_fn_item.get_complete_name().eq(focal_name)
+                });
             }
         }
-        SynUseTree::Group(use_group) => {
-            for item in use_group.items.iter() {
-                expand_use_tree(item, visibility, expand_path.clone(), expanded_trees);
+        for (index, function_item) in stubbed_context.functions.iter_mut().enumerate() {
+            let is_focal = function_item.get_complete_name().eq(focal_name);
+            if get_layer(is_focal, index, direct_counts.functions) > max_depth {
+                function_item.clear_body();
             }
         }
+        stubbed_context
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct SyntaxContext {
-    consts: Vec<ConstItem>,
-    trait_aliases: Vec<TraitAliasItem>,
-    uses: Vec<UseItem>,
-    mods: Vec<ModItem>,
-    statics: Vec<StaticItem>,
-    types: Vec<TypeItem>,
-    structs: Vec<StructItem>,
-    enums: Vec<EnumItem>,
-    unions: Vec<UnionItem>,
-    impls: Vec<ImplItem>,
-    functions: Vec<FnItem>,
-    traits: Vec<TraitItem>,
-    use_trees: Vec<UseTree>,
-}
 
-impl SyntaxContext {
-    pub fn new() -> Self {
-        SyntaxContext {
-            consts: Vec::new(),
-            trait_aliases: Vec::new(),
-            uses: Vec::new(),
-            mods: Vec::new(),
-            statics: Vec::new(),
-            types: Vec::new(),
-            structs: Vec::new(),
-            enums: Vec::new(),
-            unions: Vec::new(),
-            impls: Vec::new(),
-            functions: Vec::new(),
-            traits: Vec::new(),
-            use_trees: Vec::new(),
+    // The focal item references everything it pulled into the context, and the fns of an
+    // impl reference its self type, which came with them.
+    fn get_graph_edges(&self, focal_name: &str) -> Vec<(String, String)> {
+        let mut edges: Vec<(String, String)> = self
+            .reached
+            .iter()
+            .filter(|item_name| !item_name.as_str().eq(focal_name))
+            .map(|item_name| (focal_name.to_string(), item_name.clone()))
+            .collect();
+        for impl_item in self.impls.iter() {
+            let struct_name = impl_item.get_struct_name().get_import_name().to_string();
+            if !self.reached.contains(&struct_name) {
+                continue;
+            }
+            for impl_fn_item in impl_item.get_fns().iter() {
+                edges.push((impl_fn_item.get_complete_name(), struct_name.clone()));
+            }
         }
+        edges
+    }
+
+    fn insert_reach(&mut self, item_name: &str) {
+        self.reached.insert(item_name.to_string());
     }
 
     pub fn from_items(items: &Vec<Item>) -> Self {
@@ -746,12 +2904,25 @@ impl SyntaxContext {
             match item {
                 Item::Const(item_const) => {
                     let mut const_item = ConstItem::new();
+                    const_item.insert_const_name(&item_const.ident.to_string());
                     let mut modified_item_const = item_const.clone();
                     modified_item_const.attrs = delete_doc_attributes(&modified_item_const.attrs);
                     const_item.insert_item(&modified_item_const);
                     const_item.insert_visibility(parse_visibility(&item_const.vis));
                     syntax_context.consts.push(const_item);
                 }
+                // Only `macro_rules!` definitions; other macro invocations are not items of their own.
+                Item::Macro(item_macro) => {
+                    if let Some(ident) = &item_macro.ident {
+                        let mut macro_item = MacroItem::new();
+                        macro_item.insert_macro_name(&ident.to_string());
+                        let mut modified_item_macro = item_macro.clone();
+                        modified_item_macro.attrs =
+                            delete_doc_attributes(&modified_item_macro.attrs);
+                        macro_item.insert_item(&modified_item_macro);
+                        syntax_context.macros.push(macro_item);
+                    }
+                }
                 Item::TraitAlias(item_trait_alias) => {
                     let mut trait_alias_item = TraitAliasItem::new();
                     let mut modified_item_trait_alias = item_trait_alias.clone();
@@ -812,6 +2983,7 @@ impl SyntaxContext {
                 }
                 Item::Static(item_static) => {
                     let mut static_item = StaticItem::new();
+                    static_item.insert_static_name(&item_static.ident.to_string());
                     let mut modified_item_static = item_static.clone();
                     modified_item_static.attrs = delete_doc_attributes(&modified_item_static.attrs);
                     static_item.insert_item(&modified_item_static);
@@ -834,6 +3006,7 @@ impl SyntaxContext {
                     struct_item.insert_item(&modified_item_struct);
                     struct_item.insert_visibility(parse_visibility(&item_struct.vis));
                     let mut relative_types: Vec<String> = Vec::new();
+                    visit_generics(&modified_item_struct.generics, &mut relative_types);
                     visit_fields(&modified_item_struct.fields, &mut relative_types);
                     struct_item.insert_relative_types(relative_types);
                     syntax_context.structs.push(struct_item);
@@ -846,6 +3019,7 @@ impl SyntaxContext {
                     enum_item.insert_item(&modified_item_enum);
                     enum_item.insert_visibility(parse_visibility(&item_enum.vis));
                     let mut relative_types: Vec<String> = Vec::new();
+                    visit_generics(&modified_item_enum.generics, &mut relative_types);
                     for variant in modified_item_enum.variants.iter() {
                         visit_fields(&variant.fields, &mut relative_types);
                     }
@@ -860,6 +3034,7 @@ impl SyntaxContext {
                     union_item.insert_item(&modified_item_union);
                     union_item.insert_visibility(parse_visibility(&item_union.vis));
                     let mut relative_types: Vec<String> = Vec::new();
+                    visit_generics(&modified_item_union.generics, &mut relative_types);
                     visit_fields_named(&modified_item_union.fields, &mut relative_types);
                     union_item.insert_relative_types(relative_types);
                     syntax_context.unions.push(union_item);
@@ -869,18 +3044,32 @@ impl SyntaxContext {
                     impl_item.insert_impl_num(impl_num);
                     impl_num += 1;
                     let mut modified_item_impl = item_impl.clone();
-                    modified_item_impl.items = Vec::new();
                     modified_item_impl.attrs = delete_doc_attributes(&modified_item_impl.attrs);
+                    impl_item.insert_original_item(&modified_item_impl);
+                    modified_item_impl.items = Vec::new();
                     impl_item.insert_item(&modified_item_impl);
-                    let mut struct_name = String::new();
                     let mut import_names: Vec<String> = Vec::new();
-                    let ty = *item_impl.self_ty.clone();
-                    if let Type::Path(ty_path) = ty {
-                        struct_name = ty_path.path.segments.last().unwrap().ident.to_string();
+                    let mut ty = *item_impl.self_ty.clone();
+                    // `impl IntoIterator for &Coll` belongs to `Coll`
+                    let mut self_reference = String::new();
+                    if let Type::Reference(ty_reference) = ty.clone() {
+                        self_reference = match ty_reference.mutability {
+                            Some(_) => String::from("&mut "),
+                            None => String::from("&"),
+                        };
+                        ty = *ty_reference.elem;
+                    }
+                    impl_item.insert_self_reference(&self_reference);
+                    let struct_name = if let Type::Path(ty_path) = ty {
                         for segment in ty_path.path.segments.iter() {
                             import_names.push(segment.ident.to_string());
                         }
-                    }
+                        ty_path.path.segments.last().unwrap().ident.to_string()
+                    } else {
+                        // `impl Trait for [u8]` is keyed by the type as rustc prints it
+                        import_names.push(get_self_type_name(&ty));
+                        get_self_type_name(&ty)
+                    };
                     impl_item.insert_struct_name(&struct_name);
                     impl_item.insert_struct_import_name(&import_names.join("::"));
                     let mut trait_name = String::new();
@@ -902,6 +3091,8 @@ impl SyntaxContext {
                         impl_item.insert_trait_name(&trait_name);
                         impl_item.insert_trait_import_name(&import_names.join("::"));
                     }
+                    let mut relative_types: Vec<String> = Vec::new();
+                    visit_generics(&item_impl.generics, &mut relative_types);
                     for item in item_impl.items.iter() {
                         match item {
                             SynImplItem::Const(item_const) => {
@@ -909,9 +3100,14 @@ impl SyntaxContext {
                                 modified_item_const.attrs =
                                     delete_doc_attributes(&modified_item_const.attrs);
                                 let mut impl_const_item = ImplConstItem::new();
+                                impl_const_item.insert_const_name(&item_const.ident.to_string());
                                 impl_const_item.insert_item(&modified_item_const);
                                 impl_const_item
                                     .insert_visibility(parse_visibility(&item_const.vis));
+                                let mut visitor = PathVisitor::new();
+                                visitor.visit_type(&item_const.ty);
+                                visitor.visit_expr(&item_const.expr);
+                                impl_const_item.insert_relative_types(visitor.paths);
                                 impl_item.insert_const(&impl_const_item);
                             }
                             SynImplItem::Type(item_type) => {
@@ -922,6 +3118,10 @@ impl SyntaxContext {
                                 impl_type_item.insert_item(&modified_item_type);
                                 impl_type_item.insert_visibility(parse_visibility(&item_type.vis));
                                 impl_item.insert_type(&impl_type_item);
+                                visit_generics(&item_type.generics, &mut relative_types);
+                                let mut visitor = PathVisitor::new();
+                                visitor.visit_type(&item_type.ty);
+                                relative_types.extend(visitor.paths);
                             }
                             SynImplItem::Fn(item_fn) => {
                                 let mut impl_fn_item = ImplFnItem::new();
@@ -940,11 +3140,18 @@ impl SyntaxContext {
                                 }
                                 impl_fn_item.insert_items(&inside_items);
                                 impl_fn_item.insert_visibility(parse_visibility(&item_fn.vis));
+                                let mut relative_types: Vec<String> = Vec::new();
+                                visit_generics(&item_fn.sig.generics, &mut relative_types);
+                                visit_impl_trait_args(&item_fn.sig, &mut relative_types);
+                                impl_fn_item.insert_relative_types(relative_types);
                                 impl_item.insert_function(&impl_fn_item);
                             }
                             _ => {}
                         }
                     }
+                    relative_types.sort();
+                    relative_types.dedup();
+                    impl_item.insert_relative_types(relative_types);
                     syntax_context.impls.push(impl_item);
                 }
                 Item::Fn(item_fn) => {
@@ -969,6 +3176,7 @@ impl SyntaxContext {
                     trait_item.insert_trait_name(&item_trait.ident.to_string());
                     let mut modified_item_trait = item_trait.clone();
                     modified_item_trait.attrs = delete_doc_attributes(&modified_item_trait.attrs);
+                    trait_item.insert_original_item(&modified_item_trait);
                     modified_item_trait.items = Vec::new();
                     trait_item.insert_item(&modified_item_trait);
                     for item in item_trait.items.iter() {
@@ -979,6 +3187,12 @@ impl SyntaxContext {
                                     delete_doc_attributes(&modified_item_const.attrs);
                                 let mut trait_const_item = TraitConstItem::new();
                                 trait_const_item.insert_item(&modified_item_const);
+                                let mut visitor = PathVisitor::new();
+                                visitor.visit_type(&item_const.ty);
+                                if let Some((_, default)) = &item_const.default {
+                                    visitor.visit_expr(default);
+                                }
+                                trait_const_item.insert_relative_types(visitor.paths);
                                 trait_item.insert_const(&trait_const_item);
                             }
                             SynTraitItem::Type(item_type) => {
@@ -987,6 +3201,21 @@ impl SyntaxContext {
                                     delete_doc_attributes(&modified_item_type.attrs);
                                 let mut trait_type_item = TraitTypeItem::new();
                                 trait_type_item.insert_item(&modified_item_type);
+                                // A GAT like `type Iter<'a>: Iterator<Item = &'a Foo> where
+                                // Self: 'a;` has generics of its own.
+                                let mut type_relative_types: Vec<String> = Vec::new();
+                                visit_generics(&item_type.generics, &mut type_relative_types);
+                                let mut visitor = PathVisitor::new();
+                                for bound in item_type.bounds.iter() {
+                                    if let TypeParamBound::Trait(trait_bound) = bound {
+                                        visitor.visit_path(&trait_bound.path);
+                                    }
+                                }
+                                if let Some((_, default)) = &item_type.default {
+                                    visitor.visit_type(default);
+                                }
+                                type_relative_types.extend(visitor.paths);
+                                trait_type_item.insert_relative_types(type_relative_types);
                                 trait_item.insert_type(&trait_type_item);
                             }
                             SynTraitItem::Fn(item_fn) => {
@@ -1012,6 +3241,18 @@ impl SyntaxContext {
                             _ => {}
                         }
                     }
+                    let mut relative_types: Vec<String> = Vec::new();
+                    visit_generics(&item_trait.generics, &mut relative_types);
+                    let mut visitor = PathVisitor::new();
+                    for supertrait in item_trait.supertraits.iter() {
+                        if let TypeParamBound::Trait(trait_bound) = supertrait {
+                            visitor.visit_path(&trait_bound.path);
+                        }
+                    }
+                    relative_types.extend(visitor.paths);
+                    relative_types.sort();
+                    relative_types.dedup();
+                    trait_item.insert_relative_types(relative_types);
                     trait_item.insert_visibility(parse_visibility(&item_trait.vis));
                     trait_item.insert_item(&modified_item_trait);
                     syntax_context.traits.push(trait_item);
@@ -1103,6 +3344,15 @@ impl SyntaxContext {
         for trait_item in self.traits.iter_mut() {
             trait_item.insert_parent_mod_tree(mod_tree);
         }
+        for const_item in self.consts.iter_mut() {
+            const_item.insert_parent_mod_tree(mod_tree);
+        }
+        for static_item in self.statics.iter_mut() {
+            static_item.insert_parent_mod_tree(mod_tree);
+        }
+        for macro_item in self.macros.iter_mut() {
+            macro_item.insert_parent_mod_tree(mod_tree);
+        }
     }
 
     pub fn change_use_trees(&mut self, mod_context: &Rc<RefCell<ModContext>>) {
@@ -1111,6 +3361,10 @@ impl SyntaxContext {
         }
     }
 
+    pub fn get_use_trees(&self) -> &Vec<UseTree> {
+        &self.use_trees
+    }
+
     pub fn get_pub_use(&self) -> Vec<UseTree> {
         let mut pub_uses: Vec<UseTree> = Vec::new();
         for use_tree in self.use_trees.iter() {
@@ -1161,9 +3415,11 @@ impl SyntaxContext {
 
     pub fn change_impl_name(&mut self, mod_context: &Rc<RefCell<ModContext>>) {
         for impl_item in self.impls.iter_mut() {
-            let mut name = impl_item.get_struct_name().clone();
-            name.change_name_for_impl_struct_name(mod_context);
-            impl_item.change_struct_name(&name);
+            if has_path_self_type(impl_item.get_item()) {
+                let mut name = impl_item.get_struct_name().clone();
+                name.change_name_for_impl_struct_name(mod_context);
+                impl_item.change_struct_name(&name);
+            }
             let mut name = impl_item.get_trait_name().clone();
             if let Some(_) = name {
                 let mut name = name.as_mut().unwrap();
@@ -1175,6 +3431,9 @@ impl SyntaxContext {
         for trait_item in self.traits.iter_mut() {
             trait_item.change_function_name();
         }
+        for type_item in self.types.iter_mut() {
+            type_item.change_name(mod_context);
+        }
     }
 
     pub fn get_struct_enum_union_name(&self, name: &String) -> Name {
@@ -1239,6 +3498,16 @@ impl SyntaxContext {
         return Name::new(&"".to_string());
     }
 
+    pub fn get_type_aliases(&self, type_aliases: &mut Vec<(String, Type)>) {
+        type_aliases.extend(self.types.iter().filter_map(TypeItem::get_type_alias));
+    }
+
+    pub fn has_type_alias(&self, name: &str) -> bool {
+        self.types
+            .iter()
+            .any(|type_item| type_item.get_name().eq(name))
+    }
+
     pub fn get_result(
         &self,
         fns: &mut HashMap<String, FnData>,
@@ -1263,6 +3532,18 @@ impl SyntaxContext {
                 };
                 fns.insert(fn_data.complete_fn_name.clone(), fn_data);
             }
+            for impl_const_item in impl_item.get_consts().iter() {
+                let const_data = StructData {
+                    struct_name: impl_const_item.get_name(),
+                    complete_struct_name: impl_const_item.get_complete_name(),
+                    struct_type: StructType::ImplConst(
+                        impl_const_item.clone(),
+                        empty_impl_item.clone(),
+                    ),
+                    empty_impls: Vec::new(),
+                };
+                structs.insert(const_data.complete_struct_name.clone(), const_data);
+            }
         }
         for trait_item in self.traits.iter() {
             let mut empty_trait_item = trait_item.clone();
@@ -1279,6 +3560,7 @@ impl SyntaxContext {
                 struct_name: trait_item.get_name(),
                 complete_struct_name: trait_item.get_trait_name().get_import_name().to_string(),
                 struct_type: StructType::Trait(empty_trait_item),
+                empty_impls: Vec::new(),
             };
             structs.insert(struct_data.complete_struct_name.clone(), struct_data);
         }
@@ -1287,6 +3569,7 @@ impl SyntaxContext {
                 struct_name: struct_item.get_name(),
                 complete_struct_name: struct_item.get_struct_name().get_import_name().to_string(),
                 struct_type: StructType::Struct(struct_item.clone()),
+                empty_impls: Vec::new(),
             };
             structs.insert(struct_data.complete_struct_name.clone(), struct_data);
         }
@@ -1295,6 +3578,7 @@ impl SyntaxContext {
                 struct_name: enum_item.get_name(),
                 complete_struct_name: enum_item.get_enum_name().get_import_name().to_string(),
                 struct_type: StructType::Enum(enum_item.clone()),
+                empty_impls: Vec::new(),
             };
             structs.insert(enum_data.complete_struct_name.clone(), enum_data);
         }
@@ -1303,43 +3587,47 @@ impl SyntaxContext {
                 struct_name: union_item.get_name(),
                 complete_struct_name: union_item.get_union_name().get_import_name().to_string(),
                 struct_type: StructType::Union(union_item.clone()),
+                empty_impls: Vec::new(),
             };
             structs.insert(union_data.complete_struct_name.clone(), union_data);
         }
+        for const_item in self.consts.iter() {
+            let const_data = StructData {
+                struct_name: const_item.get_name(),
+                complete_struct_name: const_item.get_const_name().get_import_name().to_string(),
+                struct_type: StructType::Const(const_item.clone()),
+                empty_impls: Vec::new(),
+            };
+            structs.insert(const_data.complete_struct_name.clone(), const_data);
+        }
+        for static_item in self.statics.iter() {
+            let static_data = StructData {
+                struct_name: static_item.get_name(),
+                complete_struct_name: static_item.get_static_name().get_import_name().to_string(),
+                struct_type: StructType::Static(static_item.clone()),
+                empty_impls: Vec::new(),
+            };
+            structs.insert(static_data.complete_struct_name.clone(), static_data);
+        }
+        for macro_item in self.macros.iter() {
+            let macro_data = StructData {
+                struct_name: macro_item.get_name(),
+                complete_struct_name: macro_item.get_macro_name().get_import_name().to_string(),
+                struct_type: StructType::Macro(macro_item.clone()),
+                empty_impls: Vec::new(),
+            };
+            structs.insert(macro_data.complete_struct_name.clone(), macro_data);
+        }
     }
 
-    pub fn get_relative_types_for_struct(&self, name: &String, relative_types: &mut Vec<String>) {
-        for struct_item in self.structs.iter() {
-            if struct_item
-                .get_struct_name()
-                .get_import_name()
-                .to_string()
-                .eq(name)
-            {
-                *relative_types = struct_item.get_relative_types();
-                return;
-            }
-        }
-        for enum_item in self.enums.iter() {
-            if enum_item
-                .get_enum_name()
-                .get_import_name()
-                .to_string()
-                .eq(name)
-            {
-                *relative_types = enum_item.get_relative_types();
-                return;
+    pub fn get_empty_impls(&self, structs: &mut HashMap<String, StructData>) {
+        for impl_item in self.impls.iter() {
+            if !impl_item.is_empty() {
+                continue;
             }
-        }
-        for union_item in self.unions.iter() {
-            if union_item
-                .get_union_name()
-                .get_import_name()
-                .to_string()
-                .eq(name)
-            {
-                *relative_types = union_item.get_relative_types();
-                return;
+            let struct_name = impl_item.get_struct_name().get_import_name().to_string();
+            if let Some(struct_data) = structs.get_mut(&struct_name) {
+                struct_data.empty_impls.push(impl_item.clone());
             }
         }
     }
@@ -1548,7 +3836,7 @@ impl SyntaxContext {
 
     pub fn get_context(
         &self,
-        output_path: &PathBuf,
+        output_path: &path::Path,
         mod_tree: &String,
         mod_trees: &Vec<String>,
         fns: &HashMap<String, FnData>,
@@ -1558,6 +3846,9 @@ impl SyntaxContext {
         for function_item in self.functions.iter() {
             let complete_function_name =
                 mod_tree.clone() + "::" + &function_item.get_complete_function_name_in_file();
+            if !crate_context.should_render(FocalKind::Fn, &complete_function_name) {
+                continue;
+            }
             let call_file = output_path
                 .join(String::from("callsandtypes/") + &complete_function_name + ".json");
             // println!("{}", call_file.to_string_lossy());
@@ -1567,31 +3858,47 @@ impl SyntaxContext {
                     let mut contents = String::new();
                     file.read_to_string(&mut contents).unwrap();
                     let mut data: CallsAndTypes = serde_json::from_str(&contents).unwrap();
+                    if crate_context.get_options().api_surface && !data.exported {
+                        continue;
+                    }
+                    crate_context.check_fanout(&complete_function_name, &data);
                     let mut syntax_context = SyntaxContext::new();
                     // syntax_context.functions.push(function_item.clone());
                     data.calls.push(function_item.get_complete_name());
-                    parse_callsandtypes(&mut data, mod_trees, &mut syntax_context, fns, structs);
-                    let rs_file_name = complete_function_name.clone() + ".rs";
-                    let output_file_path = output_path.join(rs_file_name);
-                    let mut file = File::create(output_file_path).unwrap();
-                    file.write_all(syntax_context.to_string().as_bytes())
-                        .unwrap();
-
-                    let directory_path = output_path.join("new_callsandtypes");
-                    create_dir_all(&directory_path).unwrap();
-                    let file_path = PathBuf::from(&directory_path)
-                        .join(format!("{}.json", complete_function_name.clone()));
-                    let mut file = File::create(&file_path).unwrap();
-                    file.write_all(serde_json::to_string(&data).unwrap().as_bytes())
-                        .unwrap();
+                    parse_callsandtypes(
+                        &mut data,
+                        mod_trees,
+                        &mut syntax_context,
+                        fns,
+                        structs,
+                        crate_context.get_options(),
+                    );
+                    if crate_context.get_options().const_only && function_item.is_const() {
+                        warn_non_const_dependencies(&complete_function_name, &syntax_context);
+                    }
+                    write_context(
+                        output_path,
+                        mod_tree,
+                        &function_item.get_complete_function_name_in_file(),
+                        &function_item.get_complete_name(),
+                        &syntax_context,
+                        &data,
+                        crate_context,
+                    );
+                }
+                Err(_) => {
+                    crate_context.count_missing_analysis(output_path, &complete_function_name)
                 }
-                Err(_) => {}
             }
         }
         for impl_item in self.impls.iter() {
+            let impl_relative_types = impl_item.get_relative_types();
             for function_item in impl_item.get_fns().iter() {
                 let complete_function_name =
                     mod_tree.clone() + "::" + &function_item.get_complete_function_name_in_file();
+                if !crate_context.should_render(FocalKind::Method, &complete_function_name) {
+                    continue;
+                }
                 let call_file = output_path
                     .join(String::from("callsandtypes/") + &complete_function_name + ".json");
                 let mut file = File::open(call_file);
@@ -1600,21 +3907,23 @@ impl SyntaxContext {
                         let mut contents = String::new();
                         file.read_to_string(&mut contents).unwrap();
                         let mut data: CallsAndTypes = serde_json::from_str(&contents).unwrap();
+                        if crate_context.get_options().api_surface && !data.exported {
+                            continue;
+                        }
+                        crate_context.check_fanout(&complete_function_name, &data);
                         let mut syntax_context = SyntaxContext::new();
                         data.calls.push(function_item.get_complete_name());
                         data.types
                             .push(impl_item.get_struct_name().get_import_name().to_string());
-                        let mut relative_types: Vec<String> = Vec::new();
-                        crate_context.get_relative_types_for_struct(
+                        data.types.extend(get_relative_types_for_struct(
+                            structs,
                             &impl_item.get_struct_name().get_import_name().to_string(),
-                            &mut relative_types,
-                        );
-                        for relative_type in relative_types.iter() {
-                            data.types.push(relative_type.clone());
-                        }
+                        ));
                         if let Some(trait_name) = impl_item.get_trait_name() {
                             data.types.push(trait_name.get_import_name().to_string());
                         }
+                        data.types.extend(impl_relative_types.iter().cloned());
+                        data.types.extend(function_item.get_relative_types());
                         // println!("{}", complete_function_name);
                         // println!(
                         //     "{}",
@@ -1628,23 +3937,25 @@ impl SyntaxContext {
                             &mut syntax_context,
                             fns,
                             structs,
+                            crate_context.get_options(),
+                        );
+                        if crate_context.get_options().const_only && function_item.is_const() {
+                            warn_non_const_dependencies(&complete_function_name, &syntax_context);
+                        }
+                        write_context(
+                            output_path,
+                            mod_tree,
+                            &function_item.get_complete_function_name_in_file(),
+                            &function_item.get_complete_name(),
+                            &syntax_context,
+                            &data,
+                            crate_context,
                         );
-                        let rs_file_name = complete_function_name.clone() + ".rs";
-                        let output_file_path = output_path.join(rs_file_name);
-                        let mut file = File::create(output_file_path).unwrap();
-                        file.write_all(syntax_context.to_string().as_bytes())
-                            .unwrap();
-
-                        let directory_path = output_path.join("new_callsandtypes");
-                        create_dir_all(&directory_path).unwrap();
-                        let file_path = PathBuf::from(&directory_path)
-                            .join(format!("{}.json", complete_function_name.clone()));
-                        let mut file = File::create(&file_path).unwrap();
-                        file.write_all(serde_json::to_string(&data).unwrap().as_bytes())
-                            .unwrap();
                         // exit(1);
                     }
-                    Err(_) => {}
+                    Err(_) => {
+                        crate_context.count_missing_analysis(output_path, &complete_function_name)
+                    }
                 }
             }
         }
@@ -1652,6 +3963,9 @@ impl SyntaxContext {
             for function_item in trait_item.get_fns().iter() {
                 let complete_function_name =
                     mod_tree.clone() + "::" + &function_item.get_complete_function_name_in_file();
+                if !crate_context.should_render(FocalKind::TraitFn, &complete_function_name) {
+                    continue;
+                }
                 let call_file = output_path
                     .join(String::from("callsandtypes/") + &complete_function_name + ".json");
                 let mut file = File::open(call_file);
@@ -1660,6 +3974,10 @@ impl SyntaxContext {
                         let mut contents = String::new();
                         file.read_to_string(&mut contents).unwrap();
                         let mut data: CallsAndTypes = serde_json::from_str(&contents).unwrap();
+                        if crate_context.get_options().api_surface && !data.exported {
+                            continue;
+                        }
+                        crate_context.check_fanout(&complete_function_name, &data);
                         let mut syntax_context = SyntaxContext::new();
                         data.calls.push(function_item.get_complete_name());
                         data.types.push(trait_item.get_name());
@@ -1669,52 +3987,630 @@ impl SyntaxContext {
                             &mut syntax_context,
                             fns,
                             structs,
+                            crate_context.get_options(),
+                        );
+                        write_context(
+                            output_path,
+                            mod_tree,
+                            &function_item.get_complete_function_name_in_file(),
+                            &function_item.get_complete_name(),
+                            &syntax_context,
+                            &data,
+                            crate_context,
+                        );
+                    }
+                    Err(_) => {
+                        crate_context.count_missing_analysis(output_path, &complete_function_name)
+                    }
+                }
+            }
+        }
+        if crate_context.get_options().focal_consts {
+            let const_items = self.consts.iter().map(|const_item| {
+                (
+                    FocalKind::Const,
+                    const_item.get_name(),
+                    const_item.get_const_name(),
+                    const_item.get_item().expr,
+                )
+            });
+            let static_items = self.statics.iter().map(|static_item| {
+                (
+                    FocalKind::Static,
+                    static_item.get_name(),
+                    static_item.get_static_name(),
+                    static_item.get_item().expr,
+                )
+            });
+            for (focal_kind, name, item_name, expr) in const_items.chain(static_items) {
+                if name.eq("_") || is_literal_initializer(&expr) {
+                    continue;
+                }
+                let complete_name = mod_tree.clone() + "::" + &name;
+                if !crate_context.should_render(focal_kind, &complete_name) {
+                    continue;
+                }
+                let call_file =
+                    output_path.join(String::from("callsandtypes/") + &complete_name + ".json");
+                match File::open(call_file) {
+                    Ok(mut file) => {
+                        let mut contents = String::new();
+                        file.read_to_string(&mut contents).unwrap();
+                        let mut data: CallsAndTypes = serde_json::from_str(&contents).unwrap();
+                        if crate_context.get_options().api_surface && !data.exported {
+                            continue;
+                        }
+                        crate_context.check_fanout(&complete_name, &data);
+                        let mut syntax_context = SyntaxContext::new();
+                        let focal_name = item_name.get_import_name().to_string();
+                        data.types.push(focal_name.clone());
+                        parse_callsandtypes(
+                            &mut data,
+                            mod_trees,
+                            &mut syntax_context,
+                            fns,
+                            structs,
+                            crate_context.get_options(),
+                        );
+                        write_context(
+                            output_path,
+                            mod_tree,
+                            &name,
+                            &focal_name,
+                            &syntax_context,
+                            &data,
+                            crate_context,
                         );
-                        let rs_file_name = complete_function_name.clone() + ".rs";
-                        let output_file_path = output_path.join(rs_file_name);
-                        let mut file = File::create(output_file_path).unwrap();
-                        file.write_all(syntax_context.to_string().as_bytes())
-                            .unwrap();
-
-                        let directory_path = output_path.join("new_callsandtypes");
-                        create_dir_all(&directory_path).unwrap();
-                        let file_path = PathBuf::from(&directory_path)
-                            .join(format!("{}.json", complete_function_name.clone()));
-                        let mut file = File::create(&file_path).unwrap();
-                        file.write_all(serde_json::to_string(&data).unwrap().as_bytes())
-                            .unwrap();
                     }
-                    Err(_) => {}
+                    Err(_) => crate_context.count_missing_analysis(output_path, &complete_name),
                 }
             }
         }
     }
 
-    fn to_string(&self) -> String {
-        let mut items: Vec<Item> = Vec::new();
-        items.extend(self.types.iter().map(|type_item| type_item.to_item()));
-        items.extend(self.uses.iter().map(|use_item| use_item.to_item()));
-        items.extend(self.mods.iter().map(|mod_item| mod_item.to_item()));
-        items.extend(self.statics.iter().map(|static_item| static_item.to_item()));
-        items.extend(self.consts.iter().map(|const_item| const_item.to_item()));
-        items.extend(
+    // With `verbatim_containers`, impls and traits are written as parsed, with all their items.
+    // Pairs each item with the module it was defined in, when its name records one.
+    // Items of a kind are added in the order their names were resolved, which follows the
+    // order of call_chain's output, so they are sorted to keep contexts the same across runs.
+    fn get_module_items<'a>(
+        &'a self,
+        options: &ContextOptions,
+    ) -> impl Iterator<Item = (Option<String>, Item)> + 'a {
+        let verbatim_containers = options.verbatim_containers;
+        let stub_all = options.stub_all;
+        let to_trait_item = move |trait_item: &TraitItem| {
+            let item = if verbatim_containers {
+                trait_item.to_original_item()
+            } else {
+                trait_item.to_item()
+            };
+            (get_module(trait_item.get_trait_name()), item)
+        };
+        let to_impl_item = move |impl_item: &ImplItem| {
+            let item = if verbatim_containers {
+                impl_item.to_original_item()
+            } else {
+                impl_item.to_item()
+            };
+            let module = impl_item
+                .get_mod_tree()
+                .or_else(|| get_module(impl_item.get_struct_name()));
+            (module, item)
+        };
+        // `macro_rules!` are textually scoped, so they go before everything that may invoke them.
+        let kinds: Vec<Vec<(Option<String>, Item)>> = vec![
+            self.macros
+                .iter()
+                .map(|macro_item| {
+                    (
+                        get_module(macro_item.get_macro_name()),
+                        macro_item.to_item(),
+                    )
+                })
+                .collect(),
+            self.types
+                .iter()
+                .map(|type_item| (None, type_item.to_item()))
+                .collect(),
+            self.uses
+                .iter()
+                .map(|use_item| (None, use_item.to_item()))
+                .collect(),
+            self.mods
+                .iter()
+                .map(|mod_item| (None, mod_item.to_item()))
+                .collect(),
+            self.statics
+                .iter()
+                .map(|static_item| {
+                    (
+                        get_module(static_item.get_static_name()),
+                        static_item.to_item(),
+                    )
+                })
+                .collect(),
+            self.consts
+                .iter()
+                .map(|const_item| {
+                    (
+                        get_module(const_item.get_const_name()),
+                        const_item.to_item(),
+                    )
+                })
+                .collect(),
             self.trait_aliases
                 .iter()
-                .map(|trait_alias_item| trait_alias_item.to_item()),
-        );
-        items.extend(self.traits.iter().map(|trait_item| trait_item.to_item()));
-        items.extend(self.structs.iter().map(|struct_item| struct_item.to_item()));
-        items.extend(self.enums.iter().map(|enum_item| enum_item.to_item()));
-        items.extend(self.unions.iter().map(|union_item| union_item.to_item()));
-        items.extend(self.impls.iter().map(|impl_item| impl_item.to_item()));
-        items.extend(
+                .map(|trait_alias_item| (None, trait_alias_item.to_item()))
+                .collect(),
+            self.traits.iter().map(to_trait_item).collect(),
+            self.structs
+                .iter()
+                .map(|struct_item| {
+                    (
+                        get_module(struct_item.get_struct_name()),
+                        struct_item.to_item(),
+                    )
+                })
+                .collect(),
+            self.enums
+                .iter()
+                .map(|enum_item| (get_module(enum_item.get_enum_name()), enum_item.to_item()))
+                .collect(),
+            self.unions
+                .iter()
+                .map(|union_item| {
+                    (
+                        get_module(union_item.get_union_name()),
+                        union_item.to_item(),
+                    )
+                })
+                .collect(),
+            self.impls.iter().map(to_impl_item).collect(),
             self.functions
                 .iter()
-                .map(|function_item| function_item.to_item()),
+                .map(|function_item| {
+                    (
+                        get_module(function_item.get_fn_name()),
+                        function_item.to_item(),
+                    )
+                })
+                .collect(),
+        ];
+        kinds
+            .into_iter()
+            .flat_map(|mut module_items| {
+                // Impls of the same type and trait are told apart by their tokens.
+                module_items.sort_by_cached_key(|(module, item)| {
+                    (
+                        module.clone(),
+                        get_item_name(item),
+                        item.to_token_stream().to_string(),
+                    )
+                });
+                module_items
+            })
+            .map(move |(module, mut item)| {
+                if stub_all {
+                    BodyStubber.visit_item_mut(&mut item);
+                }
+                (module, item)
+            })
+    }
+
+    fn get_items<'a>(&'a self, options: &ContextOptions) -> impl Iterator<Item = Item> + 'a {
+        self.get_module_items(options).map(|(_, item)| item)
+    }
+
+    // With `bundle_mods`, the items go into nested `mod` blocks mirroring the modules of the
+    // crate they were defined in, so that their paths resolve as in the crate. Items without
+    // a recorded module stay at the top, which stands for the crate root.
+    fn get_bundled_items(&self, crate_context: &CrateContext) -> Vec<Item> {
+        self.get_module_bundle(crate_context).into_items()
+    }
+
+    // Paths through a `pub use` of the crate, e.g. `crate::a::Bar` for `crate::a::inner::Bar`,
+    // only resolve if the bundle has the `pub use` too, so the ones of its items are added.
+    // Of the other `use`s of a module, the ones that the traits in the bounds of its items are
+    // named through are added, e.g. for `where T: Marker`.
+    fn get_module_bundle(&self, crate_context: &CrateContext) -> ModuleBundle {
+        let mut bundle = ModuleBundle::default();
+        let mut item_names: BTreeSet<String> = BTreeSet::new();
+        let mut bound_names: BTreeSet<(String, String)> = BTreeSet::new();
+        for (module, item) in self.get_module_items(crate_context.get_options()) {
+            let mut module_bundle = &mut bundle;
+            if let Some(module) = &module {
+                if let Some(item_name) = get_item_name(&item) {
+                    item_names.insert(module.clone() + "::" + &item_name);
+                    item_names.insert(module.clone() + "::*");
+                }
+                let mut visitor = BoundVisitor {
+                    names: BTreeSet::new(),
+                };
+                visitor.visit_item(&item);
+                bound_names.extend(visitor.names.into_iter().map(|name| (module.clone(), name)));
+                for mod_name in module.split("::").skip(1) {
+                    module_bundle = module_bundle.get_sub_mod(mod_name);
+                }
+            }
+            module_bundle.items.push(item);
+        }
+        let mut re_exported: BTreeSet<(String, String, String)> = BTreeSet::new();
+        for re_export in crate_context.get_re_exports().iter() {
+            if !item_names.contains(&re_export.target)
+                || !re_exported.insert((
+                    re_export.module.clone(),
+                    re_export.name.clone(),
+                    re_export.target.clone(),
+                ))
+            {
+                continue;
+            }
+            let Some(item) = get_re_export_item(re_export, "pub ") else {
+                continue;
+            };
+            let mut module_bundle = &mut bundle;
+            for mod_name in re_export.module.split("::").skip(1) {
+                module_bundle = module_bundle.get_sub_mod(mod_name);
+            }
+            module_bundle.items.push(item);
+        }
+        // The target of a use is an item of the bundle or a module with some, and a glob
+        // brings in the items of its module.
+        for import in crate_context.get_imports().iter() {
+            let is_bound = match import.target.strip_suffix("::*") {
+                Some(target_module) => bound_names.iter().any(|(module, name)| {
+                    module == &import.module
+                        && item_names.contains(&(target_module.to_string() + "::" + name))
+                }),
+                None => {
+                    bound_names.contains(&(import.module.clone(), import.name.clone()))
+                        && (item_names.contains(&import.target)
+                            || item_names.contains(&(import.target.clone() + "::*")))
+                }
+            };
+            // A name is brought into a module once, by its `pub use` if it has one.
+            let key = (
+                import.module.clone(),
+                import.name.clone(),
+                import.target.clone(),
+            );
+            let is_added = if import.name == "*" {
+                re_exported.contains(&key)
+            } else {
+                re_exported
+                    .iter()
+                    .any(|(module, name, _)| module == &import.module && name == &import.name)
+            };
+            if !is_bound || is_added {
+                continue;
+            }
+            re_exported.insert(key);
+            let Some(item) = get_re_export_item(import, "") else {
+                continue;
+            };
+            let mut module_bundle = &mut bundle;
+            for mod_name in import.module.split("::").skip(1) {
+                module_bundle = module_bundle.get_sub_mod(mod_name);
+            }
+            module_bundle.items.push(item);
+        }
+        bundle
+    }
+
+    fn get_entries(&self, options: &ContextOptions) -> Vec<ContextEntry> {
+        self.get_module_items(options)
+            .map(|(module, item)| ContextEntry {
+                module,
+                kind: get_item_kind(&item).to_string(),
+                name: get_item_name(&item),
+                code: unparse(&to_syn_file(vec![item])),
+            })
+            .collect()
+    }
+
+    // The items keep the spans they were parsed with, and their module names the file they are in.
+    fn get_source_map(
+        &self,
+        options: &ContextOptions,
+        mod_files: &HashMap<String, PathBuf>,
+    ) -> Vec<SourceMapEntry> {
+        self.get_module_items(options)
+            .map(|(module, item)| {
+                let span = item.span();
+                SourceMapEntry {
+                    file: module
+                        .as_ref()
+                        .and_then(|module| mod_files.get(module).cloned()),
+                    module,
+                    kind: get_item_kind(&item).to_string(),
+                    name: get_item_name(&item),
+                    start_line: span.start().line,
+                    start_column: span.start().column + 1,
+                    end_line: span.end().line,
+                    end_column: span.end().column + 1,
+                }
+            })
+            .collect()
+    }
+
+    fn get_crate_roots(&self, options: &ContextOptions) -> BTreeSet<String> {
+        let mut visitor = CrateRootVisitor::new();
+        for item in self.get_items(options) {
+            visitor.visit_item(&item);
+        }
+        visitor.roots
+    }
+
+    // Renders the items one by one, so only one item's source is in memory at a time,
+    // except when bundling them into mods.
+    fn write_items(&self, writer: &mut impl Write, crate_context: &CrateContext) -> io::Result<()> {
+        let options = crate_context.get_options();
+        if options.bundle_mods {
+            let syntax = to_syn_file(self.get_bundled_items(crate_context));
+            writer.write_all(unparse(&syntax).as_bytes())?;
+            return writer.flush();
+        }
+        for item in self.get_items(options) {
+            let syntax = to_syn_file(vec![item]);
+            writer.write_all(unparse(&syntax).as_bytes())?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn self_names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn names_self_type_by_import_name() {
+        let self_names = self_names(&["Self", "my_crate::ui::Widget"]);
+        assert!(names_self_type(&parse_quote!(Self), &self_names));
+        assert!(names_self_type(&parse_quote!(Option<Self>), &self_names));
+        assert!(names_self_type(
+            &parse_quote!(Result<my_crate::ui::Widget, Error>),
+            &self_names
+        ));
+        assert!(!names_self_type(
+            &parse_quote!(my_crate::other::Widget),
+            &self_names
+        ));
+        assert!(!names_self_type(&parse_quote!(Widget), &self_names));
+        assert!(!names_self_type(&parse_quote!(SelfishThing), &self_names));
+        assert!(!names_self_type(&parse_quote!(Self::Item), &self_names));
+        assert!(!names_self_type(
+            &parse_quote!(<Self as Iterator>::Item),
+            &self_names
+        ));
+    }
+
+    #[test]
+    fn strip_generic_args_of_type_names() {
+        assert_eq!(
+            strip_generic_args("<Hold<Outer<u8>> as std::ops::Deref>::deref"),
+            "<Hold as std::ops::Deref>::deref"
+        );
+        assert_eq!(
+            strip_generic_args("my_crate::f::<impl Fn(u8)>"),
+            "my_crate::f"
+        );
+        assert_eq!(
+            strip_generic_args("<[u8] as my_crate::Trait>::f"),
+            "<[u8] as my_crate::Trait>::f"
+        );
+        assert_eq!(strip_generic_args("my_crate::f"), "my_crate::f");
+    }
+
+    #[test]
+    fn self_type_is_replaced_in_its_qualified_self_only() {
+        let mut data = CallsAndTypes {
+            mod_name: String::new(),
+            calls: vec!["<P as Pair>::first".to_string()],
+            types: vec!["<P as Pair>::A".to_string()],
+            exported: true,
+            external: Vec::new(),
+        };
+        add_new_calls_and_types(&mut data, &vec!["my_crate".to_string()]);
+        assert!(data
+            .calls
+            .contains(&"<  as my_crate::Pair>::first".to_string()));
+        assert!(data.types.contains(&"<  as my_crate::Pair>::A".to_string()));
+        assert!(!data.calls.iter().any(|call| call.contains(" air>")));
+    }
+
+    fn impl_fn_data(fn_name: &str, impl_item: &ImplItem) -> FnData {
+        let mut impl_fn_item = ImplFnItem::new();
+        impl_fn_item.insert_fn_name(&fn_name.to_string());
+        let complete_fn_name = format!("my_crate::Foo::{}", fn_name);
+        impl_fn_item.insert_complete_name(&complete_fn_name);
+        let ident = Ident::new(fn_name, Span::call_site());
+        impl_fn_item.insert_item(&parse_quote!(fn #ident(&self) { todo!() }));
+        FnData {
+            fn_name: fn_name.to_string(),
+            complete_fn_name,
+            fn_type: FnType::ImplFn(impl_fn_item, impl_item.clone()),
+        }
+    }
+
+    fn get_fn_bodies(syntax_context: &SyntaxContext) -> Vec<(String, bool)> {
+        syntax_context.impls[0]
+            .get_fns()
+            .iter()
+            .map(|impl_fn_item| {
+                (
+                    impl_fn_item.get_name(),
+                    !impl_fn_item.get_item().block.stmts.is_empty(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn depth_zero_keeps_only_the_focal_method_body() {
+        let mut impl_item = ImplItem::new();
+        impl_item.insert_item(&parse_quote!(impl Foo {}));
+        for fn_name in ["a", "b"] {
+            if let FnType::ImplFn(impl_fn_item, _) = impl_fn_data(fn_name, &impl_item).fn_type {
+                impl_item.insert_function(&impl_fn_item);
+            }
+        }
+        let mut syntax_context = SyntaxContext::new();
+        syntax_context.impls.push(impl_item);
+        assert_eq!(
+            get_fn_bodies(&syntax_context.stub_beyond_depth("my_crate::Foo::a", 0)),
+            [("a".to_string(), true), ("b".to_string(), false)]
+        );
+        assert_eq!(
+            get_fn_bodies(&syntax_context.stub_beyond_depth("my_crate::Foo::a", 1)),
+            [("a".to_string(), true), ("b".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn call_cycle_keeps_impl_fn_once() {
+        let mut impl_item = ImplItem::new();
+        impl_item.insert_item(&parse_quote!(impl Foo {}));
+        let fns: HashMap<String, FnData> = ["a", "b"]
+            .iter()
+            .map(|fn_name| {
+                let fn_data = impl_fn_data(fn_name, &impl_item);
+                (fn_data.complete_fn_name.clone(), fn_data)
+            })
+            .collect();
+        let mut syntax_context = SyntaxContext::new();
+        // The focal `a` calls `b`, which calls `a` back.
+        for call in ["my_crate::Foo::a", "my_crate::Foo::b", "my_crate::Foo::a"] {
+            let data = CallsAndTypes::new(
+                &String::from("my_crate"),
+                &HashSet::from([call.to_string()]),
+                &HashSet::new(),
+                false,
+                &HashSet::new(),
+            );
+            get_syntax(
+                &data,
+                &mut syntax_context,
+                &fns,
+                &HashMap::new(),
+                &ContextOptions::new(),
+            );
+        }
+        assert_eq!(syntax_context.impls.len(), 1);
+        let fn_names: Vec<String> = syntax_context.impls[0]
+            .get_fns()
+            .iter()
+            .map(|impl_fn_item| impl_fn_item.get_item().sig.ident.to_string())
+            .collect();
+        assert_eq!(fn_names, ["a", "b"]);
+    }
+
+    #[test]
+    fn focal_const_is_the_focal_item() {
+        let mut syntax_context = SyntaxContext::new();
+        for (const_name, item) in [
+            (
+                "N",
+                parse_quote!(
+                    pub const N: usize = f();
+                ),
+            ),
+            (
+                "M",
+                parse_quote!(
+                    const M: usize = 1;
+                ),
+            ),
+        ] {
+            let mut const_item = ConstItem::new();
+            const_item.insert_const_name(&String::from(const_name));
+            const_item.insert_parent_mod_tree(&String::from("my_crate"));
+            const_item.insert_item(&item);
+            syntax_context.consts.push(const_item);
+        }
+        let layers = syntax_context.split_layers("my_crate::N");
+        assert_eq!(layers[0].consts.len(), 1);
+        assert_eq!(layers[0].consts[0].get_name(), "N");
+        assert_eq!(syntax_context.get_dependency_count("my_crate::N"), 1);
+        let truncated_context = syntax_context.truncate("my_crate::N", 0);
+        assert_eq!(truncated_context.consts.len(), 1);
+        assert_eq!(truncated_context.consts[0].get_name(), "N");
+    }
+
+    #[test]
+    fn self_names_follow_aliases() {
+        let mut impl_item = ImplItem::new();
+        impl_item.insert_struct_name(&String::from("Widget"));
+        impl_item.insert_struct_import_name(&String::from("my_crate::ui::Widget"));
+        let mut impl_type_item = ImplTypeItem::new();
+        impl_type_item.insert_item(&parse_quote!(
+            type Handle = Widget;
+        ));
+        impl_type_item.insert_import_type(&parse_quote!(my_crate::ui::Widget));
+        impl_item.insert_type(&impl_type_item);
+        let type_aliases: Vec<(String, Type)> = vec![
+            (
+                String::from("my_crate::ui::Shared"),
+                parse_quote!(Rc<my_crate::ui::Widget>),
+            ),
+            (
+                String::from("my_crate::ui::SharedList"),
+                parse_quote!(Vec<my_crate::ui::Shared>),
+            ),
+            (
+                String::from("my_crate::other::Shared"),
+                parse_quote!(Rc<my_crate::other::Widget>),
+            ),
+            (String::from("my_crate::ui::Label"), parse_quote!(String)),
+        ];
+        assert_eq!(
+            get_self_names(&impl_item, &type_aliases),
+            self_names(&[
+                "Self",
+                "Self::Handle",
+                "my_crate::ui::Widget",
+                "my_crate::ui::Shared",
+                "my_crate::ui::SharedList"
+            ])
+        );
+    }
+
+    #[test]
+    fn gat_bounds_are_relative_types_of_the_trait() {
+        let syntax_context = SyntaxContext::from_items(&vec![parse_quote!(
+            pub trait Container {
+                type Iter<'a>: Iterator<Item = &'a Foo>
+                where
+                    Self: 'a;
+            }
+        )]);
+        assert!(syntax_context.traits[0]
+            .get_relative_types()
+            .contains(&"Foo".to_string()));
+    }
+
+    #[test]
+    fn impl_trait_args_are_applications() {
+        let signature: Signature = parse_quote!(fn f(x: impl Into<MyType>));
+        let mut applications = Vec::new();
+        visit_impl_trait_args(&signature, &mut applications);
+        assert!(applications.contains(&"MyType".to_string()));
+    }
+
+    #[test]
+    fn where_clause_bounds_are_applications() {
+        let item_fn: ItemFn = parse_quote!(
+            fn f<T>(x: T)
+            where
+                T: From<Foo>,
+            {
+            }
         );
-        let tokens = quote! {#(#items)*};
-        let syntax: syn::File = parse2(tokens).unwrap();
-        unparse(&syntax)
-        // tokens.to_string()
+        let mut applications = Vec::new();
+        visit_generics(&item_fn.sig.generics, &mut applications);
+        assert!(applications.contains(&"Foo".to_string()));
     }
 }
@@ -1,17 +1,23 @@
 use std::{
     collections::{HashMap, HashSet},
+    env,
     fs::{self, File},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
+    time::Instant,
 };
 
-use clap::Parser;
+use clap::{value_parser, ArgAction, Command, CommandFactory, FromArgMatches, Parser};
 use collect_context::{
     crate_context::CrateContext,
+    options::{BodyMode, ContextOptions, FocalKind},
     result::{FnData, StructData},
 };
-use utils::run_call_chain;
+use utils::{
+    print_contexts, read_config, run_call_chain, run_on_stdin_crate, CallChainEnv, CONFIG_FILE,
+    STDIN_CRATE_VAR,
+};
 
 mod collect_context;
 mod utils;
@@ -21,25 +27,403 @@ mod utils;
 #[command(author = "AbeZbm")]
 #[command(version = "1.0")]
 #[command(about="A rust program to get focal context for a crate.",long_about=None)]
+// A flag given twice takes its last value.
+#[command(args_override_self = true)]
 struct Cli {
     ///Sets crate path
-    #[arg(short = 'c', long = "crate", required = true)]
-    crate_path: String,
+    #[arg(short = 'c', long = "crate", required_unless_present = "stdin")]
+    crate_path: Option<String>,
+    ///Reads a single file from stdin and prints its contexts to stdout (no modules or dependencies)
+    #[arg(long = "stdin", conflicts_with_all = ["crate_path", "render_only"])]
+    stdin: bool,
+    ///Prints the context of the --focal function to stdout instead of writing it to a file
+    #[arg(
+        long = "stdout",
+        requires = "focal",
+        conflicts_with_all = ["stdin", "json", "file_tree", "emit_cargo_toml", "stats", "trace", "source_map", "emit_graph", "incremental"]
+    )]
+    stdout: bool,
+    ///Ignores the rfocxt.toml of the crate, whose options (e.g. `max-depth = 2`) are otherwise used where the command line leaves them unset
+    #[arg(long = "no-config")]
+    no_config: bool,
+    ///Sets the edition of the source read with --stdin
+    #[arg(
+        long = "edition",
+        default_value = "2021",
+        value_parser = ["2015", "2018", "2021"]
+    )]
+    edition: String,
+    ///Writes each focal context into subdirectories mirroring its module path
+    #[arg(long = "nested-output")]
+    nested_output: bool,
+    ///Sets how many targets call_chain analyzes at the same time (defaults to the number of cores)
+    #[arg(long = "max-parallel-crates")]
+    max_parallel_crates: Option<usize>,
+    ///Shows the log of call_chain down to this level (it is hidden by default)
+    #[arg(
+        long = "log-level",
+        value_name = "LEVEL",
+        value_parser = LOG_LEVELS
+    )]
+    log_level: Option<String>,
+    ///Hides the log of call_chain, even when RUST_LOG is set
+    #[arg(long = "quiet", conflicts_with = "log_level")]
+    quiet: bool,
+    ///Re-renders only the given focal function (e.g. my_crate::foo) from a previous run's analysis
+    #[arg(long = "render-only", value_name = "NAME")]
+    render_only: Option<String>,
+    ///Only writes the context of the given focal function (e.g. my_crate::foo or its file name)
+    #[arg(long = "focal", value_name = "NAME", conflicts_with_all = ["render_only", "focal_trait", "focal_impl"])]
+    focal: Option<String>,
+    ///Only writes focal contexts of the given kinds (repeatable)
+    #[arg(long = "only-kind", value_enum)]
+    only_kinds: Vec<FocalKind>,
+    ///Also writes contexts for free consts and statics, from their initializers (literals are skipped)
+    #[arg(long = "focal-consts")]
+    focal_consts: bool,
+    ///Warns when the context of a const fn contains fns it could not call in a const context
+    #[arg(long = "const-only")]
+    const_only: bool,
+    ///Sets the separator between path segments in output file names
+    #[arg(long = "name-sep", default_value = "::")]
+    name_sep: String,
+    ///Writes impls and traits as in the source, with all their items, instead of only the used ones
+    #[arg(long = "verbatim-containers")]
+    verbatim_containers: bool,
+    ///Prepends the crate's #![feature(...)] gates to each written context
+    #[arg(long = "emit-features")]
+    emit_features: bool,
+    ///Prepends the crate's inner attributes with these names to each written context (e.g. allow,recursion_limit)
+    #[arg(long = "inner-attrs", value_delimiter = ',', value_name = "NAMES")]
+    inner_attrs: Vec<String>,
+    ///Splits each context into <fn>.layerN.rs files by distance from the focal function
+    #[arg(long = "layered-output")]
+    layered_output: bool,
+    ///Warns about fns with more than N applications, and lists the highest ones at the end
+    #[arg(long = "warn-fanout", value_name = "N")]
+    warn_fanout: Option<usize>,
+    ///Writes a markdown summary (<fn>.md) next to each context
+    #[arg(long = "emit-summaries")]
+    emit_summaries: bool,
+    ///Writes the focal fn's params, return type, generics and where clauses (<fn>.meta.json) next to each context
+    #[arg(long = "emit-meta")]
+    emit_meta: bool,
+    ///Only includes impls of these traits, unless one of their fns is called (e.g. Clone,PartialEq)
+    #[arg(
+        long = "allow-trait-impls",
+        value_delimiter = ',',
+        value_name = "TRAITS"
+    )]
+    allow_trait_impls: Vec<String>,
+    ///Leaves out impls of these traits, unless one of their fns is called (e.g. Debug,Serialize)
+    #[arg(
+        long = "deny-trait-impls",
+        value_delimiter = ',',
+        value_name = "TRAITS"
+    )]
+    deny_trait_impls: Vec<String>,
+    ///Adds the concrete types behind `impl Trait` returns, and the impl methods called on them
+    #[arg(long = "resolve-opaque")]
+    resolve_opaque: bool,
+    ///Analyzes the crate with its own panic strategy instead of panic=abort, e.g. for crates relying on catch_unwind
+    #[arg(long = "keep-unwind")]
+    keep_unwind: bool,
+    ///Only writes contexts of fns reachable from outside the crate, through public modules or `pub use` re-exports; private fns still appear as dependencies
+    #[arg(long = "api-surface")]
+    api_surface: bool,
+    ///Only writes contexts with at least N applications (the items in the context besides the focal fn)
+    #[arg(long = "min-apps", value_name = "N")]
+    min_apps: Option<usize>,
+    ///Only writes contexts with at most N applications
+    #[arg(long = "max-apps", value_name = "N")]
+    max_apps: Option<usize>,
+    ///Writes at most N items besides the focal fn in each context, leaving out the ones furthest from it, and marks the context as truncated
+    #[arg(long = "max-items-per-focal", value_name = "N")]
+    max_items_per_focal: Option<usize>,
+    ///Stops after writing N contexts
+    #[arg(long = "max-focals", value_name = "N")]
+    max_focals: Option<usize>,
+    ///Writes every fn in a context, the focal fn included, with an empty body, keeping all type definitions (the context parses, but fns returning a value no longer type-check)
+    #[arg(long = "stub-all")]
+    stub_all: bool,
+    ///Writes the methods of a context with an empty body, except the focal fn and constructors returning their self type (stubbed methods returning a value no longer type-check)
+    #[arg(long = "elide-bodies", conflicts_with = "stub_all")]
+    elide_bodies: bool,
+    ///Writes the fns further than N levels from the focal fn with an empty body (1: the fns and types it uses, 2: what their impls bring in); stubbed fns returning a value no longer type-check
+    #[arg(long = "max-depth", value_name = "N", conflicts_with = "stub_all")]
+    max_depth: Option<usize>,
+    ///Keeps the bodies of all fns of a context (full), only of the focal fn (signatures), or as the flags above decide (auto); with signatures, the other fns returning a value no longer type-check
+    #[arg(
+        long = "bodies",
+        value_enum,
+        default_value = "auto",
+        conflicts_with = "stub_all"
+    )]
+    bodies: BodyMode,
+    ///Starts every context file with a comment naming the crate version and git commit it came from
+    #[arg(long = "version-header")]
+    version_header: bool,
+    ///Writes a Cargo.toml for each context, in a directory named after it, with the dependencies the context uses
+    #[arg(long = "emit-cargo-toml", conflicts_with = "layered_output")]
+    emit_cargo_toml: bool,
+    ///Writes each context as a JSON list of its items, with their module, kind, name and code, instead of a .rs file
+    #[arg(long = "json", conflicts_with_all = ["emit_cargo_toml", "stdin"])]
+    json: bool,
+    ///Nests the items of each context in `mod` blocks mirroring the modules of the crate, so the file compiles on its own
+    #[arg(long = "bundle-mods", conflicts_with = "json")]
+    bundle_mods: bool,
+    ///Writes each context as a directory, with lib.rs for the crate root and a/b/mod.rs for each module it has items from
+    #[arg(long = "file-tree", conflicts_with_all = ["json", "bundle_mods", "layered_output", "emit_cargo_toml", "stdin"])]
+    file_tree: bool,
+    ///Prints the number of items, unresolved applications and lines of each context, largest first, instead of writing them
+    #[arg(long = "stats", conflicts_with_all = ["stdin", "incremental"])]
+    stats: bool,
+    ///Writes <fn>.trace.json next to each context, with the spans in the focal fn each call and type found by call_chain comes from
+    #[arg(long = "trace", conflicts_with = "stdin")]
+    trace: bool,
+    ///Leaves the items whose path starts with PREFIX (e.g. my_crate::bindings) out of every context, reported as unresolved with --report-unresolved (repeatable)
+    #[arg(long = "exclude", value_name = "PREFIX")]
+    excludes: Vec<String>,
+    ///Writes rfocxt/source_map.json with the file and lines in the crate of every item of every context
+    #[arg(long = "source-map", conflicts_with_all = ["reuse_context", "stdin"])]
+    source_map: bool,
+    ///Writes the items of dependency crates each context refers to, which it can't include, to rfocxt/external
+    #[arg(long = "report-external")]
+    report_external: bool,
+    ///Writes the items of std, core and alloc each context refers to, which are never part of it, to rfocxt/std
+    #[arg(long = "include-std")]
+    include_std: bool,
+    ///Writes the applications of each context that resolve to no item of the crate, e.g. derived impls or items of dependencies, to rfocxt/unresolved
+    #[arg(long = "report-unresolved")]
+    report_unresolved: bool,
+    ///Writes the time spent in each phase, and by call_chain per item kind, to rfocxt/profile.json
+    #[arg(long = "profile")]
+    profile: bool,
+    ///Writes rfocxt/deps.dot, a Graphviz graph of the items each written context references, coloured by kind
+    #[arg(long = "emit-graph", conflicts_with = "stdin")]
+    emit_graph: bool,
+    ///Writes all outputs to DIR instead of the rfocxt directory of the crate (or set RFOCXT_OUT_DIR)
+    #[arg(long = "out-dir", value_name = "DIR")]
+    out_dir: Option<String>,
+    ///Only rewrites the contexts whose content changed since the last incremental run, as recorded in rfocxt/.rfocxt_cache
+    #[arg(long = "incremental", conflicts_with = "stdin")]
+    incremental: bool,
+    ///Removes the output directory before the run, e.g. to drop contexts of fns that no longer exist
+    #[arg(long = "clean", conflicts_with_all = ["render_only", "stdin"])]
+    clean: bool,
+    ///Reuses the parsed crate in rfocxt/context.json of a previous run if none of its source files changed
+    #[arg(long = "reuse-context")]
+    reuse_context: bool,
+    ///Writes one context for a trait (e.g. my_crate::MyTrait) with all its impls, instead of per-function contexts
+    #[arg(long = "focal-trait", value_name = "PATH")]
+    focal_trait: Option<String>,
+    ///Writes one context for the impls of a type (e.g. my_crate::Foo) with all their fns, instead of per-function contexts
+    #[arg(
+        long = "focal-impl",
+        value_name = "PATH",
+        conflicts_with = "focal_trait"
+    )]
+    focal_impl: Option<String>,
+    ///Only takes the impls of this trait (e.g. my_crate::MyTrait) with --focal-impl
+    #[arg(long = "impl-trait", value_name = "PATH", requires = "focal_impl")]
+    impl_trait: Option<String>,
+}
+
+const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+// The boolean flags that can't be set in rfocxt.toml.
+const CLI_ONLY_FLAGS: [&str; 5] = ["stdin", "stdout", "no_config", "stats", "clean"];
+
+// Boolean flags take an optional value, so that e.g. `--bundle-mods=false` turns off an option
+// set in rfocxt.toml.
+fn cli_command() -> Command {
+    Cli::command().mut_args(|arg| {
+        if matches!(arg.get_action(), ArgAction::SetTrue)
+            && !CLI_ONLY_FLAGS.contains(&arg.get_id().as_str())
+        {
+            arg.action(ArgAction::Set)
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_value("false")
+                .default_missing_value("true")
+                .value_parser(value_parser!(bool))
+        } else {
+            arg
+        }
+    })
 }
 
 fn main() {
-    let cli = Cli::parse();
-    let input_crate_path = PathBuf::from(cli.crate_path);
-    let crate_path = fs::canonicalize(&input_crate_path).unwrap_or_else(|_err| {
-        eprintln!("The crate path {:?} doesn't exisit!", &input_crate_path);
-        process::exit(1)
-    });
-    run_call_chain(&crate_path);
+    let command = cli_command();
+    let matches = command.clone().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let (Some(crate_path), false) = (&cli.crate_path, cli.no_config) {
+        if let Some(config) = read_config(Path::new(crate_path)) {
+            cli = config
+                .apply(Path::new(crate_path), &command, &matches, env::args_os())
+                .unwrap_or_else(|err| {
+                    eprintln!(
+                        "The options of {:?} don't fit together with the command line:",
+                        Path::new(crate_path).join(CONFIG_FILE)
+                    );
+                    err.exit()
+                });
+        }
+    }
+    if cli.name_sep.is_empty() || cli.name_sep.contains(['/', '\\', '\0']) {
+        eprintln!(
+            "The name separator {:?} can't be used in file names! Use --nested-output for directories.",
+            &cli.name_sep
+        );
+        process::exit(2);
+    }
+    if let (Some(min_apps), Some(max_apps)) = (cli.min_apps, cli.max_apps) {
+        if min_apps > max_apps {
+            eprintln!(
+                "--min-apps {} is larger than --max-apps {}, no context would be written!",
+                min_apps, max_apps
+            );
+            process::exit(2);
+        }
+    }
+    if cli.bodies != BodyMode::Auto && (cli.elide_bodies || cli.max_depth.is_some()) {
+        eprintln!("--bodies full and --bodies signatures can't be combined with --elide-bodies or --max-depth!");
+        process::exit(2);
+    }
+    let crate_path = match &cli.crate_path {
+        Some(crate_path) => {
+            let input_crate_path = PathBuf::from(crate_path);
+            fs::canonicalize(&input_crate_path).unwrap_or_else(|_err| {
+                eprintln!("The crate path {:?} doesn't exisit!", &input_crate_path);
+                process::exit(1)
+            })
+        }
+        None => match env::var_os(STDIN_CRATE_VAR) {
+            Some(crate_path) => PathBuf::from(crate_path),
+            None => run_on_stdin_crate(&cli.edition),
+        },
+    };
+    let output_path = match cli
+        .out_dir
+        .clone()
+        .or_else(|| env::var("RFOCXT_OUT_DIR").ok())
+    {
+        Some(out_dir) => {
+            fs::create_dir_all(&out_dir).unwrap_or_else(|_err| {
+                eprintln!("Can not create the output directory {:?}!", &out_dir);
+                process::exit(1)
+            });
+            // call_chain runs in the crate directory, so the path has to be absolute.
+            fs::canonicalize(&out_dir).unwrap()
+        }
+        None => crate_path.join("rfocxt"),
+    };
+    // --render-only needs the analysis of the previous run, a stale cache is only ignored then.
+    let has_stale_cache = cli.incremental
+        && cli.render_only.is_none()
+        && CrateContext::has_stale_incremental_cache(&output_path);
+    if (cli.clean || has_stale_cache) && output_path.is_dir() {
+        if crate_path.starts_with(&output_path) {
+            eprintln!(
+                "The output directory {:?} contains the crate, it won't be removed!",
+                &output_path
+            );
+            process::exit(2);
+        }
+        if has_stale_cache && !cli.clean {
+            eprintln!("Warning: the incremental cache is from another version of rfocxt, removing all outputs");
+        }
+        fs::remove_dir_all(&output_path).unwrap();
+        fs::create_dir_all(&output_path).unwrap();
+    }
+    let mut phase_micros: Vec<(&str, u128)> = Vec::new();
+    let phase_start = Instant::now();
+    if cli.render_only.is_none() {
+        let log_level = if cli.quiet {
+            Some("off")
+        } else {
+            cli.log_level.as_deref()
+        };
+        run_call_chain(
+            &crate_path,
+            &output_path,
+            &CallChainEnv {
+                max_parallel_crates: cli.max_parallel_crates,
+                log_level,
+                resolve_opaque: cli.resolve_opaque,
+                profile: cli.profile,
+                keep_unwind: cli.keep_unwind,
+                focal_consts: cli.focal_consts,
+                trace: cli.trace,
+            },
+        );
+        phase_micros.push(("call_chain", phase_start.elapsed().as_micros()));
+    } else if !output_path.join("callsandtypes").is_dir() {
+        eprintln!(
+            "No previous analysis found in {:?}, run rfocxt without --render-only first!",
+            &output_path
+        );
+        process::exit(2);
+    }
 
-    let mut crate_context = CrateContext::new(&crate_path);
+    let mut options = ContextOptions::new();
+    options.nested_output = cli.nested_output;
+    options.render_only = cli.render_only.clone();
+    options.focal = cli.focal.clone();
+    options.only_kinds = cli.only_kinds.clone();
+    options.const_only = cli.const_only;
+    options.name_sep = cli.name_sep.clone();
+    options.verbatim_containers = cli.verbatim_containers;
+    options.emit_features = cli.emit_features;
+    options.inner_attrs = cli.inner_attrs.clone();
+    options.layered_output = cli.layered_output;
+    options.warn_fanout = cli.warn_fanout;
+    options.emit_summaries = cli.emit_summaries;
+    options.emit_meta = cli.emit_meta;
+    options.allow_trait_impls = cli.allow_trait_impls;
+    options.deny_trait_impls = cli.deny_trait_impls;
+    options.api_surface = cli.api_surface;
+    options.min_apps = cli.min_apps;
+    options.max_apps = cli.max_apps;
+    options.max_items_per_focal = cli.max_items_per_focal;
+    options.max_focals = cli.max_focals;
+    options.stub_all = cli.stub_all;
+    options.elide_bodies = cli.elide_bodies;
+    options.max_depth = cli.max_depth;
+    options.bodies = cli.bodies;
+    options.version_header = cli.version_header;
+    options.emit_cargo_toml = cli.emit_cargo_toml;
+    options.json_output = cli.json;
+    options.bundle_mods = cli.bundle_mods;
+    options.file_tree = cli.file_tree;
+    options.source_map = cli.source_map;
+    options.report_external = cli.report_external;
+    options.include_std = cli.include_std;
+    options.report_unresolved = cli.report_unresolved;
+    options.emit_graph = cli.emit_graph;
+    options.incremental = cli.incremental;
+    options.focal_consts = cli.focal_consts;
+    options.stats = cli.stats;
+    options.trace = cli.trace;
+    options.excludes = cli.excludes.clone();
 
-    crate_context.parse_crate();
-    crate_context.change_all_names();
+    let mut crate_context = CrateContext::new(&crate_path, &output_path);
+    crate_context.insert_options(&options);
+    if cli.incremental {
+        crate_context.load_incremental_cache();
+    }
+
+    let phase_start = Instant::now();
+    if !(cli.reuse_context && crate_context.load_context_cache()) {
+        crate_context.parse_crate();
+        phase_micros.push(("parse_crate", phase_start.elapsed().as_micros()));
+        let phase_start = Instant::now();
+        crate_context.change_all_names();
+        phase_micros.push(("change_all_names", phase_start.elapsed().as_micros()));
+        crate_context.write_context_cache();
+    } else {
+        phase_micros.push(("load_context_cache", phase_start.elapsed().as_micros()));
+    }
 
     let mut mod_trees: HashSet<String> = HashSet::new();
     crate_context.cout_all_mod_trees_in_on_file_for_test(&mut mod_trees);
@@ -51,18 +435,109 @@ fn main() {
 
     let mut fns: HashMap<String, FnData> = HashMap::new();
     let mut structs: HashMap<String, StructData> = HashMap::new();
+    let phase_start = Instant::now();
     crate_context.get_result(&mut fns, &mut structs);
+    phase_micros.push(("get_result", phase_start.elapsed().as_micros()));
     // println!("fns:\n{:#?}", fns);
     // println!("structs:\n{:#?}", structs);
-    let output_path = crate_path.join("rfocxt/result.txt");
-    fs::create_dir_all(output_path.parent().unwrap()).unwrap();
-    let mut file = File::create(&output_path).unwrap();
+    let result_path = output_path.join("result.txt");
+    fs::create_dir_all(&output_path).unwrap();
+    let mut file = File::create(&result_path).unwrap();
     file.write_all(format!("fns:\n{:#?}\n", fns).as_bytes())
         .unwrap();
     file.write_all(format!("structs:\n{:#?}", structs).as_bytes())
         .unwrap();
 
-    crate_context.parse_all_context(&mod_trees, &fns, &structs);
+    let phase_start = Instant::now();
+    let printed_contexts = if cli.stdin {
+        let contexts = match (&cli.focal_trait, &cli.focal_impl) {
+            (Some(focal_trait), _) => crate_context
+                .collect_trait_context(focal_trait, &mod_trees, &fns, &structs)
+                .unwrap_or_else(|| {
+                    eprintln!("The trait {} doesn't exist!", focal_trait);
+                    process::exit(3)
+                }),
+            (None, Some(focal_impl)) => crate_context
+                .collect_impl_context(
+                    focal_impl,
+                    cli.impl_trait.as_ref(),
+                    &mod_trees,
+                    &fns,
+                    &structs,
+                )
+                .unwrap_or_else(|| {
+                    eprintln!("The type {} doesn't exist!", focal_impl);
+                    process::exit(3)
+                }),
+            (None, None) => crate_context.collect_all_context(&mod_trees, &fns, &structs),
+        };
+        Some(contexts)
+    } else if cli.stdout {
+        Some(crate_context.collect_all_context(&mod_trees, &fns, &structs))
+    } else {
+        if let Some(focal_trait) = &cli.focal_trait {
+            if !crate_context.parse_trait_context(focal_trait, &mod_trees, &fns, &structs) {
+                eprintln!("The trait {} doesn't exist!", focal_trait);
+                process::exit(3);
+            }
+        } else if let Some(focal_impl) = &cli.focal_impl {
+            if !crate_context.parse_impl_context(
+                focal_impl,
+                cli.impl_trait.as_ref(),
+                &mod_trees,
+                &fns,
+                &structs,
+            ) {
+                eprintln!("The type {} doesn't exist!", focal_impl);
+                process::exit(3);
+            }
+        } else {
+            crate_context.parse_all_context(&mod_trees, &fns, &structs);
+        }
+        None
+    };
+    phase_micros.push(("render", phase_start.elapsed().as_micros()));
+    if let Some(focal) = &cli.focal {
+        if !crate_context.has_focal_match() {
+            eprintln!(
+                "The focal function {} doesn't exist! Names are like my_crate::foo or my_crate::{{impl#0}}::bar.",
+                focal
+            );
+            process::exit(3);
+        }
+    }
+    // A fn of the lib and one of the bin next to it may share their name.
+    if cli.stdout && crate_context.get_focal_match_count() > 1 {
+        eprintln!(
+            "The focal function {} matches {} functions, --stdout prints only one!",
+            cli.focal.as_ref().unwrap(),
+            crate_context.get_focal_match_count()
+        );
+        process::exit(3);
+    }
+    if cli.stats {
+        crate_context.report_stats();
+    } else if !cli.stdout {
+        crate_context.write_manifest();
+        crate_context.write_name_map();
+        crate_context.write_index();
+        if cli.incremental {
+            crate_context.write_incremental_cache();
+        }
+        if cli.source_map {
+            crate_context.write_source_map();
+        }
+        if cli.emit_graph {
+            crate_context.write_dependency_graph(&fns, &structs);
+        }
+    }
+    if cli.profile {
+        crate_context.write_profile(&phase_micros);
+    }
     crate_context.cout_in_one_file_for_test();
     crate_context.cout_complete_function_name_in_on_file_for_test();
+
+    if let Some(contexts) = printed_contexts {
+        print_contexts(&contexts, crate_context.get_options());
+    }
 }
@@ -1,4 +1,24 @@
-use std::{env, path::PathBuf, process::Command};
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs::{self, create_dir_all},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+use clap::{parser::ValueSource, ArgMatches, Command as ClapCommand, FromArgMatches, ValueEnum};
+use prettyplease::unparse;
+use serde::Deserialize;
+
+use crate::{
+    collect_context::{
+        crate_context::ContextFiles,
+        options::{BodyMode, ContextOptions, FocalKind},
+    },
+    Cli, LOG_LEVELS,
+};
 
 fn cargo_install() {
     let current_dir = env::current_dir().unwrap();
@@ -30,12 +50,63 @@ pub fn cargo_clean(work_path: &PathBuf) {
     }
 }
 
-fn call_chain(crate_path: &PathBuf) {
-    let call_chain_output = Command::new("cargo")
+// The settings of a call_chain run, which it reads from the environment.
+pub struct CallChainEnv<'a> {
+    pub max_parallel_crates: Option<usize>,
+    pub log_level: Option<&'a str>,
+    pub resolve_opaque: bool,
+    pub profile: bool,
+    pub keep_unwind: bool,
+    pub focal_consts: bool,
+    pub trace: bool,
+}
+
+fn call_chain(crate_path: &PathBuf, output_path: &PathBuf, call_chain_env: &CallChainEnv) {
+    let CallChainEnv {
+        max_parallel_crates,
+        log_level,
+        resolve_opaque,
+        profile,
+        keep_unwind,
+        focal_consts,
+        trace,
+    } = *call_chain_env;
+    let mut command = Command::new("cargo");
+    command
         .arg("call-chain")
         .current_dir(crate_path)
-        .output()
-        .expect("Failed to run call_chain");
+        .env("RFOCXT_OUT_DIR", output_path);
+    if let Some(max_parallel_crates) = max_parallel_crates {
+        command.env("CALL_CHAIN_MAX_PARALLEL", max_parallel_crates.to_string());
+    }
+    // The log of call_chain only shows when asked for, it is captured with the rest otherwise.
+    if let Some(log_level) = log_level {
+        command.env("CALL_CHAIN_LOG", log_level);
+        if log_level != "off" {
+            command.stderr(Stdio::inherit());
+        }
+    }
+    if resolve_opaque {
+        command.env("CALL_CHAIN_RESOLVE_OPAQUE", "1");
+    }
+    if keep_unwind {
+        command.env("CALL_CHAIN_KEEP_UNWIND", "1");
+    }
+    if focal_consts {
+        command.env("CALL_CHAIN_FOCAL_CONSTS", "1");
+    }
+    if trace {
+        command.env("CALL_CHAIN_TRACE", "1");
+    }
+    if profile {
+        // Profiles of an earlier run would be merged with this one's.
+        let profile_path = output_path.join("profile");
+        if profile_path.is_dir() {
+            fs::remove_dir_all(&profile_path).unwrap();
+        }
+        command.env("CALL_CHAIN_PROFILE", "1");
+    }
+    let call_chain_output = command.output().expect("Failed to run call_chain");
 
     if !call_chain_output.status.success() {
         eprintln!("Call_chain failed!");
@@ -43,8 +114,442 @@ fn call_chain(crate_path: &PathBuf) {
     }
 }
 
-pub fn run_call_chain(crate_path: &PathBuf) {
+pub fn run_call_chain(crate_path: &PathBuf, output_path: &PathBuf, call_chain_env: &CallChainEnv) {
     cargo_install();
     cargo_clean(crate_path);
-    call_chain(crate_path);
+    call_chain(crate_path, output_path, call_chain_env);
+}
+
+/// The run on a crate created by --stdin gets its path through this variable.
+pub const STDIN_CRATE_VAR: &str = "RFOCXT_STDIN_CRATE";
+
+/// The throwaway crate of --stdin, removed when it is dropped.
+pub struct StdinCrate {
+    crate_path: PathBuf,
+}
+
+impl StdinCrate {
+    pub fn get_crate_path(&self) -> &Path {
+        &self.crate_path
+    }
+}
+
+impl Drop for StdinCrate {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.crate_path);
+    }
+}
+
+// Piped source has no crate around it, so it gets a throwaway one pinned to the
+// toolchain call_chain is built with. It can't declare file modules or use dependencies.
+pub fn create_stdin_crate(edition: &str) -> StdinCrate {
+    let mut code = String::new();
+    io::stdin()
+        .read_to_string(&mut code)
+        .unwrap_or_else(|_err| {
+            eprintln!("Failed to read the source from stdin!");
+            process::exit(1)
+        });
+    let mut stdin_crate = StdinCrate {
+        crate_path: env::temp_dir().join(format!("rfocxt-stdin-{}", process::id())),
+    };
+    let crate_path = &stdin_crate.crate_path;
+    create_dir_all(crate_path.join("src")).unwrap();
+    fs::write(
+        crate_path.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"stdin\"\nversion = \"0.1.0\"\nedition = \"{}\"\n\n[workspace]\n",
+            edition
+        ),
+    )
+    .unwrap();
+    fs::write(
+        crate_path.join("rust-toolchain.toml"),
+        include_str!("../rust-toolchain.toml"),
+    )
+    .unwrap();
+    fs::write(crate_path.join("src/lib.rs"), code).unwrap();
+    stdin_crate.crate_path = crate_path.canonicalize().unwrap();
+    stdin_crate
+}
+
+// rfocxt exits in many places without unwinding, which would leave the crate of --stdin
+// behind, so it is analyzed by a second run of rfocxt, after which it is removed however
+// that run ended. Exits with the code of that run.
+pub fn run_on_stdin_crate(edition: &str) -> ! {
+    let stdin_crate = create_stdin_crate(edition);
+    let status = Command::new(env::current_exe().unwrap())
+        .args(env::args_os().skip(1))
+        .env(STDIN_CRATE_VAR, stdin_crate.get_crate_path())
+        .status()
+        .unwrap_or_else(|_err| {
+            eprintln!("Failed to run rfocxt on the source from stdin!");
+            process::exit(1)
+        });
+    drop(stdin_crate);
+    process::exit(status.code().unwrap_or(1))
+}
+
+pub fn print_contexts(contexts: &BTreeMap<String, ContextFiles>, options: &ContextOptions) {
+    for (complete_function_name, files) in contexts.iter() {
+        let file_name = options.file_name(complete_function_name);
+        for (layer, file) in files.iter() {
+            if options.layered_output {
+                println!("// {}.layer{}.rs", file_name, layer);
+            } else {
+                println!("// {}.rs", file_name);
+            }
+            println!("{}", unparse(file));
+        }
+    }
+}
+
+pub const CONFIG_FILE: &str = "rfocxt.toml";
+
+/// The options of the rfocxt.toml of a crate, named like the flags, e.g. `bundle-mods = true`,
+/// `max-depth = 2` or `exclude = ["my_crate::bindings"]`. The flags that pick what a single run
+/// does, like --focal or --clean, can only be given on the command line.
+///
+/// A value of the file is only used when the flag isn't given on the command line, nor one of
+/// the flags it conflicts with: the command line overrides the file field by field.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    nested_output: Option<bool>,
+    max_parallel_crates: Option<usize>,
+    log_level: Option<String>,
+    quiet: Option<bool>,
+    /// Replaced, not extended, by the kinds given with --only-kind.
+    #[serde(rename = "only-kind")]
+    only_kinds: Option<Vec<FocalKind>>,
+    focal_consts: Option<bool>,
+    const_only: Option<bool>,
+    name_sep: Option<String>,
+    verbatim_containers: Option<bool>,
+    emit_features: Option<bool>,
+    /// Replaced, not extended, by the names given with --inner-attrs.
+    inner_attrs: Option<Vec<String>>,
+    layered_output: Option<bool>,
+    warn_fanout: Option<usize>,
+    emit_summaries: Option<bool>,
+    emit_meta: Option<bool>,
+    /// Replaced, not extended, by the traits given with --allow-trait-impls.
+    allow_trait_impls: Option<Vec<String>>,
+    /// Replaced, not extended, by the traits given with --deny-trait-impls.
+    deny_trait_impls: Option<Vec<String>>,
+    resolve_opaque: Option<bool>,
+    keep_unwind: Option<bool>,
+    api_surface: Option<bool>,
+    min_apps: Option<usize>,
+    max_apps: Option<usize>,
+    max_items_per_focal: Option<usize>,
+    max_focals: Option<usize>,
+    stub_all: Option<bool>,
+    elide_bodies: Option<bool>,
+    max_depth: Option<usize>,
+    bodies: Option<BodyMode>,
+    version_header: Option<bool>,
+    emit_cargo_toml: Option<bool>,
+    json: Option<bool>,
+    bundle_mods: Option<bool>,
+    file_tree: Option<bool>,
+    trace: Option<bool>,
+    /// Replaced, not extended, by the prefixes given with --exclude.
+    #[serde(rename = "exclude")]
+    excludes: Option<Vec<String>>,
+    source_map: Option<bool>,
+    report_external: Option<bool>,
+    include_std: Option<bool>,
+    report_unresolved: Option<bool>,
+    profile: Option<bool>,
+    emit_graph: Option<bool>,
+    out_dir: Option<String>,
+    incremental: Option<bool>,
+    reuse_context: Option<bool>,
+}
+
+// How a value of rfocxt.toml is given on the command line, e.g. `--max-depth=2`. A `false`
+// is the default of a flag, so it isn't given.
+trait ConfigValue {
+    fn to_args(&self, long: &str) -> Vec<String>;
+}
+
+impl ConfigValue for bool {
+    fn to_args(&self, long: &str) -> Vec<String> {
+        if *self {
+            vec![format!("--{}", long)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+macro_rules! impl_config_value {
+    ($($value_type:ty),* $(,)?) => {
+        $(impl ConfigValue for $value_type {
+            fn to_args(&self, long: &str) -> Vec<String> {
+                vec![format!("--{}={}", long, self)]
+            }
+        })*
+    };
+}
+
+impl_config_value!(usize, String);
+
+macro_rules! impl_config_value_enum {
+    ($($value_type:ty),* $(,)?) => {
+        $(impl ConfigValue for $value_type {
+            fn to_args(&self, long: &str) -> Vec<String> {
+                vec![format!(
+                    "--{}={}",
+                    long,
+                    self.to_possible_value().unwrap().get_name()
+                )]
+            }
+        })*
+    };
+}
+
+impl_config_value_enum!(FocalKind, BodyMode);
+
+// A list is given one value at a time, like a repeated flag.
+impl<T: ConfigValue> ConfigValue for Vec<T> {
+    fn to_args(&self, long: &str) -> Vec<String> {
+        self.iter().flat_map(|value| value.to_args(long)).collect()
+    }
+}
+
+impl Config {
+    /// Sets the options of the file that the command line leaves unset. A `true` of the file is
+    /// turned off with e.g. `--bundle-mods=false`. The options of the file are parsed as flags
+    /// after the command line, so that clap checks that the merged options fit together, e.g.
+    /// that `json = true` isn't given with `emit-cargo-toml = true`. A relative `out-dir` is
+    /// relative to the crate.
+    pub fn apply<I, T>(
+        mut self,
+        crate_path: &Path,
+        command: &ClapCommand,
+        matches: &ArgMatches,
+        args: I,
+    ) -> Result<Cli, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        self.out_dir = self
+            .out_dir
+            .map(|out_dir| crate_path.join(out_dir).to_string_lossy().to_string());
+        let is_unset = |id: &str| !is_given(command, matches, id);
+        let long = |id: &str| {
+            command
+                .get_arguments()
+                .find(|arg| arg.get_id() == id)
+                .and_then(|arg| arg.get_long())
+                .unwrap()
+                .to_string()
+        };
+        let mut config_args: Vec<String> = Vec::new();
+        macro_rules! apply {
+            ($($field:ident),* $(,)?) => {
+                $(if let Some(value) = &self.$field {
+                    if is_unset(stringify!($field)) {
+                        config_args.extend(value.to_args(&long(stringify!($field))));
+                    }
+                })*
+            };
+        }
+        apply!(
+            nested_output,
+            max_parallel_crates,
+            log_level,
+            quiet,
+            only_kinds,
+            focal_consts,
+            const_only,
+            name_sep,
+            verbatim_containers,
+            emit_features,
+            inner_attrs,
+            layered_output,
+            warn_fanout,
+            emit_summaries,
+            emit_meta,
+            allow_trait_impls,
+            deny_trait_impls,
+            resolve_opaque,
+            keep_unwind,
+            api_surface,
+            min_apps,
+            max_apps,
+            max_items_per_focal,
+            max_focals,
+            stub_all,
+            elide_bodies,
+            max_depth,
+            bodies,
+            version_header,
+            emit_cargo_toml,
+            json,
+            bundle_mods,
+            file_tree,
+            trace,
+            excludes,
+            source_map,
+            report_external,
+            include_std,
+            report_unresolved,
+            profile,
+            emit_graph,
+            out_dir,
+            incremental,
+            reuse_context,
+        );
+        let merged_matches = command.clone().try_get_matches_from(
+            args.into_iter()
+                .map(Into::into)
+                .chain(config_args.into_iter().map(OsString::from)),
+        )?;
+        Cli::from_arg_matches(&merged_matches)
+    }
+}
+
+// A flag counts as given when it or a flag conflicting with it is on the command line, so that
+// the file yields to the command line instead of making the two conflict.
+fn is_given(command: &ClapCommand, matches: &ArgMatches, id: &str) -> bool {
+    let is_on_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if is_on_command_line(id) {
+        return true;
+    }
+    let Some(arg) = command.get_arguments().find(|arg| arg.get_id() == id) else {
+        return false;
+    };
+    command.get_arguments().any(|other| {
+        is_on_command_line(other.get_id().as_str())
+            && (command
+                .get_arg_conflicts_with(arg)
+                .iter()
+                .any(|conflict| conflict.get_id() == other.get_id())
+                || command
+                    .get_arg_conflicts_with(other)
+                    .iter()
+                    .any(|conflict| conflict.get_id() == id))
+    })
+}
+
+pub fn read_config(crate_path: &Path) -> Option<Config> {
+    let config_path = crate_path.join(CONFIG_FILE);
+    let contents = fs::read_to_string(&config_path).ok()?;
+    let config: Config = toml::from_str(&contents).unwrap_or_else(|err| {
+        eprintln!("Can not parse {:?}: {}", &config_path, err);
+        process::exit(2)
+    });
+    if let Some(log_level) = &config.log_level {
+        if !LOG_LEVELS.contains(&log_level.as_str()) {
+            eprintln!(
+                "The log-level {:?} in {:?} is not one of {}!",
+                log_level,
+                &config_path,
+                LOG_LEVELS.join(", ")
+            );
+            process::exit(2)
+        }
+    }
+    Some(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_command;
+
+    fn try_apply_config(config: &str, args: &[&str]) -> Result<Cli, clap::Error> {
+        let command = cli_command();
+        let args: Vec<&str> = ["rfocxt", "-c", "my_crate"]
+            .iter()
+            .chain(args)
+            .copied()
+            .collect();
+        let matches = command.clone().try_get_matches_from(&args).unwrap();
+        toml::from_str::<Config>(config).unwrap().apply(
+            Path::new("my_crate"),
+            &command,
+            &matches,
+            &args,
+        )
+    }
+
+    fn apply_config(config: &str, args: &[&str]) -> Cli {
+        try_apply_config(config, args).unwrap()
+    }
+
+    #[test]
+    fn config_fills_unset_flags() {
+        let cli = apply_config(
+            "bundle-mods = true\nmax-depth = 2\nbodies = \"full\"\nexclude = [\"a\"]",
+            &[],
+        );
+        assert!(cli.bundle_mods);
+        assert_eq!(cli.max_depth, Some(2));
+        assert_eq!(cli.bodies, BodyMode::Full);
+        assert_eq!(cli.excludes, vec!["a"]);
+    }
+
+    #[test]
+    fn command_line_replaces_config_lists() {
+        let cli = apply_config("exclude = [\"a\"]", &["--exclude", "b"]);
+        assert_eq!(cli.excludes, vec!["b"]);
+    }
+
+    #[test]
+    fn command_line_turns_off_config_flags() {
+        let cli = apply_config("bundle-mods = true", &["--bundle-mods=false"]);
+        assert!(!cli.bundle_mods);
+    }
+
+    #[test]
+    fn config_yields_to_conflicting_flags() {
+        let cli = apply_config("stub-all = true", &["--elide-bodies"]);
+        assert!(!cli.stub_all);
+        assert!(cli.elide_bodies);
+    }
+
+    #[test]
+    fn conflicting_config_options_are_rejected() {
+        assert!(try_apply_config("json = true\nemit-cargo-toml = true", &[]).is_err());
+        assert!(try_apply_config("stub-all = true\nmax-depth = 1", &[]).is_err());
+        assert!(try_apply_config("quiet = true\nlog-level = \"info\"", &[]).is_err());
+        assert!(try_apply_config("layered-output = true", &["--emit-cargo-toml"]).is_ok());
+        assert!(try_apply_config("layered-output = true\nemit-cargo-toml = true", &[]).is_err());
+        assert!(try_apply_config("json = true\nemit-cargo-toml = false", &[]).is_ok());
+    }
+
+    #[test]
+    fn config_out_dir_is_relative_to_the_crate() {
+        let cli = apply_config("out-dir = \"contexts\"", &[]);
+        assert_eq!(cli.out_dir, Some(String::from("my_crate/contexts")));
+        let cli = apply_config("out-dir = \"/tmp/contexts\"", &[]);
+        assert_eq!(cli.out_dir, Some(String::from("/tmp/contexts")));
+    }
+
+    #[test]
+    fn config_rejects_command_line_only_flags() {
+        assert!(toml::from_str::<Config>("focal = \"my_crate::foo\"").is_err());
+        assert!(toml::from_str::<Config>("stdin = true").is_err());
+    }
+
+    #[test]
+    fn read_config_of_crate() {
+        let crate_path = env::temp_dir().join(format!("rfocxt-config-{}", process::id()));
+        create_dir_all(&crate_path).unwrap();
+        assert!(read_config(&crate_path).is_none());
+        fs::write(
+            crate_path.join(CONFIG_FILE),
+            "max-depth = 1\nonly-kind = [\"trait-fn\"]",
+        )
+        .unwrap();
+        let config = read_config(&crate_path).unwrap();
+        fs::remove_dir_all(&crate_path).unwrap();
+        assert_eq!(config.max_depth, Some(1));
+        assert_eq!(config.only_kinds, Some(vec![FocalKind::TraitFn]));
+    }
 }
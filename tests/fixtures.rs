@@ -0,0 +1,360 @@
+// Fixtures are analyzed by call_chain, which needs the toolchain of rust-toolchain.toml with
+// rustc-dev, so they are ignored by default: `cargo test --test fixtures -- --ignored`.
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Tests run in parallel, sometimes on the same fixture, so each run gets a copy of its own.
+static RUN_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()));
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+    }
+}
+
+// Runs rfocxt on a copy of tests/fixtures/<fixture> and returns what it prints.
+fn rfocxt(fixture: &str, args: &[&str]) -> String {
+    let crate_path = std::env::temp_dir().join(format!(
+        "rfocxt-{}-{}-{}",
+        fixture,
+        std::process::id(),
+        RUN_COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = fs::remove_dir_all(&crate_path);
+    copy_dir(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(fixture),
+        &crate_path,
+    );
+    fs::write(
+        crate_path.join("rust-toolchain.toml"),
+        include_str!("../rust-toolchain.toml"),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rfocxt"))
+        .arg("--crate")
+        .arg(&crate_path)
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&crate_path).unwrap();
+    assert!(
+        output.status.success(),
+        "rfocxt failed on {}: {}",
+        fixture,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+#[ignore]
+fn focal_static_with_const_evaluated_length() {
+    let context = rfocxt(
+        "focal_consts",
+        &["--focal-consts", "--focal", "focal_consts::A", "--stdout"],
+    );
+    assert!(context.contains("pub static A"));
+}
+
+#[test]
+#[ignore]
+fn focal_const_calling_const_fn() {
+    let context = rfocxt(
+        "focal_consts",
+        &["--focal-consts", "--focal", "focal_consts::N", "--stdout"],
+    );
+    assert!(context.contains("const fn f()"));
+}
+
+#[test]
+#[ignore]
+fn focal_fn_with_body_local_const_and_struct() {
+    let context = rfocxt(
+        "nested_items",
+        &["--focal", "nested_items::focal", "--stdout"],
+    );
+    assert!(context.contains("struct Local"));
+    assert!(context.contains("const fn len()"));
+}
+
+#[test]
+#[ignore]
+fn attributes_and_field_visibilities_are_kept() {
+    let context = rfocxt(
+        "attributes",
+        &["--focal", "attributes::describe", "--stdout"],
+    );
+    assert_eq!(context.matches("#[non_exhaustive]").count(), 2);
+    assert!(context.contains("#[derive(Debug, Clone)]"));
+    assert!(context.contains("pub name: String"));
+    assert!(context.contains("pub(crate) id: u32"));
+    assert!(context.contains("\n    secret: u64"));
+    assert!(!context.contains("Doc comments"));
+}
+
+#[test]
+#[ignore]
+fn impl_lifetime_params_survive_method_isolation() {
+    let context = rfocxt(
+        "lifetimes",
+        &["--focal", "lifetimes::{impl#0}::get", "--stdout"],
+    );
+    assert!(context.contains("impl<'a> Lent<'a>"));
+    assert!(context.contains("pub fn get(&self) -> &'a Bar"));
+    assert!(!context.contains("fn unused"));
+}
+
+#[test]
+#[ignore]
+fn items_are_written_once_per_definition() {
+    let context = rfocxt(
+        "unique_items",
+        &["--focal", "unique_items::focal", "--stdout"],
+    );
+    assert_eq!(context.matches("pub struct Shared").count(), 1);
+    assert_eq!(context.matches("pub fn f12()").count(), 2);
+}
+
+#[test]
+#[ignore]
+fn reachability_counts_distinct_direct_applications() {
+    let out_dir = std::env::temp_dir().join(format!("rfocxt-out-{}", std::process::id()));
+    rfocxt(
+        "reachability",
+        &[
+            "--focal",
+            "reachability::focal",
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+        ],
+    );
+    let index: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("index.json")).unwrap()).unwrap();
+    fs::remove_dir_all(&out_dir).unwrap();
+    // `helper` is called twice, and `Hub` is reached as a type and through both its methods.
+    assert_eq!(
+        index[0]["reachability"],
+        serde_json::json!({
+            "reachability::Hub": 3,
+            "reachability::Hub::get": 1,
+            "reachability::Hub::new": 1,
+            "reachability::Leaf": 1,
+            "reachability::helper": 1,
+        })
+    );
+}
+
+#[test]
+#[ignore]
+fn stdin_crate_is_removed_after_a_failed_run() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rfocxt"))
+        .arg("--stdin")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let crate_path = std::env::temp_dir().join(format!("rfocxt-stdin-{}", child.id()));
+    std::io::Write::write_all(child.stdin.as_mut().unwrap(), b"pub fn f( {").unwrap();
+    drop(child.stdin.take());
+    let status = child.wait().unwrap();
+    assert!(!status.success());
+    assert!(!crate_path.exists());
+}
+
+#[test]
+#[ignore]
+fn elided_bodies_keep_constructors_of_the_self_type_only() {
+    let context = rfocxt(
+        "self_names",
+        &[
+            "--focal",
+            "self_names::b::{impl#0}::focal",
+            "--elide-bodies",
+            "--stdout",
+        ],
+    );
+    // `Handle` is an alias of the other `Widget`, `Shared` one of the self type.
+    assert!(context.contains("pub fn handle() -> Handle {}"));
+    assert!(context.contains("pub fn new() -> Self {\n        Widget\n    }"));
+    assert!(context.contains("pub fn shared() -> Shared {\n        Widget\n    }"));
+}
+
+#[test]
+#[ignore]
+fn bundled_bound_traits_keep_their_uses() {
+    let context = rfocxt(
+        "where_bounds",
+        &[
+            "--focal",
+            "where_bounds::user::focal",
+            "--bundle-mods",
+            "--stdout",
+        ],
+    );
+    // `Marker` is named through a use in `focal`, and through its path in `wrap`.
+    assert!(context.contains("pub trait Marker {}"));
+    assert!(context.contains("use crate::other_mod::Marker;"));
+    assert!(context.contains("T: crate::other_mod::Marker,"));
+}
+
+#[test]
+#[ignore]
+fn mutually_recursive_items_are_written_once() {
+    let context = rfocxt(
+        "mutual_recursion",
+        &["--focal", "mutual_recursion::{impl#0}::size", "--stdout"],
+    );
+    assert_eq!(context.matches("pub struct Tree").count(), 1);
+    assert_eq!(context.matches("pub struct Node").count(), 1);
+    assert_eq!(context.matches("self.children.iter()").count(), 1);
+    assert_eq!(context.matches("pub fn size(&self) -> usize").count(), 2);
+}
+
+#[test]
+#[ignore]
+fn turbofish_type_args_are_applications() {
+    let context = rfocxt("turbofish", &["--focal", "turbofish::focal", "--stdout"]);
+    assert!(context.contains("pub struct MyType;"));
+}
+
+#[test]
+#[ignore]
+fn array_length_consts_are_applications() {
+    let context = rfocxt("array_len", &["--focal", "array_len::focal", "--stdout"]);
+    assert!(context.contains("pub const BUFFER_SIZE: usize = 16;"));
+    assert!(context.contains("pub data: [u8; BUFFER_SIZE]"));
+}
+
+#[test]
+#[ignore]
+fn impl_associated_types_are_written() {
+    let context = rfocxt(
+        "impl_assoc_types",
+        &["--focal", "impl_assoc_types::focal", "--stdout"],
+    );
+    assert!(context.contains("impl Iterator for Foo"));
+    assert!(context.contains("type Item = u32;"));
+    assert!(context.contains("fn next(&mut self) -> Option<u32>"));
+}
+
+#[test]
+#[ignore]
+fn associated_type_defaults_keep_their_order() {
+    let context = rfocxt(
+        "assoc_type_defaults",
+        &["--focal", "assoc_type_defaults::focal", "--stdout"],
+    );
+    let a = context.find("type A;").unwrap();
+    let b = context.find("type B = Self::A;").unwrap();
+    assert!(a < b);
+    // `<P as Pair>::first` is resolved although `P` is also in the name of the trait.
+    assert!(context.contains("fn first(&self) -> Self::A;"));
+}
+
+#[test]
+#[ignore]
+fn trait_fns_keep_their_impl_trait_returns() {
+    let context = rfocxt("rpitit", &["--focal", "rpitit::focal", "--stdout"]);
+    assert!(context.contains("fn f(&self) -> impl Iterator<Item = Foo>;"));
+    assert!(context.contains("pub struct Foo;"));
+}
+
+#[test]
+#[ignore]
+fn upcast_trait_objects_bring_both_traits() {
+    let context = rfocxt("upcasting", &["--focal", "upcasting::focal", "--stdout"]);
+    assert!(context.contains("pub trait Sub: Super"));
+    assert!(context.contains("pub trait Super"));
+    assert!(context.contains("fn name(&self) -> &str;"));
+}
+
+#[test]
+#[ignore]
+fn method_calls_bring_their_impl_fn() {
+    let context = rfocxt(
+        "method_only",
+        &["--focal", "method_only::focal", "--stdout"],
+    );
+    assert!(context.contains("impl Gauge"));
+    assert!(context.contains("pub fn level(&self) -> u8"));
+    assert!(!context.contains("fn unused"));
+}
+
+#[test]
+#[ignore]
+fn awaited_fns_and_their_types_are_applications() {
+    let context = rfocxt(
+        "async_focal",
+        &["--focal", "async_focal::focal", "--stdout"],
+    );
+    assert!(context.contains("pub async fn load() -> Foo"));
+    assert!(context.contains("pub struct Foo;"));
+}
+
+#[test]
+#[ignore]
+fn trait_object_bindings_are_applications() {
+    let context = rfocxt(
+        "dyn_bindings",
+        &["--focal", "dyn_bindings::focal", "--stdout"],
+    );
+    assert!(context.contains("pub struct MyType;"));
+}
+
+#[test]
+#[ignore]
+fn item_less_impls_bring_their_header_traits() {
+    let context = rfocxt(
+        "marker_impl",
+        &["--focal", "marker_impl::focal", "--stdout"],
+    );
+    assert!(context.contains("impl<T: Bound> Marker for Wrapper<T> {}"));
+    assert!(context.contains("pub trait Bound {}"));
+    assert!(context.contains("pub trait Marker {}"));
+}
+
+#[test]
+#[ignore]
+fn for_loops_bring_the_into_iterator_impl() {
+    let context = rfocxt("for_loop", &["--focal", "for_loop::focal", "--stdout"]);
+    assert!(context.contains("impl IntoIterator for Bag"));
+    assert!(context.contains("fn into_iter(self) -> Self::IntoIter"));
+}
+
+#[test]
+#[ignore]
+fn every_deref_of_an_autoderef_chain_is_written() {
+    let context = rfocxt(
+        "deref_chain",
+        &["--focal", "deref_chain::focal", "--stdout"],
+    );
+    // The `Deref` of `Arc` is std's, the ones of the two wrappers behind it are local.
+    assert!(context.contains("impl<T> Deref for Hold<T>"));
+    assert!(context.contains("impl Deref for Outer"));
+    assert!(context.contains("pub fn method(&self) -> u32"));
+}
+
+#[test]
+#[ignore]
+fn combinator_args_are_applications() {
+    let context = rfocxt(
+        "combinators",
+        &["--focal", "combinators::focal", "--stdout"],
+    );
+    assert!(context.contains("impl From<Raw> for Foo"));
+    assert!(context.contains("pub struct Raw(pub u32);"));
+    assert!(context.contains("pub enum Error"));
+}
@@ -0,0 +1,9 @@
+pub const BUFFER_SIZE: usize = 16;
+
+pub struct Buffer {
+    pub data: [u8; BUFFER_SIZE],
+}
+
+pub fn focal(buffer: &Buffer) -> u8 {
+    buffer.data[0]
+}
@@ -0,0 +1,12 @@
+#![feature(associated_type_defaults)]
+
+pub trait Pair {
+    type A;
+    type B = Self::A;
+
+    fn first(&self) -> Self::A;
+}
+
+pub fn focal<P: Pair>(pair: &P) -> P::A {
+    pair.first()
+}
@@ -0,0 +1,10 @@
+pub struct Foo;
+
+pub async fn load() -> Foo {
+    Foo
+}
+
+pub async fn focal() -> usize {
+    let _foo = load().await;
+    0
+}
@@ -0,0 +1,19 @@
+/// Doc comments are the only attributes left out of a context.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Config {
+    pub name: String,
+    pub(crate) id: u32,
+    secret: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum Mode {
+    Fast,
+    Slow,
+}
+
+pub fn describe(config: &Config, mode: Mode) -> String {
+    format!("{} {} {} {:?}", config.name, config.id, config.secret, mode)
+}
@@ -0,0 +1,18 @@
+pub struct Raw(pub u32);
+
+pub struct Foo(pub u32);
+
+impl From<Raw> for Foo {
+    fn from(raw: Raw) -> Self {
+        Foo(raw.0)
+    }
+}
+
+pub enum Error {
+    Missing,
+}
+
+pub fn focal(opt: Option<Raw>) -> Result<u32, Error> {
+    let foo = opt.map(Foo::from).ok_or(Error::Missing)?;
+    Ok(foo.0)
+}
@@ -0,0 +1,36 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+pub struct Concrete {
+    n: u32,
+}
+
+impl Concrete {
+    pub fn method(&self) -> u32 {
+        self.n
+    }
+}
+
+pub struct Outer(Concrete);
+
+impl Deref for Outer {
+    type Target = Concrete;
+
+    fn deref(&self) -> &Concrete {
+        &self.0
+    }
+}
+
+pub struct Hold<T>(T);
+
+impl<T> Deref for Hold<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+pub fn focal(a: Arc<Hold<Outer>>) -> u32 {
+    a.method()
+}
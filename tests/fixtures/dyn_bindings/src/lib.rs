@@ -0,0 +1,5 @@
+pub struct MyType;
+
+pub fn focal(items: &mut dyn Iterator<Item = MyType>) -> bool {
+    items.next().is_some()
+}
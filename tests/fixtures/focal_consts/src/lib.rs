@@ -0,0 +1,8 @@
+// The type of A const-evaluates N, which steals the mir_built of N before it is visited.
+pub static A: [u8; N] = [0; N];
+
+pub const N: usize = f();
+
+const fn f() -> usize {
+    4
+}
@@ -0,0 +1,20 @@
+pub struct Bag {
+    items: Vec<u32>,
+}
+
+impl IntoIterator for Bag {
+    type Item = u32;
+    type IntoIter = std::vec::IntoIter<u32>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+pub fn focal(bag: Bag) -> u32 {
+    let mut total = 0;
+    for item in bag {
+        total += item;
+    }
+    total
+}
@@ -0,0 +1,16 @@
+pub struct Foo {
+    pub n: u32,
+}
+
+impl Iterator for Foo {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        self.n += 1;
+        Some(self.n)
+    }
+}
+
+pub fn focal(foo: &mut Foo) -> Option<u32> {
+    foo.next()
+}
@@ -0,0 +1,15 @@
+pub struct Bar;
+
+pub struct Lent<'a> {
+    bar: &'a Bar,
+}
+
+impl<'a> Lent<'a> {
+    pub fn get(&self) -> &'a Bar {
+        self.bar
+    }
+
+    pub fn unused(&self) -> usize {
+        0
+    }
+}
@@ -0,0 +1,15 @@
+pub trait Bound {}
+
+pub trait Marker {}
+
+pub struct Wrapper<T>(pub T);
+
+impl<T: Bound> Marker for Wrapper<T> {}
+
+pub fn needs_marker<M: Marker>(marked: M) -> M {
+    marked
+}
+
+pub fn focal<T: Bound>(value: T) -> Wrapper<T> {
+    needs_marker(Wrapper(value))
+}
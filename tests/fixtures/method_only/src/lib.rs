@@ -0,0 +1,17 @@
+pub struct Gauge {
+    level: u8,
+}
+
+impl Gauge {
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn unused(&self) -> u8 {
+        0
+    }
+}
+
+pub fn focal(x: &Gauge) -> u8 {
+    x.level()
+}
@@ -0,0 +1,19 @@
+pub struct Tree {
+    pub children: Vec<Node>,
+}
+
+pub struct Node {
+    pub tree: Option<Box<Tree>>,
+}
+
+impl Tree {
+    pub fn size(&self) -> usize {
+        self.children.iter().map(|node| node.size()).sum()
+    }
+}
+
+impl Node {
+    pub fn size(&self) -> usize {
+        1 + self.tree.as_ref().map_or(0, |tree| tree.size())
+    }
+}
@@ -0,0 +1,13 @@
+// The array lengths const-evaluate X, which steals the mir_built of X before it is merged.
+pub fn focal() -> usize {
+    struct Local {
+        buf: [u8; X],
+    }
+    const X: usize = len();
+    let local = Local { buf: [0; X] };
+    local.buf.len()
+}
+
+const fn len() -> usize {
+    8
+}
@@ -0,0 +1,24 @@
+pub struct Hub {
+    n: u32,
+}
+
+impl Hub {
+    pub fn new() -> Self {
+        Hub { n: 1 }
+    }
+
+    pub fn get(&self) -> u32 {
+        self.n
+    }
+}
+
+pub struct Leaf;
+
+pub fn helper(_leaf: &Leaf) -> u32 {
+    1
+}
+
+pub fn focal() -> u32 {
+    let hub = Hub::new();
+    hub.get() + helper(&Leaf) + helper(&Leaf)
+}
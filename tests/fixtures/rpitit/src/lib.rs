@@ -0,0 +1,9 @@
+pub struct Foo;
+
+pub trait T {
+    fn f(&self) -> impl Iterator<Item = Foo>;
+}
+
+pub fn focal<X: T>(x: &X) -> usize {
+    x.f().count()
+}
@@ -0,0 +1,32 @@
+pub mod a {
+    pub struct Widget;
+
+    pub type Handle = Widget;
+}
+
+pub mod b {
+    use crate::a::Handle;
+
+    pub struct Widget;
+
+    pub type Shared = Widget;
+
+    impl Widget {
+        pub fn new() -> Self {
+            Widget
+        }
+
+        pub fn shared() -> Shared {
+            Widget
+        }
+
+        pub fn handle() -> Handle {
+            crate::a::Widget
+        }
+
+        pub fn focal(&self) -> usize {
+            let _ = (Self::new(), Self::shared(), Self::handle());
+            0
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub struct MyType;
+
+pub fn focal() -> usize {
+    Vec::<MyType>::new().len()
+}
@@ -0,0 +1,27 @@
+// Shared is reachable by its path and by a re-export, the two f12 are distinct items.
+mod inner {
+    pub struct Shared;
+
+    pub fn make() -> Shared {
+        Shared
+    }
+}
+
+pub use inner::Shared;
+
+pub mod m6 {
+    pub fn f12() -> u8 {
+        6
+    }
+}
+
+pub mod m7 {
+    pub fn f12() -> u8 {
+        7
+    }
+}
+
+pub fn focal(shared: Shared) -> (inner::Shared, u8) {
+    let _ = shared;
+    (inner::make(), m6::f12() + m7::f12())
+}
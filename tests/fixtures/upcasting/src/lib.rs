@@ -0,0 +1,14 @@
+#![feature(trait_upcasting)]
+
+pub trait Super {
+    fn name(&self) -> &str;
+}
+
+pub trait Sub: Super {
+    fn id(&self) -> u32;
+}
+
+pub fn focal(x: &dyn Sub) -> usize {
+    let upcast: &dyn Super = x;
+    upcast.name().len()
+}
@@ -0,0 +1,20 @@
+pub mod other_mod {
+    pub trait Marker {}
+}
+
+pub mod user {
+    use crate::other_mod::Marker;
+
+    pub struct Holder<T>(pub T);
+
+    pub fn wrap<T>(value: T) -> Holder<T>
+    where
+        T: crate::other_mod::Marker,
+    {
+        Holder(value)
+    }
+
+    pub fn focal<T: Marker>(value: T) -> Holder<T> {
+        wrap(value)
+    }
+}